@@ -0,0 +1,47 @@
+//! The Keccak permutation and SHAKE absorb/squeeze on their own.
+//!
+//!     cargo bench --bench keccak
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+#[allow(dead_code, unused_imports, clippy::all)]
+#[path = "../src/fips202.rs"]
+mod fips202;
+
+use fips202::*;
+
+fn bench_keccak(c: &mut Criterion) {
+    c.bench_function("keccakf1600", |b| {
+        let mut s = [0u64; 25];
+        b.iter(|| keccakf1600_statepermute(black_box(&mut s)))
+    });
+
+    let input = vec![0x3cu8; 1024];
+    let mut group = c.benchmark_group("shake");
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("shake256_absorb_1k", |b| {
+        b.iter(|| {
+            let mut state = KeccakState::default();
+            shake256_absorb(&mut state, black_box(&input), input.len());
+            shake256_finalize(&mut state);
+            state
+        })
+    });
+    group.bench_function("shake128_squeeze_1k", |b| {
+        let mut out = vec![0u8; 8 * SHAKE128_RATE];
+        b.iter(|| {
+            let mut state = KeccakState::default();
+            shake128_absorb(&mut state, &input[..34], 34);
+            shake128_finalize(&mut state);
+            shake128_squeezeblocks(black_box(&mut out), 8, &mut state);
+        })
+    });
+    group.bench_function("shake256_1k", |b| {
+        let mut out = vec![0u8; 1024];
+        b.iter(|| shake256(black_box(&mut out), 1024, &input, input.len()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_keccak);
+criterion_main!(benches);
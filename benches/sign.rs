@@ -0,0 +1,45 @@
+//! Key generation, signing and verification for each parameter set.
+//!
+//!     cargo bench --bench sign
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use dilithium_lib::{Dilithium2, Dilithium3, Dilithium5, ParameterSet};
+
+/// Sign with a masked key, split once outside the measurement.
+macro_rules! bench_masked {
+    ($group:expr, $alg:ty, $msg:expr, $sk:expr) => {{
+        let mut msk = <$alg>::mask_secret_key($sk.clone()).unwrap();
+        $group.bench_function("sign_masked", |b| {
+            b.iter(|| <$alg>::sign_masked(black_box($msg.clone()), &mut msk, None, false).unwrap())
+        });
+    }};
+}
+
+fn bench_sign(c: &mut Criterion) {
+    let msg = vec![0x5au8; 64];
+    for ps in [ParameterSet::MlDsa44, ParameterSet::MlDsa65, ParameterSet::MlDsa87] {
+        let mut group = c.benchmark_group(ps.name());
+        let zeta = Some(vec![7u8; 32]);
+        group.bench_function("keygen", |b| b.iter(|| ps.key_gen(black_box(zeta.clone())).unwrap()));
+
+        let (sk, pk) = ps.key_gen(zeta.clone()).unwrap();
+        group.bench_function("sign", |b| {
+            b.iter(|| ps.sign(black_box(msg.clone()), sk.clone(), None, false).unwrap())
+        });
+
+        match ps {
+            ParameterSet::MlDsa44 => bench_masked!(group, Dilithium2, msg, sk),
+            ParameterSet::MlDsa65 => bench_masked!(group, Dilithium3, msg, sk),
+            ParameterSet::MlDsa87 => bench_masked!(group, Dilithium5, msg, sk),
+        }
+
+        let sig = ps.sign(msg.clone(), sk.clone(), None, false).unwrap();
+        group.bench_function("verify", |b| {
+            b.iter(|| ps.verify(black_box(msg.clone()), sig.clone(), pk.clone(), None).unwrap())
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bench_sign);
+criterion_main!(benches);
@@ -0,0 +1,19 @@
+//! Write the NIST PQC known-answer files for all parameter sets.
+//!
+//!     cargo run --release --example kat [output directory]
+
+use std::path::PathBuf;
+
+use dilithium_lib::{generate_kat, ParameterSet};
+
+fn main() -> anyhow::Result<()> {
+    let dir = PathBuf::from(std::env::args().nth(1).unwrap_or_else(|| ".".into()));
+    for ps in [ParameterSet::MlDsa44, ParameterSet::MlDsa65, ParameterSet::MlDsa87] {
+        let (req, rsp) = generate_kat(ps, 100)?;
+        let name = format!("PQCsignKAT_{}", ps.sizes().1);
+        std::fs::write(dir.join(format!("{}.req", name)), req)?;
+        std::fs::write(dir.join(format!("{}.rsp", name)), rsp)?;
+        println!("{}: {}.rsp", ps.name(), name);
+    }
+    Ok(())
+}
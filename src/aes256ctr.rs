@@ -1,8 +1,51 @@
 pub const AES256CTR_BLOCKBYTES: usize = 64;
 
+/// Which AES implementation a context uses. All of them produce the same
+/// keystream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AesImpl {
+  /// Constant-time bitsliced code, available everywhere.
+  Bitsliced,
+  /// AES-NI, four blocks in parallel.
+  AesNi,
+  /// VAES with AVX2, two blocks per register.
+  Vaes,
+}
+
+impl AesImpl {
+  /// The fastest implementation the CPU supports.
+  pub fn detect() -> Self {
+    #[cfg(target_arch = "x86_64")]
+    {
+      if crate::aesni::has_vaes() {
+        return AesImpl::Vaes;
+      }
+      if crate::aesni::has_aesni() {
+        return AesImpl::AesNi;
+      }
+    }
+    AesImpl::Bitsliced
+  }
+
+  pub fn is_available(self) -> bool {
+    match self {
+      AesImpl::Bitsliced => true,
+      #[cfg(target_arch = "x86_64")]
+      AesImpl::AesNi => crate::aesni::has_aesni(),
+      #[cfg(target_arch = "x86_64")]
+      AesImpl::Vaes => crate::aesni::has_vaes(),
+      #[cfg(not(target_arch = "x86_64"))]
+      _ => false,
+    }
+  }
+}
+
 pub struct Aes256ctrCtx {
   pub sk_exp: [u64; 120],
   pub ivw: [u32; 16],
+  /// Round keys for the hardware implementations.
+  pub rk: [[u8; 16]; 15],
+  pub imp: AesImpl,
 }
 
 impl Default for Aes256ctrCtx {
@@ -10,6 +53,8 @@ impl Default for Aes256ctrCtx {
     Self {
       sk_exp: [0u64; 120],
       ivw: [0u32; 16],
+      rk: [[0u8; 16]; 15],
+      imp: AesImpl::Bitsliced,
     }
   }
 }
@@ -434,9 +479,8 @@ fn inc4_be(x: u32) -> u32 {
   br_swap32(t)
 }
 
-fn aes_ctr4x(out: &mut [u8], ivw: &mut [u32], sk_exp: &[u64]) {
-  let mut w = [0u32; 16];
-  w.copy_from_slice(&ivw);
+/// Encrypt four blocks in place, given as little-endian words.
+fn aes_encrypt4x(w: &mut [u32; 16], sk_exp: &[u64]) {
   let mut q = [0u64; 8];
   let (q0, q1) = q.split_at_mut(4);
   for i in 0..4 {
@@ -459,6 +503,12 @@ fn aes_ctr4x(out: &mut [u8], ivw: &mut [u32], sk_exp: &[u64]) {
   for i in 0..4 {
     br_aes_ct64_interleave_out(&mut w[(i << 2)..], q[i], q[i + 4]);
   }
+}
+
+fn aes_ctr4x(out: &mut [u8], ivw: &mut [u32], sk_exp: &[u64]) {
+  let mut w = [0u32; 16];
+  w.copy_from_slice(ivw);
+  aes_encrypt4x(&mut w, sk_exp);
   br_range_enc32le(out, &w, 16);
 
   // Increase counter for next 4 blocks
@@ -474,8 +524,40 @@ fn br_aes_ct64_ctr_init(sk_exp: &mut [u64], key: &[u8]) {
   br_aes_ct64_skey_expand(sk_exp, &skey);
 }
 
+/// Expand a 32 byte key for `aes256_encrypt_block`.
+pub fn aes256_key_expand(sk_exp: &mut [u64; 120], key: &[u8]) {
+  br_aes_ct64_ctr_init(sk_exp, key);
+}
+
+/// Encrypt a single 16 byte block (AES-256 in ECB mode).
+pub fn aes256_encrypt_block(out: &mut [u8; 16], input: &[u8; 16], sk_exp: &[u64; 120]) {
+  let mut w = [0u32; 16];
+  br_range_dec32le(&mut w, 4, input);
+  aes_encrypt4x(&mut w, sk_exp);
+  br_range_enc32le(out, &w, 4);
+}
+
 pub fn aes256ctr_init(s: &mut Aes256ctrCtx, key: &[u8], nonce: [u8; 12]) {
-  br_aes_ct64_ctr_init(&mut s.sk_exp, &key);
+  aes256ctr_init_with(s, key, nonce, AesImpl::detect());
+}
+
+/// `aes256ctr_init` with a fixed implementation, which must be available.
+pub fn aes256ctr_init_with(
+  s: &mut Aes256ctrCtx,
+  key: &[u8],
+  nonce: [u8; 12],
+  imp: AesImpl,
+) {
+  assert!(imp.is_available(), "{:?} is not supported by this CPU", imp);
+  s.imp = imp;
+  match imp {
+    AesImpl::Bitsliced => br_aes_ct64_ctr_init(&mut s.sk_exp, key),
+    // SAFETY: AES-NI support was checked above
+    #[cfg(target_arch = "x86_64")]
+    _ => unsafe { crate::aesni::key_expand(&mut s.rk, &key[..32]) },
+    #[cfg(not(target_arch = "x86_64"))]
+    _ => unreachable!(),
+  }
   br_range_dec32le(&mut s.ivw, 3, &nonce);
   let mut slice = [0u32; 3];
   slice.copy_from_slice(&s.ivw[..3]);
@@ -493,6 +575,25 @@ pub fn aes256ctr_squeezeblocks(
   mut nblocks: u64,
   s: &mut Aes256ctrCtx,
 ) {
+  #[cfg(target_arch = "x86_64")]
+  if s.imp != AesImpl::Bitsliced {
+    let mut nonce = [0u8; 12];
+    br_range_enc32le(&mut nonce, &s.ivw, 3);
+    let ctr = br_swap32(s.ivw[3]);
+    let n = nblocks as usize;
+    // SAFETY: the implementation was checked in aes256ctr_init_with
+    unsafe {
+      match s.imp {
+        AesImpl::Vaes => crate::aesni::ctr4x_vaes(out, &s.rk, &nonce, ctr, n),
+        _ => crate::aesni::ctr4x(out, &s.rk, &nonce, ctr, n),
+      }
+    }
+    for i in 0..4 {
+      s.ivw[4 * i + 3] = br_swap32(ctr.wrapping_add(4 * nblocks as u32 + i as u32));
+    }
+    return;
+  }
+
   let mut idx = 0;
   while nblocks > 0 {
     aes_ctr4x(&mut out[idx..], &mut s.ivw, &s.sk_exp);
@@ -500,3 +601,36 @@ pub fn aes256ctr_squeezeblocks(
     nblocks -= 1;
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn keystream(imp: AesImpl, key: &[u8], nonce: [u8; 12], chunks: &[u64]) -> Vec<u8> {
+    let mut s = Aes256ctrCtx::default();
+    aes256ctr_init_with(&mut s, key, nonce, imp);
+    let mut out = Vec::new();
+    for &n in chunks {
+      let mut buf = vec![0u8; 64 * n as usize];
+      aes256ctr_squeezeblocks(&mut buf, n, &mut s);
+      out.extend(buf);
+    }
+    out
+  }
+
+  #[test]
+  fn implementations_agree() {
+    let imps = [AesImpl::AesNi, AesImpl::Vaes];
+    for t in 0u8..8 {
+      let key: Vec<u8> = (0..32).map(|i: u8| i.wrapping_mul(7).wrapping_add(t.wrapping_mul(31))).collect();
+      let mut nonce = [0u8; 12];
+      nonce[0] = t;
+      nonce[11] = 0xff - t;
+      let chunks = [1, 3, 0, 5, 2];
+      let expected = keystream(AesImpl::Bitsliced, &key, nonce, &chunks);
+      for imp in imps.into_iter().filter(|imp| imp.is_available()) {
+        assert_eq!(keystream(imp, &key, nonce, &chunks), expected, "{:?}", imp);
+      }
+    }
+  }
+}
@@ -0,0 +1,137 @@
+//! AES-256-CTR with the AES-NI and VAES instructions.
+//!
+//! Produces the same keystream as the bitsliced code in aes256ctr.rs: the
+//! counter block is the 12 byte nonce followed by a 32 bit big-endian block
+//! counter. Callers must check `has_aesni`/`has_vaes` first.
+
+use std::arch::x86_64::*;
+
+pub fn has_aesni() -> bool {
+  is_x86_feature_detected!("aes") && is_x86_feature_detected!("sse4.1")
+}
+
+pub fn has_vaes() -> bool {
+  has_aesni() && is_x86_feature_detected!("vaes") && is_x86_feature_detected!("avx2")
+}
+
+#[target_feature(enable = "sse2")]
+fn expand_assist_1(mut t1: __m128i, t2: __m128i) -> __m128i {
+  let mut t4 = _mm_slli_si128::<4>(t1);
+  t1 = _mm_xor_si128(t1, t4);
+  t4 = _mm_slli_si128::<4>(t4);
+  t1 = _mm_xor_si128(t1, t4);
+  t4 = _mm_slli_si128::<4>(t4);
+  t1 = _mm_xor_si128(t1, t4);
+  _mm_xor_si128(t1, _mm_shuffle_epi32::<0xff>(t2))
+}
+
+#[target_feature(enable = "aes")]
+fn expand_assist_2(t1: __m128i, mut t3: __m128i) -> __m128i {
+  let t2 = _mm_shuffle_epi32::<0xaa>(_mm_aeskeygenassist_si128::<0>(t1));
+  let mut t4 = _mm_slli_si128::<4>(t3);
+  t3 = _mm_xor_si128(t3, t4);
+  t4 = _mm_slli_si128::<4>(t4);
+  t3 = _mm_xor_si128(t3, t4);
+  t4 = _mm_slli_si128::<4>(t4);
+  t3 = _mm_xor_si128(t3, t4);
+  _mm_xor_si128(t3, t2)
+}
+
+/// AES-256 key schedule, 15 round keys.
+#[target_feature(enable = "aes")]
+pub fn key_expand(rk: &mut [[u8; 16]; 15], key: &[u8]) {
+  let mut k = [_mm_setzero_si128(); 15];
+  // SAFETY: key is at least 32 bytes, unaligned loads
+  let (mut t1, mut t3) = unsafe {
+    (_mm_loadu_si128(key.as_ptr() as *const __m128i), _mm_loadu_si128(key[16..32].as_ptr() as *const __m128i))
+  };
+  k[0] = t1;
+  k[1] = t3;
+  macro_rules! round {
+    ($i:expr, $rcon:expr) => {
+      t1 = expand_assist_1(t1, _mm_aeskeygenassist_si128::<$rcon>(t3));
+      k[$i] = t1;
+      t3 = expand_assist_2(t1, t3);
+      k[$i + 1] = t3;
+    };
+  }
+  round!(2, 0x01);
+  round!(4, 0x02);
+  round!(6, 0x04);
+  round!(8, 0x08);
+  round!(10, 0x10);
+  round!(12, 0x20);
+  k[14] = expand_assist_1(t1, _mm_aeskeygenassist_si128::<0x40>(t3));
+  for (out, k) in rk.iter_mut().zip(k) {
+    // SAFETY: out is 16 bytes
+    unsafe { _mm_storeu_si128(out.as_mut_ptr() as *mut __m128i, k) };
+  }
+}
+
+fn load_keys(rk: &[[u8; 16]; 15]) -> [__m128i; 15] {
+  // SAFETY: every round key is 16 bytes
+  rk.map(|k| unsafe { _mm_loadu_si128(k.as_ptr() as *const __m128i) })
+}
+
+/// Counter block for block `ctr` as little-endian words.
+#[target_feature(enable = "sse4.1")]
+fn counter_block(nonce: __m128i, ctr: u32) -> __m128i {
+  _mm_insert_epi32::<3>(nonce, ctr.swap_bytes() as i32)
+}
+
+/// Write `nblocks` groups of four keystream blocks, starting at block `ctr`.
+#[target_feature(enable = "aes,sse4.1")]
+pub fn ctr4x(out: &mut [u8], rk: &[[u8; 16]; 15], nonce: &[u8; 12], mut ctr: u32, nblocks: usize) {
+  let k = load_keys(rk);
+  let mut n = [0u8; 16];
+  n[..12].copy_from_slice(nonce);
+  // SAFETY: n is 16 bytes
+  let nonce = unsafe { _mm_loadu_si128(n.as_ptr() as *const __m128i) };
+
+  for chunk in out[..64 * nblocks].chunks_exact_mut(64) {
+    let mut b = [0u32; 4].map(|_| {
+      let block = _mm_xor_si128(counter_block(nonce, ctr), k[0]);
+      ctr = ctr.wrapping_add(1);
+      block
+    });
+    for key in &k[1..14] {
+      for x in b.iter_mut() {
+        *x = _mm_aesenc_si128(*x, *key);
+      }
+    }
+    for (i, x) in b.iter_mut().enumerate() {
+      *x = _mm_aesenclast_si128(*x, k[14]);
+      // SAFETY: chunk is 64 bytes
+      unsafe { _mm_storeu_si128(chunk[16 * i..].as_mut_ptr() as *mut __m128i, *x) };
+    }
+  }
+}
+
+/// Same as `ctr4x`, two blocks per 256 bit register.
+#[target_feature(enable = "vaes,avx2,aes,sse4.1")]
+pub fn ctr4x_vaes(out: &mut [u8], rk: &[[u8; 16]; 15], nonce: &[u8; 12], mut ctr: u32, nblocks: usize) {
+  let k = load_keys(rk).map(|k| _mm256_broadcastsi128_si256(k));
+  let mut n = [0u8; 16];
+  n[..12].copy_from_slice(nonce);
+  // SAFETY: n is 16 bytes
+  let nonce = unsafe { _mm_loadu_si128(n.as_ptr() as *const __m128i) };
+
+  for chunk in out[..64 * nblocks].chunks_exact_mut(64) {
+    let mut b = [0u32; 2].map(|_| {
+      let lo = counter_block(nonce, ctr);
+      let hi = counter_block(nonce, ctr.wrapping_add(1));
+      ctr = ctr.wrapping_add(2);
+      _mm256_xor_si256(_mm256_set_m128i(hi, lo), k[0])
+    });
+    for key in &k[1..14] {
+      for x in b.iter_mut() {
+        *x = _mm256_aesenc_epi128(*x, *key);
+      }
+    }
+    for (i, x) in b.iter_mut().enumerate() {
+      *x = _mm256_aesenclast_epi128(*x, k[14]);
+      // SAFETY: chunk is 64 bytes
+      unsafe { _mm256_storeu_si256(chunk[32 * i..].as_mut_ptr() as *mut __m256i, *x) };
+    }
+  }
+}
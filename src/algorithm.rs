@@ -1,6 +1,161 @@
+use std::marker::PhantomData;
+
+use anyhow::bail;
+
 use crate::api::SignError;
-use crate::sign::{crypto_sign_keypair, crypto_sign_signature, crypto_sign_verify};
+use crate::asn1::{ID_ML_DSA_44, ID_ML_DSA_65, ID_ML_DSA_87};
+use crate::lowmem::{crypto_sign_signature_lowmem, crypto_sign_verify_lowmem};
+use crate::masked::{crypto_sign_signature_masked, MaskedSecretKey};
+use crate::params::{Params, CRHBYTES, RNDBYTES, TRBYTES};
+use crate::prehash::{prefix, PreHashAlgorithm};
+use crate::sign::{
+    crypto_sign_keypair, crypto_sign_signature, crypto_sign_signature_internal, crypto_sign_signature_mu,
+    crypto_sign_verify, crypto_sign_verify_internal, crypto_sign_verify_mu,
+};
+use crate::symmetric::{Aes, Shake, StreamBackend};
+
+/// Parameter sets standardized in FIPS 204.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParameterSet {
+    MlDsa44,
+    MlDsa65,
+    MlDsa87,
+}
+
+impl ParameterSet {
+    pub fn name(self) -> &'static str {
+        match self {
+            ParameterSet::MlDsa44 => "ML-DSA-44",
+            ParameterSet::MlDsa65 => "ML-DSA-65",
+            ParameterSet::MlDsa87 => "ML-DSA-87",
+        }
+    }
+
+    /// DER encoded algorithm object identifier, without tag and length.
+    pub fn oid(self) -> &'static [u8] {
+        match self {
+            ParameterSet::MlDsa44 => ID_ML_DSA_44,
+            ParameterSet::MlDsa65 => ID_ML_DSA_65,
+            ParameterSet::MlDsa87 => ID_ML_DSA_87,
+        }
+    }
+
+    pub fn from_oid(oid: &[u8]) -> Option<Self> {
+        match oid {
+            ID_ML_DSA_44 => Some(ParameterSet::MlDsa44),
+            ID_ML_DSA_65 => Some(ParameterSet::MlDsa65),
+            ID_ML_DSA_87 => Some(ParameterSet::MlDsa87),
+            _ => None,
+        }
+    }
+
+    /// Parameter set from its name, which is also the JOSE `alg` value.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [ParameterSet::MlDsa44, ParameterSet::MlDsa65, ParameterSet::MlDsa87]
+            .into_iter()
+            .find(|p| p.name() == name)
+    }
+
+    /// Sizes of the packed public key, secret key and signature.
+    pub fn sizes(self) -> (usize, usize, usize) {
+        match self {
+            ParameterSet::MlDsa44 => (1312, 2560, 2420),
+            ParameterSet::MlDsa65 => (1952, 4032, 3309),
+            ParameterSet::MlDsa87 => (2592, 4896, 4627),
+        }
+    }
+
+    /// Generate a key pair with the parameter set chosen at runtime.
+    pub fn key_gen(self, zeta: Option<Vec<u8>>) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+        match self {
+            ParameterSet::MlDsa44 => Dilithium2::key_gen(zeta),
+            ParameterSet::MlDsa65 => Dilithium3::key_gen(zeta),
+            ParameterSet::MlDsa87 => Dilithium5::key_gen(zeta),
+        }
+    }
+
+    /// Sign with the parameter set chosen at runtime.
+    pub fn sign(self, data: Vec<u8>, sk: Vec<u8>, ctx: Option<Vec<u8>>, using_randomized_signing: bool) -> anyhow::Result<Vec<u8>> {
+        match self {
+            ParameterSet::MlDsa44 => Dilithium2::sign(data, sk, ctx, using_randomized_signing),
+            ParameterSet::MlDsa65 => Dilithium3::sign(data, sk, ctx, using_randomized_signing),
+            ParameterSet::MlDsa87 => Dilithium5::sign(data, sk, ctx, using_randomized_signing),
+        }
+    }
+
+    /// Verify a signature with the parameter set chosen at runtime, e.g.
+    /// from an algorithm identifier.
+    pub fn verify(self, data: Vec<u8>, sig: Vec<u8>, pk: Vec<u8>, ctx: Option<Vec<u8>>) -> Result<(), SignError> {
+        match self {
+            ParameterSet::MlDsa44 => Dilithium2::verify(data, sig, pk, ctx),
+            ParameterSet::MlDsa65 => Dilithium3::verify(data, sig, pk, ctx),
+            ParameterSet::MlDsa87 => Dilithium5::verify(data, sig, pk, ctx),
+        }
+    }
+
+    /// Sign a formatted message M' with the internal interface.
+    pub fn sign_internal(self, m: Vec<u8>, sk: Vec<u8>, rnd: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        match self {
+            ParameterSet::MlDsa44 => Dilithium2::sign_internal(m, sk, rnd),
+            ParameterSet::MlDsa65 => Dilithium3::sign_internal(m, sk, rnd),
+            ParameterSet::MlDsa87 => Dilithium5::sign_internal(m, sk, rnd),
+        }
+    }
+
+    pub fn verify_internal(self, m: Vec<u8>, sig: Vec<u8>, pk: Vec<u8>) -> Result<(), SignError> {
+        match self {
+            ParameterSet::MlDsa44 => Dilithium2::verify_internal(m, sig, pk),
+            ParameterSet::MlDsa65 => Dilithium3::verify_internal(m, sig, pk),
+            ParameterSet::MlDsa87 => Dilithium5::verify_internal(m, sig, pk),
+        }
+    }
 
+    /// Sign an externally computed message representative mu.
+    pub fn sign_mu(self, mu: Vec<u8>, sk: Vec<u8>, rnd: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        match self {
+            ParameterSet::MlDsa44 => Dilithium2::sign_mu(mu, sk, rnd),
+            ParameterSet::MlDsa65 => Dilithium3::sign_mu(mu, sk, rnd),
+            ParameterSet::MlDsa87 => Dilithium5::sign_mu(mu, sk, rnd),
+        }
+    }
+
+    pub fn verify_mu(self, mu: Vec<u8>, sig: Vec<u8>, pk: Vec<u8>) -> Result<(), SignError> {
+        match self {
+            ParameterSet::MlDsa44 => Dilithium2::verify_mu(mu, sig, pk),
+            ParameterSet::MlDsa65 => Dilithium3::verify_mu(mu, sig, pk),
+            ParameterSet::MlDsa87 => Dilithium5::verify_mu(mu, sig, pk),
+        }
+    }
+
+    /// HashML-DSA signature over a message digest.
+    pub fn sign_prehash(self, digest: Vec<u8>, hash: PreHashAlgorithm, sk: Vec<u8>, ctx: Option<Vec<u8>>, using_randomized_signing: bool) -> anyhow::Result<Vec<u8>> {
+        match self {
+            ParameterSet::MlDsa44 => Dilithium2::sign_prehash(digest, hash, sk, ctx, using_randomized_signing),
+            ParameterSet::MlDsa65 => Dilithium3::sign_prehash(digest, hash, sk, ctx, using_randomized_signing),
+            ParameterSet::MlDsa87 => Dilithium5::sign_prehash(digest, hash, sk, ctx, using_randomized_signing),
+        }
+    }
+
+    pub fn verify_prehash(self, digest: Vec<u8>, hash: PreHashAlgorithm, sig: Vec<u8>, pk: Vec<u8>, ctx: Option<Vec<u8>>) -> Result<(), SignError> {
+        match self {
+            ParameterSet::MlDsa44 => Dilithium2::verify_prehash(digest, hash, sig, pk, ctx),
+            ParameterSet::MlDsa65 => Dilithium3::verify_prehash(digest, hash, sig, pk, ctx),
+            ParameterSet::MlDsa87 => Dilithium5::verify_prehash(digest, hash, sig, pk, ctx),
+        }
+    }
+}
+
+/// ML-DSA over arbitrary parameters; use the `Dilithium2` etc. aliases for
+/// the standardized ones. Inconsistent parameters are rejected when a
+/// function of the instantiation is compiled:
+///
+/// ```compile_fail
+/// use dilithium_lib::{Dilithium, Shake};
+///
+/// // Dilithium2 with GAMMA2 = (Q-1)/44
+/// type Broken = Dilithium<4, 4, 2, 39, 78, 0x20000, 190464, 80, 1312, 2560, 5, Shake, 1, 96, 2420, 192, 32, 576, 84, 5>;
+/// let _ = Broken::key_gen(None);
+/// ```
 pub struct Dilithium<
     const K: usize,
     const L: usize,
@@ -13,7 +168,7 @@ pub struct Dilithium<
     const PUBLICKEYBYTES: usize,
     const SECRETKEYBYTES: usize,
     const POLY_UNIFORM_NBLOCKS: usize,
-    const STREAM128_BLOCKBYTES: usize,
+    S: StreamBackend,
     const POLY_UNIFORM_ETA_NBLOCKS: usize,
     const POLYETA_PACKEDBYTES: usize,
     const SIGNBYTES: usize,
@@ -22,7 +177,7 @@ pub struct Dilithium<
     const POLYZ_PACKEDBYTES: usize,
     const POLYVECH_PACKEDBYTES: usize,
     const POLY_UNIFORM_GAMMA1_NBLOCKS: usize,
->;
+>(PhantomData<S>);
 
 impl<
     const K: usize,
@@ -36,7 +191,7 @@ impl<
     const PUBLICKEYBYTES: usize,
     const SECRETKEYBYTES: usize,
     const POLY_UNIFORM_NBLOCKS: usize,
-    const STREAM128_BLOCKBYTES: usize,
+    S: StreamBackend,
     const POLY_UNIFORM_ETA_NBLOCKS: usize,
     const POLYETA_PACKEDBYTES: usize,
     const SIGNBYTES: usize,
@@ -58,7 +213,7 @@ Dilithium<
     PUBLICKEYBYTES,
     SECRETKEYBYTES,
     POLY_UNIFORM_NBLOCKS,
-    STREAM128_BLOCKBYTES,
+    S,
     POLY_UNIFORM_ETA_NBLOCKS,
     POLYETA_PACKEDBYTES,
     SIGNBYTES,
@@ -69,55 +224,219 @@ Dilithium<
     POLY_UNIFORM_GAMMA1_NBLOCKS,
 >
 {
+    /// The FIPS 204 parameter set these parameters correspond to, if any.
+    /// Variants with a non-standard stream backend have none.
+    pub fn parameter_set() -> Option<ParameterSet> {
+        if !S::STANDARD {
+            return None;
+        }
+        match (K, L, PUBLICKEYBYTES, SECRETKEYBYTES, SIGNBYTES) {
+            (4, 4, 1312, 2560, 2420) => Some(ParameterSet::MlDsa44),
+            (6, 5, 1952, 4032, 3309) => Some(ParameterSet::MlDsa65),
+            (8, 7, 2592, 4896, 4627) => Some(ParameterSet::MlDsa87),
+            _ => None,
+        }
+    }
+
+    /// Compile-time consistency checks of the parameters, evaluated for each
+    /// instantiation that is used.
+    #[inline(always)]
+    fn check_params() {
+        const {
+            Params {
+                k: K,
+                l: L,
+                eta: ETA,
+                tau: TAU,
+                beta: BETA,
+                gamma1: GAMMA1,
+                gamma2: GAMMA2,
+                omega: OMEGA,
+                trbytes: TRBYTES,
+                ctildebytes: CTILDEBYTES,
+                publickeybytes: PUBLICKEYBYTES,
+                secretkeybytes: SECRETKEYBYTES,
+                signbytes: SIGNBYTES,
+                polyeta_packedbytes: POLYETA_PACKEDBYTES,
+                polyz_packedbytes: POLYZ_PACKEDBYTES,
+                polyw1_packedbytes: POLYW1_PACKEDBYTES,
+                polyvech_packedbytes: POLYVECH_PACKEDBYTES,
+                poly_uniform_nblocks: POLY_UNIFORM_NBLOCKS,
+                poly_uniform_eta_nblocks: POLY_UNIFORM_ETA_NBLOCKS,
+                poly_uniform_gamma1_nblocks: POLY_UNIFORM_GAMMA1_NBLOCKS,
+                stream256_blockbytes: S::STREAM256_BLOCKBYTES,
+            }
+            .check()
+        }
+    }
+
     pub fn key_gen(zeta: Option<Vec<u8>>) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+        Self::check_params();
         let mut pk = [0u8; PUBLICKEYBYTES];
         let mut sk = [0u8; SECRETKEYBYTES];
         match zeta {
             None => {
-                crypto_sign_keypair::<K, L, PUBLICKEYBYTES, POLY_UNIFORM_NBLOCKS, STREAM128_BLOCKBYTES, POLY_UNIFORM_ETA_NBLOCKS, ETA, POLYETA_PACKEDBYTES>(&mut pk, &mut sk, None);
+                crypto_sign_keypair::<K, L, PUBLICKEYBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_ETA_NBLOCKS, ETA, POLYETA_PACKEDBYTES>(&mut pk, &mut sk, None);
             }
             Some(zeta) => {
-                crypto_sign_keypair::<K, L, PUBLICKEYBYTES, POLY_UNIFORM_NBLOCKS, STREAM128_BLOCKBYTES, POLY_UNIFORM_ETA_NBLOCKS, ETA, POLYETA_PACKEDBYTES>(&mut pk, &mut sk, Some(&zeta));
+                crypto_sign_keypair::<K, L, PUBLICKEYBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_ETA_NBLOCKS, ETA, POLYETA_PACKEDBYTES>(&mut pk, &mut sk, Some(&zeta));
             }
         }
         Ok((sk.to_vec(), pk.to_vec()))
     }
 
     pub fn sign(data: Vec<u8>, sk: Vec<u8>, ctx: Option<Vec<u8>>, using_randomized_signing: bool) -> anyhow::Result<Vec<u8>> {
+        Self::check_params();
+        if sk.len() != SECRETKEYBYTES {
+            bail!("secret key must be {} bytes", SECRETKEYBYTES);
+        }
         let mut sig = [0u8; SIGNBYTES];
         match ctx {
             None => {
-                crypto_sign_signature::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYETA_PACKEDBYTES, POLY_UNIFORM_NBLOCKS, STREAM128_BLOCKBYTES, POLY_UNIFORM_GAMMA1_NBLOCKS, TAU, POLYZ_PACKEDBYTES, ETA>(&mut sig, &data, &sk, None, using_randomized_signing);
+                crypto_sign_signature::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYETA_PACKEDBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_GAMMA1_NBLOCKS, TAU, POLYZ_PACKEDBYTES, ETA>(&mut sig, &data, &sk, None, using_randomized_signing)?;
             }
             Some(ctx) => {
-                crypto_sign_signature::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYETA_PACKEDBYTES, POLY_UNIFORM_NBLOCKS, STREAM128_BLOCKBYTES, POLY_UNIFORM_GAMMA1_NBLOCKS, TAU, POLYZ_PACKEDBYTES, ETA>(&mut sig, &data, &sk, Some(&ctx), using_randomized_signing);
+                crypto_sign_signature::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYETA_PACKEDBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_GAMMA1_NBLOCKS, TAU, POLYZ_PACKEDBYTES, ETA>(&mut sig, &data, &sk, Some(&ctx), using_randomized_signing)?;
             }
         }
         Ok(sig.to_vec())
     }
 
     pub fn verify(data: Vec<u8>, sig: Vec<u8>, pk: Vec<u8>, zeta: Option<Vec<u8>>) -> Result<(), SignError> {
+        Self::check_params();
         match zeta {
             None => {
-                crypto_sign_verify::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, SIGNBYTES, PUBLICKEYBYTES, POLYZ_PACKEDBYTES, TAU, POLY_UNIFORM_NBLOCKS, STREAM128_BLOCKBYTES>(&sig, &data, &pk, None)
+                crypto_sign_verify::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, SIGNBYTES, PUBLICKEYBYTES, POLYZ_PACKEDBYTES, TAU, POLY_UNIFORM_NBLOCKS, S>(&sig, &data, &pk, None)
             }
             Some(zeta) => {
-                crypto_sign_verify::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, SIGNBYTES, PUBLICKEYBYTES, POLYZ_PACKEDBYTES, TAU, POLY_UNIFORM_NBLOCKS, STREAM128_BLOCKBYTES>(&sig, &data, &pk, Some(&zeta))
+                crypto_sign_verify::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, SIGNBYTES, PUBLICKEYBYTES, POLYZ_PACKEDBYTES, TAU, POLY_UNIFORM_NBLOCKS, S>(&sig, &data, &pk, Some(&zeta))
             }
         }
     }
+
+    /// ML-DSA.Sign_internal: sign the formatted message M' as is. `rnd` is
+    /// 32 bytes of signing randomness, all zero for deterministic signing.
+    pub fn sign_internal(m: Vec<u8>, sk: Vec<u8>, rnd: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        Self::check_params();
+        if sk.len() != SECRETKEYBYTES || rnd.len() != RNDBYTES {
+            bail!("invalid secret key or randomness length");
+        }
+        let mut sig = [0u8; SIGNBYTES];
+        crypto_sign_signature_internal::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYETA_PACKEDBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_GAMMA1_NBLOCKS, TAU, POLYZ_PACKEDBYTES, ETA>(&mut sig, &m, &[], &rnd, &sk)?;
+        Ok(sig.to_vec())
+    }
+
+    /// ML-DSA.Verify_internal on the formatted message M'.
+    pub fn verify_internal(m: Vec<u8>, sig: Vec<u8>, pk: Vec<u8>) -> Result<(), SignError> {
+        Self::check_params();
+        crypto_sign_verify_internal::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, SIGNBYTES, PUBLICKEYBYTES, POLYZ_PACKEDBYTES, TAU, POLY_UNIFORM_NBLOCKS, S>(&sig, &m, &[], &pk)
+    }
+
+    /// Sign the 64 byte message representative mu = H(tr || M'), computed
+    /// elsewhere, e.g. by a client that holds only the public key.
+    pub fn sign_mu(mu: Vec<u8>, sk: Vec<u8>, rnd: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        Self::check_params();
+        if sk.len() != SECRETKEYBYTES || rnd.len() != RNDBYTES || mu.len() != CRHBYTES {
+            bail!("invalid secret key, randomness or mu length");
+        }
+        let mut sig = [0u8; SIGNBYTES];
+        crypto_sign_signature_mu::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYETA_PACKEDBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_GAMMA1_NBLOCKS, TAU, POLYZ_PACKEDBYTES, ETA>(&mut sig, &mu, &rnd, &sk)?;
+        Ok(sig.to_vec())
+    }
+
+    pub fn verify_mu(mu: Vec<u8>, sig: Vec<u8>, pk: Vec<u8>) -> Result<(), SignError> {
+        Self::check_params();
+        crypto_sign_verify_mu::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, SIGNBYTES, PUBLICKEYBYTES, POLYZ_PACKEDBYTES, TAU, POLY_UNIFORM_NBLOCKS, S>(&sig, &mu, &pk)
+    }
+
+    /// HashML-DSA.Sign over `digest`, the output of `hash` on the message.
+    pub fn sign_prehash(digest: Vec<u8>, hash: PreHashAlgorithm, sk: Vec<u8>, ctx: Option<Vec<u8>>, using_randomized_signing: bool) -> anyhow::Result<Vec<u8>> {
+        Self::check_params();
+        let ctx = ctx.unwrap_or_default();
+        if ctx.len() > 255 || digest.len() != hash.digest_len() {
+            bail!("invalid context or digest length");
+        }
+        let mut rnd = vec![0u8; RNDBYTES];
+        if using_randomized_signing {
+            crate::randombytes::randombytes(&mut rnd, RNDBYTES);
+        }
+        Self::sign_internal([prefix(hash, &ctx), digest].concat(), sk, rnd)
+    }
+
+    pub fn verify_prehash(digest: Vec<u8>, hash: PreHashAlgorithm, sig: Vec<u8>, pk: Vec<u8>, ctx: Option<Vec<u8>>) -> Result<(), SignError> {
+        Self::check_params();
+        let ctx = ctx.unwrap_or_default();
+        if ctx.len() > 255 || digest.len() != hash.digest_len() {
+            return Err(SignError::Input);
+        }
+        Self::verify_internal([prefix(hash, &ctx), digest].concat(), sig, pk)
+    }
+
+    /// Same as `sign`, but samples the matrix A on the fly and keeps the
+    /// secret vectors bit-packed, trading speed for a small, constant stack.
+    /// Produces the same signature as `sign`.
+    pub fn sign_lowmem(data: Vec<u8>, sk: Vec<u8>, ctx: Option<Vec<u8>>, using_randomized_signing: bool) -> anyhow::Result<Vec<u8>> {
+        Self::check_params();
+        if sk.len() != SECRETKEYBYTES {
+            bail!("invalid secret key length");
+        }
+        let mut sig = [0u8; SIGNBYTES];
+        crypto_sign_signature_lowmem::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYETA_PACKEDBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_GAMMA1_NBLOCKS, TAU, POLYZ_PACKEDBYTES, ETA>(&mut sig, &data, &sk, ctx.as_deref(), using_randomized_signing)?;
+        Ok(sig.to_vec())
+    }
+
+    /// Split a secret key into two shares for `sign_masked`. This is the
+    /// only step that handles the key in the clear.
+    pub fn mask_secret_key(sk: Vec<u8>) -> anyhow::Result<MaskedSecretKey<K, L>> {
+        Self::check_params();
+        if sk.len() != SECRETKEYBYTES {
+            bail!("invalid secret key length");
+        }
+        Ok(MaskedSecretKey::new::<POLYETA_PACKEDBYTES, ETA>(&sk))
+    }
+
+    /// Same as `sign`, but on a key kept in shares and with the secret
+    /// computations masked, to resist first-order power analysis. The shares
+    /// are refreshed by every call. Produces the same signature as `sign`.
+    /// Only for the SHAKE stream backend.
+    pub fn sign_masked(data: Vec<u8>, sk: &mut MaskedSecretKey<K, L>, ctx: Option<Vec<u8>>, using_randomized_signing: bool) -> anyhow::Result<Vec<u8>> {
+        Self::check_params();
+        let mut sig = [0u8; SIGNBYTES];
+        crypto_sign_signature_masked::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_GAMMA1_NBLOCKS, TAU, POLYZ_PACKEDBYTES>(&mut sig, &data, sk, ctx.as_deref(), using_randomized_signing)?;
+        Ok(sig.to_vec())
+    }
+
+    /// Same as `verify`, but samples the matrix A on the fly.
+    pub fn verify_lowmem(data: Vec<u8>, sig: Vec<u8>, pk: Vec<u8>, ctx: Option<Vec<u8>>) -> Result<(), SignError> {
+        Self::check_params();
+        crypto_sign_verify_lowmem::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, SIGNBYTES, PUBLICKEYBYTES, POLYZ_PACKEDBYTES, TAU, POLY_UNIFORM_NBLOCKS, S>(&sig, &data, &pk, ctx.as_deref())
+    }
 }
 
 pub type Dilithium2 = Dilithium::<
-    4, 4, 2, 39, 78, 0x20000, 95232, 80, 1312, 2560, 5, 168, 1, 96, 2420, 192, 32, 576, 84, 5
+    4, 4, 2, 39, 78, 0x20000, 95232, 80, 1312, 2560, 5, Shake, 1, 96, 2420, 192, 32, 576, 84, 5
 >;
 
 pub type Dilithium3 = Dilithium::<
-    6, 5, 4, 49, 196, 0x80000, 261888, 55, 1952, 4032, 5, 168, 2, 128, 3309, 128, 48, 640, 71, 5
+    6, 5, 4, 49, 196, 0x80000, 261888, 55, 1952, 4032, 5, Shake, 2, 128, 3309, 128, 48, 640, 61, 5
 >;
 
 pub type Dilithium5 = Dilithium::<
-    8, 7, 2, 60, 120, 0x80000, 261888, 75, 2592, 4896, 5, 168, 1, 96, 4627, 128, 64, 640, 83, 5
+    8, 7, 2, 60, 120, 0x80000, 261888, 75, 2592, 4896, 5, Shake, 1, 96, 4627, 128, 64, 640, 83, 5
+>;
+
+// AES-256-CTR variants. The number of stream blocks sampled up front is
+// recomputed for 64 byte AES blocks.
+pub type Dilithium2Aes = Dilithium::<
+    4, 4, 2, 39, 78, 0x20000, 95232, 80, 1312, 2560, 12, Aes, 3, 96, 2420, 192, 32, 576, 84, 9
+>;
+
+pub type Dilithium3Aes = Dilithium::<
+    6, 5, 4, 49, 196, 0x80000, 261888, 55, 1952, 4032, 12, Aes, 4, 128, 3309, 128, 48, 640, 61, 10
+>;
+
+pub type Dilithium5Aes = Dilithium::<
+    8, 7, 2, 60, 120, 0x80000, 261888, 75, 2592, 4896, 12, Aes, 3, 96, 4627, 128, 64, 640, 83, 10
 >;
 
 #[test]
@@ -135,7 +454,7 @@ fn test_dilithium_mode2() {
 #[test]
 fn test_dilithium_mode3() {
     type D3 = Dilithium::<
-        6, 5, 4, 49, 196, 0x80000, 261888, 55, 1952, 4032, 5, 168, 2, 128, 3309, 128, 48, 640, 71, 5
+        6, 5, 4, 49, 196, 0x80000, 261888, 55, 1952, 4032, 5, Shake, 2, 128, 3309, 128, 48, 640, 61, 5
     >;
     let (sk, pk) = D3::key_gen(None).unwrap();
     let data = vec![31u8; 10];
@@ -148,7 +467,7 @@ fn test_dilithium_mode3() {
 #[test]
 fn test_dilithium_mode5() {
     type D5 = Dilithium::<
-        8, 7, 2, 60, 120, 0x80000, 261888, 75, 2592, 4896, 5, 168, 1, 96, 4627, 128, 64, 640, 83, 5
+        8, 7, 2, 60, 120, 0x80000, 261888, 75, 2592, 4896, 5, Shake, 1, 96, 4627, 128, 64, 640, 83, 5
     >;
     let (sk, pk) = D5::key_gen(None).unwrap();
     let data = vec![31u8; 10];
@@ -156,4 +475,157 @@ fn test_dilithium_mode5() {
     let data = vec![31u8; 10];
     let x = D5::verify(data, sign, pk, None);
     assert!(x.is_ok());
-}
\ No newline at end of file
+}
+#[test]
+fn test_dilithium_lowmem() {
+    let (sk, pk) = Dilithium2::key_gen(Some(vec![7u8; 32])).unwrap();
+    let data = vec![49u8; 10];
+    let ctx = Some(b"lowmem".to_vec());
+    let sign = Dilithium2::sign(data.clone(), sk.clone(), ctx.clone(), false).unwrap();
+    let sign_lowmem = Dilithium2::sign_lowmem(data.clone(), sk, ctx.clone(), false).unwrap();
+    assert_eq!(sign, sign_lowmem);
+    assert!(Dilithium2::verify_lowmem(data.clone(), sign, pk.clone(), ctx.clone()).is_ok());
+    assert!(Dilithium2::verify_lowmem(vec![48u8; 10], sign_lowmem, pk, ctx).is_err());
+
+    let (sk, pk) = Dilithium5::key_gen(None).unwrap();
+    let sign_lowmem = Dilithium5::sign_lowmem(data.clone(), sk, None, true).unwrap();
+    assert!(Dilithium5::verify(data.clone(), sign_lowmem.clone(), pk.clone(), None).is_ok());
+    assert!(Dilithium5::verify_lowmem(data.clone(), sign_lowmem.clone(), pk.clone(), None).is_ok());
+
+    // Keys of the wrong length are rejected instead of panicking
+    assert!(Dilithium5::sign_lowmem(data.clone(), vec![0u8; 100], None, false).is_err());
+    assert_eq!(Dilithium5::verify_lowmem(data, sign_lowmem, pk[..100].to_vec(), None), Err(SignError::Input));
+}
+
+#[test]
+fn test_dilithium_interfaces() {
+    let (sk, pk) = Dilithium3::key_gen(Some(vec![3u8; 32])).unwrap();
+    let data = b"interfaces".to_vec();
+    let ctx = b"ctx".to_vec();
+
+    // The external interface is the internal one with a 0 || ctxlen || ctx prefix
+    let sign = Dilithium3::sign(data.clone(), sk.clone(), Some(ctx.clone()), false).unwrap();
+    let m = [&[0u8, 3][..], &ctx, &data].concat();
+    let sign_internal = Dilithium3::sign_internal(m.clone(), sk.clone(), vec![0u8; 32]).unwrap();
+    assert_eq!(sign, sign_internal);
+    assert!(Dilithium3::verify_internal(m.clone(), sign.clone(), pk.clone()).is_ok());
+
+    // ... and the internal interface signs mu = H(tr || M')
+    let mut mu = [0u8; 64];
+    let mut tr = [0u8; 64];
+    crate::fips202::shake256(&mut tr, 64, &pk, pk.len());
+    crate::fips202::shake256(&mut mu, 64, &[&tr[..], &m].concat(), 64 + m.len());
+    let sign_mu = Dilithium3::sign_mu(mu.to_vec(), sk.clone(), vec![0u8; 32]).unwrap();
+    assert_eq!(sign, sign_mu);
+    assert!(Dilithium3::verify_mu(mu.to_vec(), sign.clone(), pk.clone()).is_ok());
+
+    // Hedged signatures differ but verify
+    let hedged = Dilithium3::sign(data.clone(), sk.clone(), Some(ctx.clone()), true).unwrap();
+    assert_ne!(hedged, sign);
+    assert!(Dilithium3::verify(data, hedged, pk.clone(), Some(ctx.clone())).is_ok());
+
+    let digest = vec![9u8; 64];
+    let sign = Dilithium3::sign_prehash(digest.clone(), PreHashAlgorithm::Sha2_512, sk.clone(), Some(ctx.clone()), true).unwrap();
+    assert!(Dilithium3::verify_prehash(digest.clone(), PreHashAlgorithm::Sha2_512, sign.clone(), pk.clone(), Some(ctx.clone())).is_ok());
+    assert!(Dilithium3::verify_prehash(digest.clone(), PreHashAlgorithm::Sha3_512, sign.clone(), pk.clone(), Some(ctx)).is_err());
+    assert!(Dilithium3::sign_prehash(vec![0u8; 32], PreHashAlgorithm::Sha2_512, sk.clone(), None, false).is_err());
+
+    // Keys of the wrong length are rejected instead of panicking
+    assert!(Dilithium3::sign(b"short key".to_vec(), sk[..10].to_vec(), None, false).is_err());
+}
+
+#[test]
+fn test_dilithium_aes() {
+    let data = vec![17u8; 10];
+    let seed = Some(vec![1u8; 32]);
+    let (sk, pk) = Dilithium3Aes::key_gen(seed.clone()).unwrap();
+    let (sk_shake, pk_shake) = Dilithium3::key_gen(seed).unwrap();
+    assert_ne!(pk, pk_shake);
+    assert_eq!(Dilithium3Aes::parameter_set(), None);
+
+    let sign = Dilithium3Aes::sign(data.clone(), sk.clone(), None, false).unwrap();
+    assert!(Dilithium3Aes::verify(data.clone(), sign.clone(), pk.clone(), None).is_ok());
+    assert!(Dilithium3::verify(data.clone(), sign.clone(), pk.clone(), None).is_err());
+    let sign_lowmem = Dilithium3Aes::sign_lowmem(data.clone(), sk, None, false).unwrap();
+    assert_eq!(sign, sign_lowmem);
+
+    // Both backends in one binary
+    let sign_shake = Dilithium3::sign(data.clone(), sk_shake, None, false).unwrap();
+    assert!(Dilithium3::verify(data.clone(), sign_shake, pk_shake, None).is_ok());
+
+    let (sk, pk) = Dilithium2Aes::key_gen(None).unwrap();
+    let sign = Dilithium2Aes::sign(data.clone(), sk, None, true).unwrap();
+    assert!(Dilithium2Aes::verify(data.clone(), sign, pk, None).is_ok());
+    let (sk, pk) = Dilithium5Aes::key_gen(None).unwrap();
+    let sign = Dilithium5Aes::sign(data.clone(), sk, Some(b"ctx".to_vec()), true).unwrap();
+    assert!(Dilithium5Aes::verify(data, sign, pk, Some(b"ctx".to_vec())).is_ok());
+}
+
+#[test]
+fn test_sign_iteration_limit() {
+    use crate::rejection::{with_max_sign_iterations, with_sign_stats};
+
+    let (sk, pk) = Dilithium2::key_gen(Some(vec![9u8; 32])).unwrap();
+    // Find a message that needs more than one iteration
+    let (data, sign, stats) = (0u8..)
+        .map(|i| {
+            let data = vec![i; 8];
+            let (sign, stats) = with_sign_stats(|| Dilithium2::sign(data.clone(), sk.clone(), None, false));
+            (data, sign.unwrap(), stats)
+        })
+        .find(|(_, _, stats)| stats.iterations > 1)
+        .unwrap();
+    let rejected = stats.z_norm + stats.r0_norm + stats.ct0_norm + stats.hint_count;
+    assert_eq!(rejected, stats.iterations - 1);
+    assert!(Dilithium2::verify(data.clone(), sign.clone(), pk, None).is_ok());
+
+    let limited = with_max_sign_iterations(stats.iterations, || Dilithium2::sign(data.clone(), sk.clone(), None, false));
+    assert_eq!(limited.unwrap(), sign);
+    let err = with_max_sign_iterations(stats.iterations - 1, || Dilithium2::sign(data.clone(), sk.clone(), None, false));
+    assert_eq!(err.unwrap_err().downcast::<SignError>().unwrap(), SignError::Exhausted);
+    let err = with_max_sign_iterations(stats.iterations - 1, || Dilithium2::sign_lowmem(data.clone(), sk.clone(), None, false));
+    assert_eq!(err.unwrap_err().downcast::<SignError>().unwrap(), SignError::Exhausted);
+
+    let (sign_lowmem, stats_lowmem) = with_sign_stats(|| Dilithium2::sign_lowmem(data.clone(), sk.clone(), None, false));
+    assert_eq!(sign_lowmem.unwrap(), sign);
+    assert_eq!(stats_lowmem.iterations, stats.iterations);
+
+    // A context longer than 255 bytes is an input error, not a panic
+    let ctx = Some(vec![0u8; 256]);
+    for sign in [Dilithium2::sign, Dilithium2::sign_lowmem] {
+        let err = sign(data.clone(), sk.clone(), ctx.clone(), false).unwrap_err();
+        assert_eq!(err.downcast::<SignError>().unwrap(), SignError::Input);
+    }
+    let mut msk = Dilithium2::mask_secret_key(sk.clone()).unwrap();
+    let err = Dilithium2::sign_masked(data.clone(), &mut msk, ctx, false).unwrap_err();
+    assert_eq!(err.downcast::<SignError>().unwrap(), SignError::Input);
+}
+
+#[test]
+fn test_dilithium_masked() {
+    use crate::rejection::with_sign_stats;
+
+    let data = b"masked".to_vec();
+    let (sk, pk) = Dilithium2::key_gen(Some(vec![5u8; 32])).unwrap();
+    let mut msk = Dilithium2::mask_secret_key(sk.clone()).unwrap();
+    for i in 0u8..4 {
+        let data = vec![i; 8];
+        let (sign, stats) = with_sign_stats(|| Dilithium2::sign(data.clone(), sk.clone(), None, false).unwrap());
+        let (sign_masked, stats_masked) = with_sign_stats(|| Dilithium2::sign_masked(data.clone(), &mut msk, None, false).unwrap());
+        assert_eq!(sign, sign_masked);
+        assert_eq!(stats, stats_masked);
+    }
+    let sign_masked = Dilithium2::sign_masked(data.clone(), &mut msk, None, true).unwrap();
+    assert!(Dilithium2::verify(data.clone(), sign_masked, pk, None).is_ok());
+    assert!(Dilithium2::mask_secret_key(sk[1..].to_vec()).is_err());
+
+    let ctx = Some(b"ctx".to_vec());
+    let (sk, _) = Dilithium3::key_gen(Some(vec![6u8; 32])).unwrap();
+    let sign = Dilithium3::sign(data.clone(), sk.clone(), ctx.clone(), false).unwrap();
+    let mut msk = Dilithium3::mask_secret_key(sk).unwrap();
+    assert_eq!(Dilithium3::sign_masked(data.clone(), &mut msk, ctx, false).unwrap(), sign);
+    let (sk, _) = Dilithium5::key_gen(Some(vec![7u8; 32])).unwrap();
+    let sign = Dilithium5::sign(data.clone(), sk.clone(), None, false).unwrap();
+    let mut msk = Dilithium5::mask_secret_key(sk).unwrap();
+    assert_eq!(Dilithium5::sign_masked(data, &mut msk, None, false).unwrap(), sign);
+}
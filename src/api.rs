@@ -1,3 +1,6 @@
+use crate::ct::{ct_eq, neg_mask, wipe};
+use crate::params::N;
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash)]
 pub struct Keypair<const PUBLICKEYBYTES: usize, const SECRETKEYBYTES: usize> {
     pub public: [u8; PUBLICKEYBYTES],
@@ -11,7 +14,202 @@ impl<const PUBLICKEYBYTES: usize, const SECRETKEYBYTES: usize> std::fmt::Debug f
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SignError {
     Input,
     Verify,
+    /// Signing gave up after the iteration limit of the rejection loop, see
+    /// `with_max_sign_iterations`.
+    Exhausted,
+    /// A fault countermeasure found an inconsistent signature, see
+    /// `with_fault_checks`.
+    Fault,
+}
+
+impl std::fmt::Display for SignError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignError::Input => write!(f, "invalid input"),
+            SignError::Verify => write!(f, "signature does not verify"),
+            SignError::Exhausted => write!(f, "rejection loop iteration limit reached"),
+            SignError::Fault => write!(f, "fault detected while signing"),
+        }
+    }
+}
+
+impl std::error::Error for SignError {}
+
+/// Packed public key of a parameter set, `PUBLICKEYBYTES` long.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct PublicKey<const PUBLICKEYBYTES: usize>(pub [u8; PUBLICKEYBYTES]);
+
+/// Packed secret key of a parameter set, `SECRETKEYBYTES` long. Unlike the
+/// public types it is not `Copy` or `Hash`, compares in constant time and is
+/// wiped on drop.
+#[derive(Clone)]
+pub struct SecretKey<const SECRETKEYBYTES: usize>(pub(crate) [u8; SECRETKEYBYTES]);
+
+/// Packed signature of a parameter set, `SIGNBYTES` long.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Signature<const SIGNBYTES: usize>(pub [u8; SIGNBYTES]);
+
+/// Secret key elided
+impl<const SECRETKEYBYTES: usize> std::fmt::Debug for SecretKey<SECRETKEYBYTES> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretKey(<elided>)")
+    }
+}
+
+macro_rules! impl_key_bytes {
+    ($name:ident) => {
+        impl<const LEN: usize> $name<LEN> {
+            /// Length in bytes of the encoding.
+            pub const BYTES: usize = LEN;
+
+            /// Wrap an encoding, failing if its length does not match the
+            /// parameter set.
+            pub fn from_bytes(bytes: &[u8]) -> Result<Self, SignError> {
+                match <[u8; LEN]>::try_from(bytes) {
+                    Ok(x) => Ok(Self(x)),
+                    Err(_) => Err(SignError::Input),
+                }
+            }
+
+            pub fn as_bytes(&self) -> &[u8] {
+                &self.0
+            }
+
+            pub fn to_vec(&self) -> Vec<u8> {
+                self.0.to_vec()
+            }
+        }
+
+        impl<const LEN: usize> TryFrom<&[u8]> for $name<LEN> {
+            type Error = SignError;
+
+            fn try_from(bytes: &[u8]) -> Result<Self, SignError> {
+                Self::from_bytes(bytes)
+            }
+        }
+
+        impl<const LEN: usize> AsRef<[u8]> for $name<LEN> {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+    };
+}
+
+impl_key_bytes!(PublicKey);
+impl_key_bytes!(Signature);
+
+/// Offset of s1, number of polynomials in s1 and s2, and ETA, for the secret
+/// key sizes of ML-DSA and of round-3 Dilithium.
+fn secret_key_layout(len: usize) -> Option<(usize, usize, usize)> {
+    match len {
+        2560 => Some((128, 8, 2)),
+        4032 => Some((128, 11, 4)),
+        4896 => Some((128, 15, 2)),
+        2528 => Some((96, 8, 2)),
+        4000 => Some((96, 11, 4)),
+        4864 => Some((96, 15, 2)),
+        _ => None,
+    }
+}
+
+/// Whether the packed coefficients of s1 and s2 all lie in [-ETA, ETA].
+/// Every coefficient is checked, so the time does not depend on the key.
+fn secret_key_in_range(sk: &[u8]) -> bool {
+    let Some((offset, polys, eta)) = secret_key_layout(sk.len()) else {
+        return true;
+    };
+    let bits = if eta == 2 { 3 } else { 4 };
+    let packed = &sk[offset..offset + polys * N * bits / 8];
+    let mut bad = 0;
+    for i in 0..polys * N {
+        let (byte, shift) = (i * bits / 8, i * bits % 8);
+        let word = packed[byte] as i32 | (*packed.get(byte + 1).unwrap_or(&0) as i32) << 8;
+        bad |= 2 * eta as i32 - ((word >> shift) & ((1 << bits) - 1));
+    }
+    neg_mask(bad) == 0
+}
+
+impl<const LEN: usize> SecretKey<LEN> {
+    /// Length in bytes of the encoding.
+    pub const BYTES: usize = LEN;
+
+    /// Wrap an encoding, failing if its length does not match the parameter
+    /// set or, for the ML-DSA and round-3 sizes, if s1 or s2 has coefficients
+    /// outside [-ETA, ETA].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SignError> {
+        let key = <[u8; LEN]>::try_from(bytes).map(Self).map_err(|_| SignError::Input)?;
+        if !secret_key_in_range(&key.0) {
+            return Err(SignError::Input);
+        }
+        Ok(key)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+}
+
+impl<const LEN: usize> TryFrom<&[u8]> for SecretKey<LEN> {
+    type Error = SignError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, SignError> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl<const LEN: usize> AsRef<[u8]> for SecretKey<LEN> {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<const LEN: usize> PartialEq for SecretKey<LEN> {
+    fn eq(&self, other: &Self) -> bool {
+        ct_eq(&self.0, &other.0)
+    }
+}
+
+impl<const LEN: usize> Eq for SecretKey<LEN> {}
+
+impl<const LEN: usize> Drop for SecretKey<LEN> {
+    fn drop(&mut self) {
+        wipe(&mut self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dilithium2, Dilithium3Legacy};
+
+    #[test]
+    fn secret_key_checks() {
+        let (sk, _) = Dilithium2::key_gen(None).unwrap();
+        let key = SecretKey::<2560>::from_bytes(&sk).unwrap();
+        assert!(key == key.clone());
+        let mut other = sk.clone();
+        other[2559] ^= 1;
+        assert!(key != SecretKey::<2560>::from_bytes(&other).unwrap());
+        assert!(SecretKey::<2560>::from_bytes(&sk[..2559]).is_err());
+
+        // The first coefficient of s1 set to 7, outside [-2, 2]
+        let mut bad = sk.clone();
+        bad[128] |= 0x07;
+        assert!(SecretKey::<2560>::from_bytes(&bad).is_err());
+        // The last coefficient of s2, with ETA = 4
+        let (sk, _) = Dilithium3Legacy::key_gen(None).unwrap();
+        assert!(SecretKey::<4000>::from_bytes(&sk).is_ok());
+        let mut bad = sk.clone();
+        bad[96 + 11 * 128 - 1] |= 0xf0;
+        assert!(SecretKey::<4000>::from_bytes(&bad).is_err());
+    }
 }
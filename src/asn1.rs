@@ -0,0 +1,364 @@
+//! Minimal DER encoder and decoder plus PEM armoring, covering the subset of
+//! ASN.1 needed for the key and certificate formats of this crate.
+
+// The time, string and SET helpers are only used by the X.509 code.
+#![cfg_attr(not(feature = "x509"), allow(dead_code))]
+
+#[cfg(feature = "pem")]
+use anyhow::anyhow;
+use anyhow::bail;
+#[cfg(feature = "pem")]
+use base64::Engine;
+#[cfg(feature = "pem")]
+use base64::engine::general_purpose::STANDARD;
+
+pub const BOOLEAN: u8 = 0x01;
+pub const INTEGER: u8 = 0x02;
+pub const BIT_STRING: u8 = 0x03;
+pub const OCTET_STRING: u8 = 0x04;
+pub const NULL: u8 = 0x05;
+pub const OBJECT_IDENTIFIER: u8 = 0x06;
+pub const UTF8_STRING: u8 = 0x0c;
+pub const PRINTABLE_STRING: u8 = 0x13;
+pub const UTC_TIME: u8 = 0x17;
+pub const GENERALIZED_TIME: u8 = 0x18;
+pub const SEQUENCE: u8 = 0x30;
+pub const SET: u8 = 0x31;
+
+/// Tag of a context specific, primitive field `[n] IMPLICIT`.
+pub const fn context(n: u8) -> u8 {
+    0x80 | n
+}
+
+/// Tag of a context specific, constructed field `[n]`.
+pub const fn context_constructed(n: u8) -> u8 {
+    0xa0 | n
+}
+
+// Object identifiers, DER encoded without tag and length
+/// id-ml-dsa-44, 2.16.840.1.101.3.4.3.17
+pub const ID_ML_DSA_44: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x03, 0x11];
+/// id-ml-dsa-65, 2.16.840.1.101.3.4.3.18
+pub const ID_ML_DSA_65: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x03, 0x12];
+/// id-ml-dsa-87, 2.16.840.1.101.3.4.3.19
+pub const ID_ML_DSA_87: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x03, 0x13];
+
+/// Append the DER length octets of `len` to `out`.
+fn push_length(out: &mut Vec<u8>, len: usize) {
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|&&b| b == 0).count();
+        out.push(0x80 | (bytes.len() - skip) as u8);
+        out.extend_from_slice(&bytes[skip..]);
+    }
+}
+
+/// Encode a single tag-length-value triple.
+pub fn tlv(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(value.len() + 6);
+    out.push(tag);
+    push_length(&mut out, value.len());
+    out.extend_from_slice(value);
+    out
+}
+
+/// Encode a constructed value from already encoded parts.
+pub fn constructed(tag: u8, parts: &[&[u8]]) -> Vec<u8> {
+    tlv(tag, &parts.concat())
+}
+
+pub fn sequence(parts: &[&[u8]]) -> Vec<u8> {
+    constructed(SEQUENCE, parts)
+}
+
+/// Encode a SET OF, sorting the encoded elements as DER requires.
+pub fn set_of(parts: &[&[u8]]) -> Vec<u8> {
+    let mut sorted = parts.to_vec();
+    sorted.sort();
+    constructed(SET, &sorted)
+}
+
+pub fn oid(value: &[u8]) -> Vec<u8> {
+    tlv(OBJECT_IDENTIFIER, value)
+}
+
+pub fn octet_string(value: &[u8]) -> Vec<u8> {
+    tlv(OCTET_STRING, value)
+}
+
+/// Encode a BIT STRING without unused bits.
+pub fn bit_string(value: &[u8]) -> Vec<u8> {
+    let mut v = Vec::with_capacity(value.len() + 1);
+    v.push(0);
+    v.extend_from_slice(value);
+    tlv(BIT_STRING, &v)
+}
+
+pub fn boolean(value: bool) -> Vec<u8> {
+    tlv(BOOLEAN, &[if value { 0xff } else { 0 }])
+}
+
+pub fn small_integer(value: u64) -> Vec<u8> {
+    integer(&value.to_be_bytes())
+}
+
+/// Encode an unsigned big-endian integer, fixing up leading zeros and the
+/// sign bit.
+pub fn integer(value: &[u8]) -> Vec<u8> {
+    let skip = value.iter().take_while(|&&b| b == 0).count();
+    let value = &value[skip..];
+    let mut v = Vec::with_capacity(value.len() + 1);
+    if value.is_empty() || value[0] & 0x80 != 0 {
+        v.push(0);
+    }
+    v.extend_from_slice(value);
+    tlv(INTEGER, &v)
+}
+
+/// AlgorithmIdentifier with absent parameters.
+pub fn algorithm_identifier(algorithm: &[u8]) -> Vec<u8> {
+    sequence(&[&oid(algorithm)])
+}
+
+/// Render an encoded object identifier in dotted decimal form.
+pub fn oid_to_string(value: &[u8]) -> String {
+    let mut arcs = Vec::new();
+    let mut acc = 0u64;
+    for &b in value {
+        acc = (acc << 7) | (b & 0x7f) as u64;
+        if b & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (acc / 40).min(2);
+                arcs.push(first);
+                arcs.push(acc - 40 * first);
+            } else {
+                arcs.push(acc);
+            }
+            acc = 0;
+        }
+    }
+    arcs.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(".")
+}
+
+/// Sequential reader over DER encoded values.
+#[derive(Clone, Copy)]
+pub struct DerReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        DerReader { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Read the next value, returning its tag, content and complete encoding.
+    pub fn read_raw(&mut self) -> anyhow::Result<(u8, &'a [u8], &'a [u8])> {
+        let data = self.data;
+        if data.len() < 2 {
+            bail!("truncated DER value");
+        }
+        let tag = data[0];
+        if tag & 0x1f == 0x1f {
+            bail!("multi-byte DER tags are not supported");
+        }
+        let (len, hdr) = if data[1] < 0x80 {
+            (data[1] as usize, 2)
+        } else {
+            let n = (data[1] & 0x7f) as usize;
+            if n == 0 || n > 4 || data.len() < 2 + n {
+                bail!("invalid DER length");
+            }
+            let mut len = 0usize;
+            for &b in &data[2..2 + n] {
+                len = (len << 8) | b as usize;
+            }
+            if data[2] == 0 || len < 0x80 {
+                bail!("non-minimal DER length");
+            }
+            (len, 2 + n)
+        };
+        if data.len() - hdr < len {
+            bail!("truncated DER value");
+        }
+        self.data = &data[hdr + len..];
+        Ok((tag, &data[hdr..hdr + len], &data[..hdr + len]))
+    }
+
+    /// Read the next value, requiring the given tag.
+    pub fn read(&mut self, tag: u8) -> anyhow::Result<&'a [u8]> {
+        let (t, value, _) = self.read_raw()?;
+        if t != tag {
+            bail!("unexpected DER tag {:#04x}, expected {:#04x}", t, tag);
+        }
+        Ok(value)
+    }
+
+    /// Read an optional value that is present if the next tag matches.
+    pub fn read_optional(&mut self, tag: u8) -> anyhow::Result<Option<&'a [u8]>> {
+        if self.peek_tag() == Some(tag) {
+            self.read(tag).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Read a constructed value and return a reader over its content.
+    pub fn read_nested(&mut self, tag: u8) -> anyhow::Result<DerReader<'a>> {
+        self.read(tag).map(DerReader::new)
+    }
+
+    pub fn read_sequence(&mut self) -> anyhow::Result<DerReader<'a>> {
+        self.read_nested(SEQUENCE)
+    }
+
+    pub fn read_oid(&mut self) -> anyhow::Result<&'a [u8]> {
+        self.read(OBJECT_IDENTIFIER)
+    }
+
+    pub fn read_octet_string(&mut self) -> anyhow::Result<&'a [u8]> {
+        self.read(OCTET_STRING)
+    }
+
+    /// Read a BIT STRING without unused bits.
+    pub fn read_bit_string(&mut self) -> anyhow::Result<&'a [u8]> {
+        let v = self.read(BIT_STRING)?;
+        match v.split_first() {
+            Some((0, bits)) => Ok(bits),
+            _ => bail!("BIT STRING with unused bits"),
+        }
+    }
+
+    pub fn read_boolean(&mut self) -> anyhow::Result<bool> {
+        match self.read(BOOLEAN)? {
+            [0] => Ok(false),
+            [0xff] => Ok(true),
+            _ => bail!("invalid DER BOOLEAN"),
+        }
+    }
+
+    /// Read a non-negative INTEGER, returning its big-endian magnitude.
+    pub fn read_integer(&mut self) -> anyhow::Result<&'a [u8]> {
+        let v = self.read(INTEGER)?;
+        match v {
+            [] => bail!("empty INTEGER"),
+            [x, ..] if x & 0x80 != 0 => bail!("negative INTEGER"),
+            [0, x, ..] if x & 0x80 == 0 => bail!("non-minimal INTEGER"),
+            [0, rest @ ..] => Ok(rest),
+            _ => Ok(v),
+        }
+    }
+
+    pub fn read_small_integer(&mut self) -> anyhow::Result<u64> {
+        let v = self.read_integer()?;
+        if v.len() > 8 {
+            bail!("INTEGER too large");
+        }
+        Ok(v.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64))
+    }
+
+    /// Read an AlgorithmIdentifier whose parameters must be absent.
+    pub fn read_algorithm_identifier(&mut self) -> anyhow::Result<&'a [u8]> {
+        let mut alg = self.read_sequence()?;
+        let oid = alg.read_oid()?;
+        alg.finish()?;
+        Ok(oid)
+    }
+
+    /// Read an AlgorithmIdentifier whose parameters are absent or NULL, as
+    /// classical signature algorithms like sha256WithRSAEncryption use.
+    /// Returns the OID and whether NULL parameters were present.
+    pub fn read_algorithm_identifier_or_null(&mut self) -> anyhow::Result<(&'a [u8], bool)> {
+        let mut alg = self.read_sequence()?;
+        let oid = alg.read_oid()?;
+        let null = match alg.read_optional(NULL)? {
+            Some([]) => true,
+            Some(_) => bail!("invalid NULL"),
+            None => false,
+        };
+        alg.finish()?;
+        Ok((oid, null))
+    }
+
+    /// Fail if there is unread data left.
+    pub fn finish(&self) -> anyhow::Result<()> {
+        if !self.data.is_empty() {
+            bail!("trailing data after DER value");
+        }
+        Ok(())
+    }
+}
+
+/// Armor DER data as PEM with the given label.
+#[cfg(feature = "pem")]
+pub fn pem_encode(label: &str, der: &[u8]) -> String {
+    let b64 = STANDARD.encode(der);
+    let mut out = format!("-----BEGIN {}-----\n", label);
+    for line in b64.as_bytes().chunks(64) {
+        out.push_str(std::str::from_utf8(line).unwrap());
+        out.push('\n');
+    }
+    out.push_str(&format!("-----END {}-----\n", label));
+    out
+}
+
+/// Decode the first PEM block with the given label.
+#[cfg(feature = "pem")]
+pub fn pem_decode(label: &str, pem: &str) -> anyhow::Result<Vec<u8>> {
+    let begin = format!("-----BEGIN {}-----", label);
+    let end = format!("-----END {}-----", label);
+    let start = pem.find(&begin).ok_or_else(|| anyhow!("missing PEM header for {}", label))? + begin.len();
+    let stop = pem[start..].find(&end).ok_or_else(|| anyhow!("missing PEM footer for {}", label))? + start;
+    let b64: String = pem[start..stop].chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    Ok(STANDARD.decode(b64)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oid_encoding() {
+        assert_eq!(oid_to_string(ID_ML_DSA_44), "2.16.840.1.101.3.4.3.17");
+        assert_eq!(oid_to_string(ID_ML_DSA_87), "2.16.840.1.101.3.4.3.19");
+    }
+
+    #[test]
+    fn length_and_integer_encoding() {
+        assert_eq!(tlv(OCTET_STRING, &[0u8; 3])[..2], [0x04, 0x03]);
+        assert_eq!(tlv(OCTET_STRING, &[0u8; 200])[..3], [0x04, 0x81, 0xc8]);
+        assert_eq!(tlv(OCTET_STRING, &[0u8; 2560])[..4], [0x04, 0x82, 0x0a, 0x00]);
+        assert_eq!(small_integer(0), [0x02, 0x01, 0x00]);
+        assert_eq!(small_integer(128), [0x02, 0x02, 0x00, 0x80]);
+
+        let enc = sequence(&[&small_integer(0x1234), &octet_string(&[0u8; 300])]);
+        let mut r = DerReader::new(&enc);
+        let mut seq = r.read_sequence().unwrap();
+        assert_eq!(seq.read_small_integer().unwrap(), 0x1234);
+        assert_eq!(seq.read_octet_string().unwrap().len(), 300);
+        seq.finish().unwrap();
+        r.finish().unwrap();
+
+        // Non-minimal lengths are not DER
+        assert!(DerReader::new(&[0x04, 0x81, 0x01, 0x00]).read_raw().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "pem")]
+    fn pem_roundtrip() {
+        let der = vec![0x30u8; 100];
+        let pem = pem_encode("PUBLIC KEY", &der);
+        assert!(pem.lines().all(|l| l.len() <= 64));
+        assert_eq!(pem_decode("PUBLIC KEY", &pem).unwrap(), der);
+        assert!(pem_decode("PRIVATE KEY", &pem).is_err());
+    }
+}
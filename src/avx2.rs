@@ -0,0 +1,406 @@
+//! AVX2 versions of the polynomial arithmetic, after the avx2 reference
+//! implementation of Dilithium.
+//!
+//! Unlike the reference code the NTT keeps the coefficient order of ntt.rs,
+//! and every function returns exactly what its scalar counterpart returns,
+//! so the two can be mixed freely. Callers must check `available` first.
+
+use std::arch::x86_64::*;
+
+use crate::{
+  ntt::ZETAS,
+  params::{N, Q, Q_I32},
+  poly::Poly,
+  reduce::QINV,
+};
+
+pub fn available() -> bool {
+  is_x86_feature_detected!("avx2")
+}
+
+#[target_feature(enable = "avx2")]
+fn load(a: &[i32]) -> __m256i {
+  assert!(a.len() >= 8);
+  // SAFETY: at least 8 coefficients, unaligned load
+  unsafe { _mm256_loadu_si256(a.as_ptr() as *const __m256i) }
+}
+
+#[target_feature(enable = "avx2")]
+fn store(a: &mut [i32], x: __m256i) {
+  assert!(a.len() >= 8);
+  // SAFETY: at least 8 coefficients, unaligned store
+  unsafe { _mm256_storeu_si256(a.as_mut_ptr() as *mut __m256i, x) }
+}
+
+/// `montgomery_reduce(a * b)` on each lane.
+#[target_feature(enable = "avx2")]
+fn montmul(a: __m256i, b: __m256i) -> __m256i {
+  let q = _mm256_set1_epi32(Q_I32);
+  let qinv = _mm256_set1_epi32(QINV);
+  let p_even = _mm256_mul_epi32(a, b);
+  let p_odd = _mm256_mul_epi32(_mm256_srli_epi64::<32>(a), _mm256_srli_epi64::<32>(b));
+  let t_even = _mm256_mul_epi32(p_even, qinv);
+  let t_odd = _mm256_mul_epi32(p_odd, qinv);
+  let r_even = _mm256_sub_epi64(p_even, _mm256_mul_epi32(t_even, q));
+  let r_odd = _mm256_sub_epi64(p_odd, _mm256_mul_epi32(t_odd, q));
+  _mm256_blend_epi32::<0xAA>(_mm256_srli_epi64::<32>(r_even), r_odd)
+}
+
+#[target_feature(enable = "avx2")]
+fn zetas(idx: [usize; 8], sign: i32) -> __m256i {
+  let z = idx.map(|k| sign * ZETAS[k]);
+  load(&z)
+}
+
+/// Cooley-Tukey butterfly with len >= 8 on every block of one layer.
+#[target_feature(enable = "avx2")]
+fn ntt_layer(a: &mut [i32; N], len: usize) {
+  for (g, start) in (0..N).step_by(2 * len).enumerate() {
+    let zeta = _mm256_set1_epi32(ZETAS[N / (2 * len) + g]);
+    for j in (start..start + len).step_by(8) {
+      let t = montmul(zeta, load(&a[j + len..]));
+      let x = load(&a[j..]);
+      store(&mut a[j + len..], _mm256_sub_epi32(x, t));
+      store(&mut a[j..], _mm256_add_epi32(x, t));
+    }
+  }
+}
+
+/// Gentleman-Sande butterfly with len >= 8 on every block of one layer.
+#[target_feature(enable = "avx2")]
+fn invntt_layer(a: &mut [i32; N], len: usize) {
+  for (g, start) in (0..N).step_by(2 * len).enumerate() {
+    let zeta = _mm256_set1_epi32(-ZETAS[N / len - 1 - g]);
+    for j in (start..start + len).step_by(8) {
+      let x = load(&a[j..]);
+      let y = load(&a[j + len..]);
+      store(&mut a[j..], _mm256_add_epi32(x, y));
+      store(&mut a[j + len..], montmul(zeta, _mm256_sub_epi32(x, y)));
+    }
+  }
+}
+
+// The layers with len 4, 2 and 1 work on 16 coefficients a || b at a time.
+// The low and high halves of each butterfly are gathered into lo and hi,
+// with the zetas in the matching lanes, and scattered back afterwards.
+
+#[target_feature(enable = "avx2")]
+fn split4(a: __m256i, b: __m256i) -> (__m256i, __m256i) {
+  (_mm256_permute2x128_si256::<0x20>(a, b), _mm256_permute2x128_si256::<0x31>(a, b))
+}
+
+#[target_feature(enable = "avx2")]
+fn split2(a: __m256i, b: __m256i) -> (__m256i, __m256i) {
+  (_mm256_unpacklo_epi64(a, b), _mm256_unpackhi_epi64(a, b))
+}
+
+#[target_feature(enable = "avx2")]
+fn join2(lo: __m256i, hi: __m256i) -> (__m256i, __m256i) {
+  (_mm256_unpacklo_epi64(lo, hi), _mm256_unpackhi_epi64(lo, hi))
+}
+
+#[target_feature(enable = "avx2")]
+fn split1(a: __m256i, b: __m256i) -> (__m256i, __m256i) {
+  let (a, b) = (_mm256_castsi256_ps(a), _mm256_castsi256_ps(b));
+  (
+    _mm256_castps_si256(_mm256_shuffle_ps::<0x88>(a, b)),
+    _mm256_castps_si256(_mm256_shuffle_ps::<0xDD>(a, b)),
+  )
+}
+
+#[target_feature(enable = "avx2")]
+fn join1(lo: __m256i, hi: __m256i) -> (__m256i, __m256i) {
+  (_mm256_unpacklo_epi32(lo, hi), _mm256_unpackhi_epi32(lo, hi))
+}
+
+/// Index of the first block of the 16 coefficients at 16*m, and the order
+/// of the blocks in the lanes of lo and hi, for len 4, 2 and 1.
+const BLOCKS4: [usize; 8] = [0, 0, 0, 0, 1, 1, 1, 1];
+const BLOCKS2: [usize; 8] = [0, 0, 2, 2, 1, 1, 3, 3];
+const BLOCKS1: [usize; 8] = [0, 1, 4, 5, 2, 3, 6, 7];
+
+/// Forward NTT, same output as `ntt::ntt`.
+#[target_feature(enable = "avx2")]
+pub fn ntt(a: &mut [i32; N]) {
+  let mut len = 128;
+  while len >= 8 {
+    ntt_layer(a, len);
+    len >>= 1;
+  }
+  for m in 0..N / 16 {
+    let c = &mut a[16 * m..16 * m + 16];
+    let (mut x, mut y) = (load(c), load(&c[8..]));
+    for (len, blocks) in [(4, BLOCKS4), (2, BLOCKS2), (1, BLOCKS1)] {
+      let first = N / (2 * len) + 16 / (2 * len) * m;
+      let zeta = zetas(blocks.map(|g| first + g), 1);
+      let (lo, hi) = match len {
+        4 => split4(x, y),
+        2 => split2(x, y),
+        _ => split1(x, y),
+      };
+      let t = montmul(zeta, hi);
+      let (lo, hi) = (_mm256_add_epi32(lo, t), _mm256_sub_epi32(lo, t));
+      (x, y) = match len {
+        4 => split4(lo, hi),
+        2 => join2(lo, hi),
+        _ => join1(lo, hi),
+      };
+    }
+    store(c, x);
+    store(&mut c[8..], y);
+  }
+}
+
+/// Inverse NTT and multiplication by 2^32, same output as
+/// `ntt::invntt_tomont`.
+#[target_feature(enable = "avx2")]
+pub fn invntt_tomont(a: &mut [i32; N]) {
+  for m in 0..N / 16 {
+    let c = &mut a[16 * m..16 * m + 16];
+    let (mut x, mut y) = (load(c), load(&c[8..]));
+    for (len, blocks) in [(1, BLOCKS1), (2, BLOCKS2), (4, BLOCKS4)] {
+      let first = N / len - 1 - 16 / (2 * len) * m;
+      let zeta = zetas(blocks.map(|g| first - g), -1);
+      let (lo, hi) = match len {
+        4 => split4(x, y),
+        2 => split2(x, y),
+        _ => split1(x, y),
+      };
+      let (lo, hi) = (_mm256_add_epi32(lo, hi), montmul(zeta, _mm256_sub_epi32(lo, hi)));
+      (x, y) = match len {
+        4 => split4(lo, hi),
+        2 => join2(lo, hi),
+        _ => join1(lo, hi),
+      };
+    }
+    store(c, x);
+    store(&mut c[8..], y);
+  }
+  let mut len = 8;
+  while len < N {
+    invntt_layer(a, len);
+    len <<= 1;
+  }
+  let f = _mm256_set1_epi32(41978); // mont^2/256
+  for c in a.chunks_exact_mut(8) {
+    store(c, montmul(f, load(c)));
+  }
+}
+
+#[target_feature(enable = "avx2")]
+pub fn poly_pointwise_montgomery(c: &mut Poly, a: &Poly, b: &Poly) {
+  for i in (0..N).step_by(8) {
+    store(&mut c.coeffs[i..], montmul(load(&a.coeffs[i..]), load(&b.coeffs[i..])));
+  }
+}
+
+#[target_feature(enable = "avx2")]
+pub fn pointwise_acc_montgomery(w: &mut Poly, u: &[Poly], v: &[Poly]) {
+  for i in (0..N).step_by(8) {
+    let mut acc = montmul(load(&u[0].coeffs[i..]), load(&v[0].coeffs[i..]));
+    for (u, v) in u.iter().zip(v).skip(1) {
+      acc = _mm256_add_epi32(acc, montmul(load(&u.coeffs[i..]), load(&v.coeffs[i..])));
+    }
+    store(&mut w.coeffs[i..], acc);
+  }
+}
+
+#[target_feature(enable = "avx2")]
+pub fn poly_reduce(a: &mut Poly) {
+  let q = _mm256_set1_epi32(Q_I32);
+  let round = _mm256_set1_epi32(1 << 22);
+  for c in a.coeffs.chunks_exact_mut(8) {
+    let x = load(c);
+    let t = _mm256_srai_epi32::<23>(_mm256_add_epi32(x, round));
+    store(c, _mm256_sub_epi32(x, _mm256_mullo_epi32(t, q)));
+  }
+}
+
+#[target_feature(enable = "avx2")]
+pub fn poly_caddq(a: &mut Poly) {
+  let q = _mm256_set1_epi32(Q_I32);
+  for c in a.coeffs.chunks_exact_mut(8) {
+    let x = load(c);
+    store(c, _mm256_add_epi32(x, _mm256_and_si256(_mm256_srai_epi32::<31>(x), q)));
+  }
+}
+
+/// Returns (a1, a0) as `rounding::decompose`.
+#[target_feature(enable = "avx2")]
+fn decompose<const GAMMA2: usize>(a: __m256i) -> (__m256i, __m256i) {
+  let mut a1 = _mm256_srai_epi32::<7>(_mm256_add_epi32(a, _mm256_set1_epi32(127)));
+  if GAMMA2 == (Q - 1) / 32 {
+    a1 = _mm256_mullo_epi32(a1, _mm256_set1_epi32(1025));
+    a1 = _mm256_srai_epi32::<22>(_mm256_add_epi32(a1, _mm256_set1_epi32(1 << 21)));
+    a1 = _mm256_and_si256(a1, _mm256_set1_epi32(15));
+  } else if GAMMA2 == (Q - 1) / 88 {
+    a1 = _mm256_mullo_epi32(a1, _mm256_set1_epi32(11275));
+    a1 = _mm256_srai_epi32::<24>(_mm256_add_epi32(a1, _mm256_set1_epi32(1 << 23)));
+    let wrap = _mm256_srai_epi32::<31>(_mm256_sub_epi32(_mm256_set1_epi32(43), a1));
+    a1 = _mm256_xor_si256(a1, _mm256_and_si256(wrap, a1));
+  }
+  let mut a0 = _mm256_sub_epi32(a, _mm256_mullo_epi32(a1, _mm256_set1_epi32(2 * GAMMA2 as i32)));
+  let over = _mm256_srai_epi32::<31>(_mm256_sub_epi32(_mm256_set1_epi32((Q_I32 - 1) / 2), a0));
+  a0 = _mm256_sub_epi32(a0, _mm256_and_si256(over, _mm256_set1_epi32(Q_I32)));
+  (a1, a0)
+}
+
+#[target_feature(enable = "avx2")]
+pub fn poly_decompose<const GAMMA2: usize>(a1: &mut Poly, a0: &mut Poly) {
+  for i in (0..N).step_by(8) {
+    let (hi, lo) = decompose::<GAMMA2>(load(&a1.coeffs[i..]));
+    store(&mut a1.coeffs[i..], hi);
+    store(&mut a0.coeffs[i..], lo);
+  }
+}
+
+#[target_feature(enable = "avx2")]
+pub fn poly_make_hint<const GAMMA2: usize>(h: &mut Poly, a0: &Poly, a1: &Poly) -> i32 {
+  let gamma2 = _mm256_set1_epi32(GAMMA2 as i32);
+  let neg_gamma2 = _mm256_set1_epi32(-(GAMMA2 as i32));
+  let zero = _mm256_setzero_si256();
+  let mut sum = zero;
+  for i in (0..N).step_by(8) {
+    let x0 = load(&a0.coeffs[i..]);
+    let x1 = load(&a1.coeffs[i..]);
+    let mut hint = _mm256_or_si256(_mm256_cmpgt_epi32(x0, gamma2), _mm256_cmpgt_epi32(neg_gamma2, x0));
+    let edge = _mm256_andnot_si256(_mm256_cmpeq_epi32(x1, zero), _mm256_cmpeq_epi32(x0, neg_gamma2));
+    hint = _mm256_srli_epi32::<31>(_mm256_or_si256(hint, edge));
+    store(&mut h.coeffs[i..], hint);
+    sum = _mm256_add_epi32(sum, hint);
+  }
+  let mut lanes = [0i32; 8];
+  store(&mut lanes, sum);
+  lanes.iter().sum()
+}
+
+#[target_feature(enable = "avx2")]
+pub fn poly_use_hint<const GAMMA2: usize>(b: &mut Poly, h: &Poly) {
+  let zero = _mm256_setzero_si256();
+  let one = _mm256_set1_epi32(1);
+  for i in (0..N).step_by(8) {
+    let (a1, a0) = decompose::<GAMMA2>(load(&b.coeffs[i..]));
+    let mut up = _mm256_add_epi32(a1, one);
+    let mut down = _mm256_sub_epi32(a1, one);
+    if GAMMA2 == (Q - 1) / 32 {
+      up = _mm256_and_si256(up, _mm256_set1_epi32(15));
+      down = _mm256_and_si256(down, _mm256_set1_epi32(15));
+    } else {
+      up = _mm256_andnot_si256(_mm256_cmpeq_epi32(a1, _mm256_set1_epi32(43)), up);
+      down = _mm256_blendv_epi8(down, _mm256_set1_epi32(43), _mm256_cmpeq_epi32(a1, zero));
+    }
+    let corrected = _mm256_blendv_epi8(down, up, _mm256_cmpgt_epi32(a0, zero));
+    // The hint is read as a u8, as in the scalar code
+    let hint = _mm256_and_si256(load(&h.coeffs[i..]), _mm256_set1_epi32(0xff));
+    let keep = _mm256_cmpeq_epi32(hint, zero);
+    store(&mut b.coeffs[i..], _mm256_blendv_epi8(corrected, a1, keep));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use rand::prelude::*;
+
+  use super::*;
+  use crate::{ntt, reduce, rounding};
+
+  fn random_poly(rng: &mut impl Rng, lo: i32, hi: i32) -> Poly {
+    let mut p = Poly::default();
+    for c in p.coeffs.iter_mut() {
+      *c = rng.random_range(lo..hi);
+    }
+    p
+  }
+
+  #[test]
+  fn matches_scalar() {
+    if !available() {
+      return;
+    }
+    let mut rng = StdRng::seed_from_u64(42);
+    let q = Q_I32;
+    for _ in 0..50 {
+      let a = random_poly(&mut rng, -q + 1, q);
+      let b = random_poly(&mut rng, -q + 1, q);
+
+      let (mut x, mut y) = (a, a);
+      ntt::ntt(&mut x.coeffs);
+      unsafe { ntt(&mut y.coeffs) };
+      assert_eq!(x.coeffs, y.coeffs);
+
+      let (mut x, mut y) = (a, a);
+      ntt::invntt_tomont(&mut x.coeffs);
+      unsafe { invntt_tomont(&mut y.coeffs) };
+      assert_eq!(x.coeffs, y.coeffs);
+
+      let mut c = Poly::default();
+      unsafe { poly_pointwise_montgomery(&mut c, &a, &b) };
+      for i in 0..N {
+        assert_eq!(c.coeffs[i], reduce::montgomery_reduce(a.coeffs[i] as i64 * b.coeffs[i] as i64));
+      }
+
+      let u = [a, b, b];
+      let v = [b, a, b];
+      unsafe { pointwise_acc_montgomery(&mut c, &u, &v) };
+      for i in 0..N {
+        let expected: i32 = (0..3)
+          .map(|l| reduce::montgomery_reduce(u[l].coeffs[i] as i64 * v[l].coeffs[i] as i64))
+          .sum();
+        assert_eq!(c.coeffs[i], expected);
+      }
+
+      let big = random_poly(&mut rng, -(1 << 30), i32::MAX - (1 << 22) + 1);
+      let mut x = big;
+      unsafe { poly_reduce(&mut x) };
+      assert!(x.coeffs.iter().zip(big.coeffs).all(|(&x, a)| x == reduce::reduce32(a)));
+      let mut x = a;
+      unsafe { poly_caddq(&mut x) };
+      assert!(x.coeffs.iter().zip(a.coeffs).all(|(&x, a)| x == reduce::caddq(a)));
+    }
+  }
+
+  fn check_rounding<const GAMMA2: usize>() {
+    let mut rng = StdRng::seed_from_u64(GAMMA2 as u64);
+    for _ in 0..50 {
+      let a = random_poly(&mut rng, 0, Q_I32);
+      let (mut a1, mut a0) = (a, Poly::default());
+      unsafe { poly_decompose::<GAMMA2>(&mut a1, &mut a0) };
+      for i in 0..N {
+        let mut r0 = 0;
+        assert_eq!(a1.coeffs[i], rounding::decompose::<GAMMA2>(&mut r0, a.coeffs[i]));
+        assert_eq!(a0.coeffs[i], r0);
+      }
+
+      // Low parts around the hint boundaries
+      let g = GAMMA2 as i32;
+      let mut z0 = random_poly(&mut rng, -g - 2, g + 3);
+      let z1 = random_poly(&mut rng, 0, 2);
+      z0.coeffs[0] = -g;
+      let mut h = Poly::default();
+      let s = unsafe { poly_make_hint::<GAMMA2>(&mut h, &z0, &z1) };
+      let mut expected = 0;
+      for i in 0..N {
+        let r = rounding::make_hint::<GAMMA2>(z0.coeffs[i], z1.coeffs[i]) as i32;
+        assert_eq!(h.coeffs[i], r);
+        expected += r;
+      }
+      assert_eq!(s, expected);
+
+      let hints = random_poly(&mut rng, 0, 2);
+      let mut b = a;
+      unsafe { poly_use_hint::<GAMMA2>(&mut b, &hints) };
+      for i in 0..N {
+        assert_eq!(b.coeffs[i], rounding::use_hint::<GAMMA2>(a.coeffs[i], hints.coeffs[i] as u8));
+      }
+    }
+  }
+
+  #[test]
+  fn rounding_matches_scalar() {
+    if !available() {
+      return;
+    }
+    check_rounding::<{ (Q - 1) / 88 }>();
+    check_rounding::<{ (Q - 1) / 32 }>();
+  }
+}
@@ -0,0 +1,260 @@
+//! Minimal CBOR (RFC 8949) encoder and decoder, covering the deterministic
+//! subset needed for COSE: integers, byte and text strings, arrays, maps,
+//! tags and null. Indefinite lengths and floats are not supported.
+
+use anyhow::bail;
+
+pub const UNSIGNED: u8 = 0;
+pub const NEGATIVE: u8 = 1;
+pub const BYTES: u8 = 2;
+pub const TEXT: u8 = 3;
+pub const ARRAY: u8 = 4;
+pub const MAP: u8 = 5;
+pub const TAG: u8 = 6;
+pub const SIMPLE: u8 = 7;
+
+pub const NULL: u8 = 0xf6;
+
+/// Encode a major type and argument in the shortest form.
+pub fn head(major: u8, n: u64) -> Vec<u8> {
+    let m = major << 5;
+    match n {
+        0..=23 => vec![m | n as u8],
+        24..=0xff => vec![m | 24, n as u8],
+        0x100..=0xffff => [&[m | 25][..], &(n as u16).to_be_bytes()].concat(),
+        0x1_0000..=0xffff_ffff => [&[m | 26][..], &(n as u32).to_be_bytes()].concat(),
+        _ => [&[m | 27][..], &n.to_be_bytes()].concat(),
+    }
+}
+
+pub fn int(value: i64) -> Vec<u8> {
+    if value >= 0 {
+        head(UNSIGNED, value as u64)
+    } else {
+        head(NEGATIVE, !value as u64)
+    }
+}
+
+pub fn bytes(value: &[u8]) -> Vec<u8> {
+    [head(BYTES, value.len() as u64), value.to_vec()].concat()
+}
+
+pub fn text(value: &str) -> Vec<u8> {
+    [head(TEXT, value.len() as u64), value.as_bytes().to_vec()].concat()
+}
+
+pub fn array(items: &[&[u8]]) -> Vec<u8> {
+    let mut v = head(ARRAY, items.len() as u64);
+    for item in items {
+        v.extend_from_slice(item);
+    }
+    v
+}
+
+/// Encode a map from encoded keys and values. Entries are sorted by their
+/// encoded keys, as core deterministic encoding requires.
+pub fn map(entries: &[(&[u8], &[u8])]) -> Vec<u8> {
+    let mut entries = entries.to_vec();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    let mut v = head(MAP, entries.len() as u64);
+    for (key, value) in entries {
+        v.extend_from_slice(key);
+        v.extend_from_slice(value);
+    }
+    v
+}
+
+pub fn tag(n: u64, item: &[u8]) -> Vec<u8> {
+    [head(TAG, n), item.to_vec()].concat()
+}
+
+/// Sequential reader over CBOR encoded items.
+#[derive(Clone, Copy)]
+pub struct CborReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> CborReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        CborReader { data }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn peek_major(&self) -> Option<u8> {
+        self.data.first().map(|b| b >> 5)
+    }
+
+    pub fn peek_null(&self) -> bool {
+        self.data.first() == Some(&NULL)
+    }
+
+    /// Read an item head, returning the major type and argument. Arguments
+    /// must be in their shortest form.
+    pub fn read_head(&mut self) -> anyhow::Result<(u8, u64)> {
+        let (&first, rest) = match self.data.split_first() {
+            Some(x) => x,
+            None => bail!("truncated CBOR item"),
+        };
+        let (major, info) = (first >> 5, first & 0x1f);
+        let (n, len) = match info {
+            0..=23 => (info as u64, 0),
+            24..=27 => {
+                let len = 1usize << (info - 24);
+                if rest.len() < len {
+                    bail!("truncated CBOR item");
+                }
+                let n = rest[..len].iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+                let min = match len {
+                    1 => 24,
+                    2 => 0x100,
+                    4 => 0x1_0000,
+                    _ => 0x1_0000_0000,
+                };
+                if major != SIMPLE && n < min {
+                    bail!("non-minimal CBOR argument");
+                }
+                (n, len)
+            }
+            _ => bail!("indefinite length CBOR items are not supported"),
+        };
+        self.data = &rest[len..];
+        Ok((major, n))
+    }
+
+    fn read_expected(&mut self, major: u8) -> anyhow::Result<u64> {
+        let (m, n) = self.read_head()?;
+        if m != major {
+            bail!("unexpected CBOR major type {}, expected {}", m, major);
+        }
+        Ok(n)
+    }
+
+    pub fn read_int(&mut self) -> anyhow::Result<i64> {
+        match self.read_head()? {
+            (UNSIGNED, n) if n <= i64::MAX as u64 => Ok(n as i64),
+            (NEGATIVE, n) if n <= i64::MAX as u64 => Ok(!(n as i64)),
+            (UNSIGNED | NEGATIVE, _) => bail!("CBOR integer out of range"),
+            (m, _) => bail!("unexpected CBOR major type {}, expected an integer", m),
+        }
+    }
+
+    fn read_content(&mut self, len: u64) -> anyhow::Result<&'a [u8]> {
+        if (self.data.len() as u64) < len {
+            bail!("truncated CBOR item");
+        }
+        let (content, rest) = self.data.split_at(len as usize);
+        self.data = rest;
+        Ok(content)
+    }
+
+    pub fn read_bytes(&mut self) -> anyhow::Result<&'a [u8]> {
+        let len = self.read_expected(BYTES)?;
+        self.read_content(len)
+    }
+
+    pub fn read_text(&mut self) -> anyhow::Result<&'a str> {
+        let len = self.read_expected(TEXT)?;
+        Ok(std::str::from_utf8(self.read_content(len)?)?)
+    }
+
+    pub fn read_array(&mut self) -> anyhow::Result<u64> {
+        self.read_expected(ARRAY)
+    }
+
+    pub fn read_map(&mut self) -> anyhow::Result<u64> {
+        self.read_expected(MAP)
+    }
+
+    /// Read a tag number; the tagged item follows.
+    pub fn read_tag(&mut self) -> anyhow::Result<u64> {
+        self.read_expected(TAG)
+    }
+
+    pub fn read_null(&mut self) -> anyhow::Result<()> {
+        match self.read_head()? {
+            (SIMPLE, 22) => Ok(()),
+            _ => bail!("expected CBOR null"),
+        }
+    }
+
+    /// Skip one complete item and return its encoding.
+    pub fn read_raw(&mut self) -> anyhow::Result<&'a [u8]> {
+        let start = self.data;
+        self.skip(0)?;
+        Ok(&start[..start.len() - self.data.len()])
+    }
+
+    fn skip(&mut self, depth: usize) -> anyhow::Result<()> {
+        if depth > 16 {
+            bail!("CBOR nesting too deep");
+        }
+        let (major, n) = self.read_head()?;
+        match major {
+            BYTES | TEXT => {
+                self.read_content(n)?;
+            }
+            ARRAY => {
+                for _ in 0..n {
+                    self.skip(depth + 1)?;
+                }
+            }
+            MAP => {
+                for _ in 0..n.saturating_mul(2) {
+                    self.skip(depth + 1)?;
+                }
+            }
+            TAG => self.skip(depth + 1)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Fail if there is unread data left.
+    pub fn finish(&self) -> anyhow::Result<()> {
+        if !self.data.is_empty() {
+            bail!("trailing data after CBOR item");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_encoding() {
+        // RFC 8949 appendix A
+        assert_eq!(int(0), [0x00]);
+        assert_eq!(int(23), [0x17]);
+        assert_eq!(int(24), [0x18, 0x18]);
+        assert_eq!(int(1000), [0x19, 0x03, 0xe8]);
+        assert_eq!(int(1000000), [0x1a, 0x00, 0x0f, 0x42, 0x40]);
+        assert_eq!(int(-1), [0x20]);
+        assert_eq!(int(-100), [0x38, 0x63]);
+        assert_eq!(int(-48), [0x38, 0x2f]);
+        for v in [0, 23, 24, 255, 256, 65536, -1, -24, -25, -1000, i64::MAX, i64::MIN] {
+            let enc = int(v);
+            let mut r = CborReader::new(&enc);
+            assert_eq!(r.read_int().unwrap(), v);
+            r.finish().unwrap();
+        }
+        assert!(CborReader::new(&[0x18, 0x05]).read_int().is_err());
+    }
+
+    #[test]
+    fn nested_items() {
+        let enc = array(&[&text("a"), &map(&[(&int(-1), &bytes(&[1, 2])), (&int(1), &int(7))]), &[NULL]]);
+        assert_eq!(enc, [0x83, 0x61, 0x61, 0xa2, 0x01, 0x07, 0x20, 0x42, 0x01, 0x02, 0xf6]);
+        let mut r = CborReader::new(&enc);
+        assert_eq!(r.read_array().unwrap(), 3);
+        assert_eq!(r.read_text().unwrap(), "a");
+        assert_eq!(r.read_raw().unwrap(), &enc[3..10]);
+        assert!(r.peek_null());
+        r.read_null().unwrap();
+        r.finish().unwrap();
+    }
+}
@@ -0,0 +1,508 @@
+//! Certificate path validation for chains with ML-DSA-signed links.
+//!
+//! A path is built from the end-entity certificate up to one of the trust
+//! anchors through the supplied intermediates, matching issuer to subject
+//! names and key identifiers. When several certificates could have issued a
+//! link, each is tried in turn. ML-DSA signatures are checked with the
+//! parameter set named by the certificate's signature algorithm; other
+//! algorithms are handed to a `SignatureVerifier`, if one is configured.
+//! Positions in the path count from the end-entity certificate at 0; the
+//! trust anchor is last.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::algorithm::ParameterSet;
+use crate::asn1::oid_to_string;
+use crate::x509::{
+    find_key_identifier, key_usage, unix_time, Certificate, SignatureVerifier, ID_CE_AUTHORITY_KEY_IDENTIFIER,
+    ID_CE_BASIC_CONSTRAINTS, ID_CE_KEY_USAGE, ID_CE_SUBJECT_ALT_NAME, ID_CE_SUBJECT_KEY_IDENTIFIER,
+};
+
+/// Extensions the validator understands, others must not be critical.
+const KNOWN_EXTENSIONS: [&[u8]; 5] = [
+    ID_CE_BASIC_CONSTRAINTS,
+    ID_CE_KEY_USAGE,
+    ID_CE_SUBJECT_KEY_IDENTIFIER,
+    ID_CE_AUTHORITY_KEY_IDENTIFIER,
+    ID_CE_SUBJECT_ALT_NAME,
+];
+
+/// Why a link of the chain was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainErrorKind {
+    /// No trust anchor or intermediate has the certificate's issuer name.
+    IssuerNotFound,
+    /// Candidate issuers exist but none of their keys verifies the signature.
+    BadSignature,
+    /// The signature algorithm is not one of the ML-DSA parameter sets and
+    /// no verifier for other algorithms is configured.
+    UnsupportedAlgorithm(String),
+    NotYetValid { not_before: i64 },
+    Expired { not_after: i64 },
+    /// An issuing certificate lacks basicConstraints cA.
+    NotCa,
+    /// An issuing certificate has more intermediates below it than its pathLenConstraint.
+    PathLenExceeded { path_len: u64, intermediates: usize },
+    /// The keyUsage does not permit the use: keyCertSign for issuers, the
+    /// required usage for the end entity.
+    KeyUsage { required: u16, present: u16 },
+    UnknownCriticalExtension(String),
+    /// The path is longer than the configured maximum.
+    TooLong,
+    /// An extension could not be decoded.
+    Malformed(String),
+}
+
+/// A failed chain, with the position of the offending certificate in the
+/// path and its subject.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainError {
+    pub index: usize,
+    pub subject: String,
+    pub kind: ChainErrorKind,
+}
+
+impl fmt::Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "certificate {} ({}): ", self.index, self.subject)?;
+        match &self.kind {
+            ChainErrorKind::IssuerNotFound => write!(f, "issuer not found"),
+            ChainErrorKind::BadSignature => write!(f, "signature does not verify"),
+            ChainErrorKind::UnsupportedAlgorithm(oid) => write!(f, "unsupported signature algorithm {}", oid),
+            ChainErrorKind::NotYetValid { not_before } => write!(f, "not valid before {}", not_before),
+            ChainErrorKind::Expired { not_after } => write!(f, "expired at {}", not_after),
+            ChainErrorKind::NotCa => write!(f, "not a CA certificate"),
+            ChainErrorKind::PathLenExceeded { path_len, intermediates } => {
+                write!(f, "path length {} exceeds constraint {}", intermediates, path_len)
+            }
+            ChainErrorKind::KeyUsage { required, present } => {
+                write!(f, "key usage {:#x} does not include {:#x}", present, required)
+            }
+            ChainErrorKind::UnknownCriticalExtension(oid) => write!(f, "unknown critical extension {}", oid),
+            ChainErrorKind::TooLong => write!(f, "chain too long"),
+            ChainErrorKind::Malformed(e) => write!(f, "malformed extension: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+/// Validates certificate chains against a set of trust anchors.
+#[derive(Clone)]
+pub struct ChainValidator {
+    anchors: Vec<Certificate>,
+    intermediates: Vec<Certificate>,
+    time: Option<i64>,
+    leaf_key_usage: u16,
+    max_depth: usize,
+    classical: Option<Arc<dyn SignatureVerifier + Send + Sync>>,
+}
+
+impl fmt::Debug for ChainValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChainValidator")
+            .field("anchors", &self.anchors)
+            .field("intermediates", &self.intermediates)
+            .field("time", &self.time)
+            .field("leaf_key_usage", &self.leaf_key_usage)
+            .field("max_depth", &self.max_depth)
+            .field("classical", &self.classical.is_some())
+            .finish()
+    }
+}
+
+impl ChainValidator {
+    pub fn new(anchors: Vec<Certificate>) -> Self {
+        ChainValidator {
+            anchors,
+            intermediates: Vec::new(),
+            time: None,
+            leaf_key_usage: 0,
+            max_depth: 8,
+            classical: None,
+        }
+    }
+
+    pub fn intermediate(mut self, cert: Certificate) -> Self {
+        self.intermediates.push(cert);
+        self
+    }
+
+    pub fn intermediates(mut self, certs: impl IntoIterator<Item = Certificate>) -> Self {
+        self.intermediates.extend(certs);
+        self
+    }
+
+    /// Validate at the given Unix time instead of the current time.
+    pub fn at_time(mut self, time: i64) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// Require these `key_usage` bits on the end entity if it has a keyUsage extension.
+    pub fn leaf_key_usage(mut self, bits: u16) -> Self {
+        self.leaf_key_usage = bits;
+        self
+    }
+
+    /// Maximum number of certificates in a path, including the anchor. Defaults to 8.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Check links signed with algorithms other than ML-DSA with `verifier`.
+    /// Without one, such links fail with `UnsupportedAlgorithm`.
+    pub fn classical_verifier(mut self, verifier: impl SignatureVerifier + Send + Sync + 'static) -> Self {
+        self.classical = Some(Arc::new(verifier));
+        self
+    }
+
+    /// Validate `leaf` and return the path from it to the trust anchor.
+    pub fn validate(&self, leaf: &Certificate) -> Result<Vec<Certificate>, ChainError> {
+        let time = self.time.unwrap_or_else(unix_time);
+        let mut path = vec![leaf.clone()];
+        self.extend(&mut path, time)?;
+        Ok(path)
+    }
+
+    /// Complete `path` up to a trust anchor, trying every candidate issuer of
+    /// its last certificate. If none works, the error from the candidate that
+    /// got furthest is returned.
+    fn extend(&self, path: &mut Vec<Certificate>, time: i64) -> Result<(), ChainError> {
+        let index = path.len() - 1;
+        let cert = path[index].clone();
+        self.check_certificate(&cert, index, time)?;
+        if self.anchors.iter().any(|a| a.to_der() == cert.to_der()) {
+            return Ok(());
+        }
+        if path.len() >= self.max_depth {
+            return Err(error(&cert, index, ChainErrorKind::TooLong));
+        }
+
+        if ParameterSet::from_oid(&cert.signature_algorithm).is_none() && self.classical.is_none() {
+            let oid = oid_to_string(&cert.signature_algorithm);
+            return Err(error(&cert, index, ChainErrorKind::UnsupportedAlgorithm(oid)));
+        }
+
+        let aki = find_key_identifier(&cert.tbs.extensions, ID_CE_AUTHORITY_KEY_IDENTIFIER)
+            .map_err(|e| error(&cert, index, ChainErrorKind::Malformed(e.to_string())))?;
+        let candidates: Vec<Certificate> = self
+            .anchors
+            .iter()
+            .chain(self.intermediates.iter())
+            .filter(|c| c.tbs.subject == cert.tbs.issuer)
+            .filter(|c| !path.iter().any(|p| p.to_der() == c.to_der()))
+            .filter(|c| match (&aki, find_key_identifier(&c.tbs.extensions, ID_CE_SUBJECT_KEY_IDENTIFIER)) {
+                (Some(aki), Ok(Some(ski))) => *aki == ski,
+                _ => true,
+            })
+            .cloned()
+            .collect();
+        if candidates.is_empty() {
+            return Err(error(&cert, index, ChainErrorKind::IssuerNotFound));
+        }
+
+        let mut best: Option<ChainError> = None;
+        for issuer in candidates {
+            let result = self.verify_link(&cert, &issuer, index).and_then(|()| self.check_issuer(&issuer, index + 1)).and_then(|()| {
+                path.push(issuer);
+                let result = self.extend(path, time);
+                if result.is_err() {
+                    path.truncate(index + 1);
+                }
+                result
+            });
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) if best.as_ref().is_none_or(|b| e.index > b.index) => best = Some(e),
+                Err(_) => {}
+            }
+        }
+        Err(best.unwrap())
+    }
+
+    /// Check the signature of `cert` with the key of `issuer`.
+    fn verify_link(&self, cert: &Certificate, issuer: &Certificate, index: usize) -> Result<(), ChainError> {
+        let spki = &issuer.tbs.subject_public_key_info;
+        let verified = match &self.classical {
+            Some(classical) => cert.verify_signature_with(spki, classical.as_ref()),
+            None => cert.verify_signature(spki),
+        };
+        verified.map_err(|_| error(cert, index, ChainErrorKind::BadSignature))
+    }
+
+    /// Checks that apply to every certificate in the path.
+    fn check_certificate(&self, cert: &Certificate, index: usize, time: i64) -> Result<(), ChainError> {
+        let validity = cert.tbs.validity;
+        if time < validity.not_before {
+            return Err(error(cert, index, ChainErrorKind::NotYetValid { not_before: validity.not_before }));
+        }
+        if time > validity.not_after {
+            return Err(error(cert, index, ChainErrorKind::Expired { not_after: validity.not_after }));
+        }
+        if let Some(ext) = cert.tbs.extensions.iter().find(|e| e.critical && !KNOWN_EXTENSIONS.contains(&e.oid.as_slice())) {
+            return Err(error(cert, index, ChainErrorKind::UnknownCriticalExtension(oid_to_string(&ext.oid))));
+        }
+        if index == 0 && self.leaf_key_usage != 0 {
+            let usage = cert.key_usage().map_err(|e| error(cert, index, ChainErrorKind::Malformed(e.to_string())))?;
+            if let Some(present) = usage {
+                if present & self.leaf_key_usage != self.leaf_key_usage {
+                    return Err(error(cert, index, ChainErrorKind::KeyUsage { required: self.leaf_key_usage, present }));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks on a certificate that issued the one below it.
+    fn check_issuer(&self, cert: &Certificate, index: usize) -> Result<(), ChainError> {
+        let malformed = |e: anyhow::Error| error(cert, index, ChainErrorKind::Malformed(e.to_string()));
+        match cert.basic_constraints().map_err(malformed)? {
+            Some(bc) if bc.ca => {
+                // Intermediates between this certificate and the end entity
+                let intermediates = index - 1;
+                if let Some(path_len) = bc.path_len {
+                    if intermediates as u64 > path_len {
+                        return Err(error(cert, index, ChainErrorKind::PathLenExceeded { path_len, intermediates }));
+                    }
+                }
+            }
+            _ => return Err(error(cert, index, ChainErrorKind::NotCa)),
+        }
+        if let Some(present) = cert.key_usage().map_err(malformed)? {
+            if present & key_usage::KEY_CERT_SIGN == 0 {
+                return Err(error(cert, index, ChainErrorKind::KeyUsage { required: key_usage::KEY_CERT_SIGN, present }));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn error(cert: &Certificate, index: usize, kind: ChainErrorKind) -> ChainError {
+    ChainError { index, subject: cert.tbs.subject.to_string(), kind }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asn1::*;
+    use crate::x509::{encode_time, CertificateBuilder, Extension, Name, Validity};
+    use crate::{Dilithium2, Dilithium3, Dilithium5};
+
+    const NOW: i64 = 1_750_000_000;
+    const DAY: i64 = 86400;
+
+    fn validity() -> Validity {
+        Validity { not_before: NOW - DAY, not_after: NOW + 365 * DAY }
+    }
+
+    struct Pki {
+        root: Certificate,
+        intermediate: Certificate,
+        leaf: Certificate,
+    }
+
+    /// Dilithium5 root, Dilithium3 intermediate, Dilithium2 leaf.
+    fn build_pki(root_path_len: Option<u64>, intermediate_ca: bool, leaf_validity: Validity) -> Pki {
+        let (root_sk, root_pk) = Dilithium5::key_gen(None).unwrap();
+        let builder = CertificateBuilder::new(Name::new().common_name("Root"), Dilithium5::to_public_key_der(&root_pk).unwrap(), validity())
+            .ca(root_path_len)
+            .unwrap();
+        let root = Dilithium5::sign_certificate(builder, &root_sk).unwrap();
+
+        let (int_sk, int_pk) = Dilithium3::key_gen(None).unwrap();
+        let builder = CertificateBuilder::new(Name::new().common_name("Intermediate"), Dilithium3::to_public_key_der(&int_pk).unwrap(), validity());
+        let builder = if intermediate_ca { builder.ca(Some(0)).unwrap() } else { builder.leaf().unwrap() };
+        let intermediate = Dilithium5::sign_certificate(builder.issued_by(&root).unwrap(), &root_sk).unwrap();
+
+        let (_, leaf_pk) = Dilithium2::key_gen(None).unwrap();
+        let builder = CertificateBuilder::new(Name::new().common_name("Leaf"), Dilithium2::to_public_key_der(&leaf_pk).unwrap(), leaf_validity)
+            .issued_by(&intermediate)
+            .unwrap()
+            .leaf()
+            .unwrap();
+        let leaf = Dilithium3::sign_certificate(builder, &int_sk).unwrap();
+        Pki { root, intermediate, leaf }
+    }
+
+    #[test]
+    fn valid_chain() {
+        let pki = build_pki(None, true, validity());
+        let validator = ChainValidator::new(vec![pki.root.clone()])
+            .intermediate(pki.intermediate.clone())
+            .at_time(NOW)
+            .leaf_key_usage(key_usage::DIGITAL_SIGNATURE);
+        let path = validator.validate(&pki.leaf).unwrap();
+        assert_eq!(path, vec![pki.leaf.clone(), pki.intermediate.clone(), pki.root.clone()]);
+        // The anchor itself is a valid path of length one
+        assert_eq!(ChainValidator::new(vec![pki.root.clone()]).at_time(NOW).validate(&pki.root).unwrap().len(), 1);
+
+        let err = validator.clone().leaf_key_usage(key_usage::KEY_CERT_SIGN).validate(&pki.leaf).unwrap_err();
+        assert_eq!(err.index, 0);
+        assert!(matches!(err.kind, ChainErrorKind::KeyUsage { .. }));
+        assert_eq!(validator.max_depth(2).validate(&pki.leaf).unwrap_err().kind, ChainErrorKind::TooLong);
+    }
+
+    #[test]
+    fn reports_failing_link() {
+        let pki = build_pki(None, true, validity());
+        let err = ChainValidator::new(vec![pki.root.clone()]).at_time(NOW).validate(&pki.leaf).unwrap_err();
+        assert_eq!((err.index, err.kind), (0, ChainErrorKind::IssuerNotFound));
+
+        let err = ChainValidator::new(vec![pki.root.clone()])
+            .intermediate(pki.intermediate.clone())
+            .at_time(NOW + 400 * DAY)
+            .validate(&pki.leaf)
+            .unwrap_err();
+        assert_eq!(err.index, 0);
+        assert!(matches!(err.kind, ChainErrorKind::Expired { .. }));
+        assert_eq!(err.to_string().split(':').next().unwrap(), "certificate 0 (CN=Leaf)");
+
+        // A different root with the same name does not verify the intermediate
+        let other = build_pki(None, true, validity());
+        let err = ChainValidator::new(vec![other.root])
+            .intermediate(pki.intermediate.clone())
+            .at_time(NOW)
+            .validate(&pki.leaf)
+            .unwrap_err();
+        assert_eq!((err.index, err.kind), (1, ChainErrorKind::IssuerNotFound));
+    }
+
+    #[test]
+    fn enforces_constraints() {
+        let pki_len = build_pki(Some(0), true, validity());
+        let err = ChainValidator::new(vec![pki_len.root.clone()])
+            .intermediate(pki_len.intermediate.clone())
+            .at_time(NOW)
+            .validate(&pki_len.leaf)
+            .unwrap_err();
+        assert_eq!((err.index, err.kind), (2, ChainErrorKind::PathLenExceeded { path_len: 0, intermediates: 1 }));
+
+        let not_ca = build_pki(None, false, validity());
+        let err = ChainValidator::new(vec![not_ca.root.clone()])
+            .intermediate(not_ca.intermediate.clone())
+            .at_time(NOW)
+            .validate(&not_ca.leaf)
+            .unwrap_err();
+        assert_eq!((err.index, err.kind), (1, ChainErrorKind::NotCa));
+
+        let future = build_pki(None, true, Validity { not_before: NOW + DAY, not_after: NOW + 2 * DAY });
+        let err = ChainValidator::new(vec![future.root.clone()])
+            .intermediate(future.intermediate.clone())
+            .at_time(NOW)
+            .validate(&future.leaf)
+            .unwrap_err();
+        assert_eq!((err.index, err.kind), (0, ChainErrorKind::NotYetValid { not_before: NOW + DAY }));
+    }
+
+    #[test]
+    fn rejects_unknown_critical_extension() {
+        let (sk, pk) = Dilithium2::key_gen(None).unwrap();
+        let ext = Extension { oid: vec![0x2a, 0x03, 0x04], critical: true, value: vec![0x05, 0x00] };
+        let builder = CertificateBuilder::new(Name::new().common_name("Self"), Dilithium2::to_public_key_der(&pk).unwrap(), validity())
+            .ca(None)
+            .unwrap()
+            .extension(ext);
+        let cert = Dilithium2::sign_certificate(builder, &sk).unwrap();
+        let err = ChainValidator::new(vec![cert.clone()]).at_time(NOW).validate(&cert).unwrap_err();
+        assert_eq!(err.kind, ChainErrorKind::UnknownCriticalExtension("1.2.3.4".to_string()));
+    }
+
+    #[test]
+    fn backtracks_over_candidate_issuers() {
+        let pki = build_pki(None, true, validity());
+        // Same name and key as the intermediate, but not a CA and signed by
+        // an unrelated key, so the leaf's signature verifies under both
+        let (other_sk, _) = Dilithium5::key_gen(None).unwrap();
+        let builder = CertificateBuilder::new(Name::new().common_name("Intermediate"), pki.intermediate.tbs.subject_public_key_info.clone(), validity())
+            .leaf()
+            .unwrap()
+            .issuer(Name::new().common_name("Root"));
+        let twin = Dilithium5::sign_certificate(builder, &other_sk).unwrap();
+
+        let validator = ChainValidator::new(vec![pki.root.clone()]).at_time(NOW);
+        let err = validator.clone().intermediate(twin.clone()).validate(&pki.leaf).unwrap_err();
+        assert_eq!((err.index, err.kind), (1, ChainErrorKind::NotCa));
+        let path = validator.intermediates([twin, pki.intermediate.clone()]).validate(&pki.leaf).unwrap();
+        assert_eq!(path, vec![pki.leaf.clone(), pki.intermediate.clone(), pki.root.clone()]);
+    }
+
+    /// sha256WithRSAEncryption, 1.2.840.113549.1.1.11
+    const SHA256_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b];
+
+    /// Stand-in for an RSA signature so the test needs no RSA implementation.
+    fn fake_rsa_signature(spki: &[u8], message: &[u8]) -> Vec<u8> {
+        let mut sig = vec![0u8; 64];
+        crate::fips202::shake256(&mut sig, 64, &[spki, message].concat(), spki.len() + message.len());
+        sig
+    }
+
+    /// Sign `tbs` with the fake RSA algorithm, writing NULL parameters in
+    /// both AlgorithmIdentifiers as RSA signers do.
+    fn sign_rsa(tbs: &crate::x509::TbsCertificate, issuer_spki: &[u8]) -> Certificate {
+        let alg = sequence(&[&oid(SHA256_WITH_RSA), &[NULL, 0]]);
+        let tbs_der = sequence(&[
+            &tlv(context_constructed(0), &small_integer(2)),
+            &integer(&tbs.serial_number),
+            &alg,
+            &tbs.issuer.to_der(),
+            &sequence(&[&encode_time(tbs.validity.not_before), &encode_time(tbs.validity.not_after)]),
+            &tbs.subject.to_der(),
+            &tbs.subject_public_key_info,
+            &tlv(context_constructed(3), &Extension::encode_list(&tbs.extensions)),
+        ]);
+        let signature = fake_rsa_signature(issuer_spki, &tbs_der);
+        Certificate::from_der(&sequence(&[&tbs_der, &alg, &bit_string(&signature)])).unwrap()
+    }
+
+    #[test]
+    fn verifies_classical_links_with_hook() {
+        // rsaEncryption key with NULL parameters; the modulus is not a real key
+        let rsa_spki = sequence(&[
+            &sequence(&[&oid(&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01]), &[NULL, 0]]),
+            &bit_string(&sequence(&[&integer(&[0xc5; 256]), &small_integer(65537)])),
+        ]);
+        let root_tbs = CertificateBuilder::new(Name::new().common_name("RSA Root"), rsa_spki.clone(), validity())
+            .extension(Extension::basic_constraints(true, None))
+            .extension(Extension::key_usage(key_usage::KEY_CERT_SIGN))
+            .extension(Extension::subject_key_identifier(&[7; 20]))
+            .build(SHA256_WITH_RSA);
+        let root = sign_rsa(&root_tbs, &rsa_spki);
+        assert_eq!(root.signature_algorithm, SHA256_WITH_RSA);
+
+        let (int_sk, int_pk) = Dilithium3::key_gen(None).unwrap();
+        let int_tbs = CertificateBuilder::new(Name::new().common_name("Intermediate"), Dilithium3::to_public_key_der(&int_pk).unwrap(), validity())
+            .ca(None)
+            .unwrap()
+            .issued_by(&root)
+            .unwrap()
+            .build(SHA256_WITH_RSA);
+        let intermediate = sign_rsa(&int_tbs, &rsa_spki);
+
+        let (_, leaf_pk) = Dilithium2::key_gen(None).unwrap();
+        let builder = CertificateBuilder::new(Name::new().common_name("Leaf"), Dilithium2::to_public_key_der(&leaf_pk).unwrap(), validity())
+            .issued_by(&intermediate)
+            .unwrap()
+            .leaf()
+            .unwrap();
+        let leaf = Dilithium3::sign_certificate(builder, &int_sk).unwrap();
+
+        let validator = ChainValidator::new(vec![root.clone()]).intermediate(intermediate.clone()).at_time(NOW);
+        let err = validator.validate(&leaf).unwrap_err();
+        assert_eq!((err.index, err.kind), (1, ChainErrorKind::UnsupportedAlgorithm("1.2.840.113549.1.1.11".to_string())));
+
+        let hook = |algorithm: &[u8], message: &[u8], signature: &[u8], spki: &[u8]| -> anyhow::Result<()> {
+            anyhow::ensure!(algorithm == SHA256_WITH_RSA, "unexpected algorithm");
+            anyhow::ensure!(signature == fake_rsa_signature(spki, message), "bad signature");
+            Ok(())
+        };
+        let path = validator.clone().classical_verifier(hook).validate(&leaf).unwrap();
+        assert_eq!(path, vec![leaf.clone(), intermediate.clone(), root.clone()]);
+
+        let reject = |_: &[u8], _: &[u8], _: &[u8], _: &[u8]| -> anyhow::Result<()> { anyhow::bail!("rejected") };
+        let err = validator.classical_verifier(reject).validate(&leaf).unwrap_err();
+        assert_eq!((err.index, err.kind), (1, ChainErrorKind::BadSignature));
+    }
+}
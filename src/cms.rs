@@ -0,0 +1,560 @@
+//! CMS SignedData (RFC 5652) with ML-DSA signers, following the IETF LAMPS
+//! ML-DSA in CMS document.
+//!
+//! Without signed attributes the signature is computed in pure mode over the
+//! content itself. With signed attributes it is computed over the DER of the
+//! SET OF Attribute holding the content type and the message digest of the
+//! content. SHA-512 is the default digest algorithm, SHAKE256 with 512 bits
+//! of output is also supported.
+
+use anyhow::{anyhow, bail};
+use sha2::{Digest, Sha512};
+
+use crate::algorithm::{Dilithium, ParameterSet};
+use crate::asn1::*;
+use crate::fips202::shake256;
+use crate::spki::decode_spki;
+use crate::x509::{find_key_identifier, Certificate, Name, ID_CE_SUBJECT_KEY_IDENTIFIER};
+use crate::symmetric::StreamBackend;
+
+#[cfg(feature = "pem")]
+const PEM_LABEL: &str = "CMS";
+
+/// id-data, 1.2.840.113549.1.7.1
+pub const ID_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x01];
+/// id-signedData, 1.2.840.113549.1.7.2
+pub const ID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+/// id-contentType, 1.2.840.113549.1.9.3
+pub const ID_CONTENT_TYPE: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x03];
+/// id-messageDigest, 1.2.840.113549.1.9.4
+pub const ID_MESSAGE_DIGEST: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x04];
+/// id-sha512, 2.16.840.1.101.3.4.2.3
+pub const ID_SHA512: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03];
+/// id-shake256, 2.16.840.1.101.3.4.2.12
+pub const ID_SHAKE256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x0c];
+
+/// Digest algorithm for the messageDigest attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    Sha512,
+    /// SHAKE256 with 512 bits of output, as in RFC 8702
+    Shake256,
+}
+
+impl DigestAlgorithm {
+    pub fn oid(self) -> &'static [u8] {
+        match self {
+            DigestAlgorithm::Sha512 => ID_SHA512,
+            DigestAlgorithm::Shake256 => ID_SHAKE256,
+        }
+    }
+
+    pub fn from_oid(oid: &[u8]) -> Option<Self> {
+        match oid {
+            ID_SHA512 => Some(DigestAlgorithm::Sha512),
+            ID_SHAKE256 => Some(DigestAlgorithm::Shake256),
+            _ => None,
+        }
+    }
+
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            DigestAlgorithm::Sha512 => Sha512::digest(data).to_vec(),
+            DigestAlgorithm::Shake256 => {
+                let mut out = vec![0u8; 64];
+                shake256(&mut out, 64, data, data.len());
+                out
+            }
+        }
+    }
+}
+
+/// Read an AlgorithmIdentifier for a digest, whose parameters may be absent
+/// or NULL.
+fn read_digest_algorithm(r: &mut DerReader) -> anyhow::Result<Vec<u8>> {
+    let mut alg = r.read_sequence()?;
+    let oid = alg.read_oid()?.to_vec();
+    if !alg.is_empty() {
+        let (tag, value, _) = alg.read_raw()?;
+        if tag != 0x05 || !value.is_empty() {
+            bail!("unexpected digest algorithm parameters");
+        }
+    }
+    alg.finish()?;
+    Ok(oid)
+}
+
+/// How a SignerInfo names the signer's certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignerIdentifier {
+    IssuerAndSerialNumber { issuer: Name, serial_number: Vec<u8> },
+    SubjectKeyIdentifier(Vec<u8>),
+}
+
+impl SignerIdentifier {
+    /// Identify the signer by the issuer and serial number of its certificate.
+    pub fn from_certificate(cert: &Certificate) -> Self {
+        SignerIdentifier::IssuerAndSerialNumber { issuer: cert.tbs.issuer.clone(), serial_number: cert.tbs.serial_number.clone() }
+    }
+
+    fn matches(&self, cert: &Certificate) -> bool {
+        match self {
+            SignerIdentifier::IssuerAndSerialNumber { issuer, serial_number } => {
+                *issuer == cert.tbs.issuer && *serial_number == cert.tbs.serial_number
+            }
+            SignerIdentifier::SubjectKeyIdentifier(id) => {
+                match find_key_identifier(&cert.tbs.extensions, ID_CE_SUBJECT_KEY_IDENTIFIER) {
+                    Ok(Some(ski)) => ski == *id,
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    fn to_der(&self) -> Vec<u8> {
+        match self {
+            SignerIdentifier::IssuerAndSerialNumber { issuer, serial_number } => {
+                sequence(&[&issuer.to_der(), &integer(serial_number)])
+            }
+            SignerIdentifier::SubjectKeyIdentifier(id) => tlv(context(0), id),
+        }
+    }
+
+    fn decode(r: &mut DerReader) -> anyhow::Result<Self> {
+        if r.peek_tag() == Some(context(0)) {
+            return Ok(SignerIdentifier::SubjectKeyIdentifier(r.read(context(0))?.to_vec()));
+        }
+        let mut ias = r.read_sequence()?;
+        let issuer = Name::decode(&mut ias)?;
+        let serial_number = ias.read_integer()?.to_vec();
+        ias.finish()?;
+        Ok(SignerIdentifier::IssuerAndSerialNumber { issuer, serial_number })
+    }
+
+    /// CMSVersion of a SignerInfo using this identifier.
+    fn version(&self) -> u64 {
+        match self {
+            SignerIdentifier::IssuerAndSerialNumber { .. } => 1,
+            SignerIdentifier::SubjectKeyIdentifier(_) => 3,
+        }
+    }
+}
+
+/// A decoded SignerInfo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignerInfo {
+    pub sid: SignerIdentifier,
+    pub digest_algorithm: Vec<u8>,
+    /// Content of the signedAttrs SET, if present.
+    pub signed_attributes: Option<Vec<u8>>,
+    pub signature_algorithm: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl SignerInfo {
+    fn decode(r: &mut DerReader) -> anyhow::Result<Self> {
+        let mut si = r.read_sequence()?;
+        let version = si.read_small_integer()?;
+        let sid = SignerIdentifier::decode(&mut si)?;
+        if version != sid.version() {
+            bail!("SignerInfo version {} does not match its signer identifier", version);
+        }
+        let digest_algorithm = read_digest_algorithm(&mut si)?;
+        let signed_attributes = si.read_optional(context_constructed(0))?.map(<[u8]>::to_vec);
+        let signature_algorithm = si.read_algorithm_identifier()?.to_vec();
+        let signature = si.read_octet_string()?.to_vec();
+        // Unsigned attributes are ignored
+        si.read_optional(context_constructed(1))?;
+        si.finish()?;
+        Ok(SignerInfo { sid, digest_algorithm, signed_attributes, signature_algorithm, signature })
+    }
+
+    /// Check the signature with a packed public key, over `content`.
+    fn verify(&self, content: &[u8], set: ParameterSet, pk: &[u8]) -> anyhow::Result<()> {
+        if self.signature_algorithm != set.oid() {
+            bail!("signature algorithm {} does not match the key", oid_to_string(&self.signature_algorithm));
+        }
+        let signed = match &self.signed_attributes {
+            Some(attrs) => {
+                check_signed_attributes(attrs, &self.digest_algorithm, content)?;
+                // Signed as a DER SET OF, not with the [0] IMPLICIT tag
+                tlv(SET, attrs)
+            }
+            None => content.to_vec(),
+        };
+        set.verify(signed, self.signature.clone(), pk.to_vec(), None)
+            .map_err(|e| anyhow!("invalid SignerInfo signature: {:?}", e))
+    }
+}
+
+/// The contentType and messageDigest attributes must be present once, with
+/// a single value each, and match the content.
+fn check_signed_attributes(attrs: &[u8], digest_algorithm: &[u8], content: &[u8]) -> anyhow::Result<()> {
+    let digest = DigestAlgorithm::from_oid(digest_algorithm)
+        .ok_or_else(|| anyhow!("unsupported digest algorithm {}", oid_to_string(digest_algorithm)))?;
+    let mut r = DerReader::new(attrs);
+    let (mut content_type, mut message_digest) = (None, None);
+    while !r.is_empty() {
+        let mut attr = r.read_sequence()?;
+        let attr_type = attr.read_oid()?;
+        let mut values = attr.read_nested(SET)?;
+        attr.finish()?;
+        let slot = match attr_type {
+            ID_CONTENT_TYPE => &mut content_type,
+            ID_MESSAGE_DIGEST => &mut message_digest,
+            _ => continue,
+        };
+        if slot.is_some() {
+            bail!("duplicate signed attribute {}", oid_to_string(attr_type));
+        }
+        let (tag, value, _) = values.read_raw()?;
+        values.finish()?;
+        *slot = Some((tag, value));
+    }
+    match content_type {
+        Some((OBJECT_IDENTIFIER, ID_DATA)) => {}
+        _ => bail!("contentType attribute missing or not id-data"),
+    }
+    match message_digest {
+        Some((OCTET_STRING, value)) if value == digest.digest(content) => Ok(()),
+        Some(_) => bail!("messageDigest does not match the content"),
+        None => bail!("messageDigest attribute missing"),
+    }
+}
+
+/// Builder for a SignedData with a single ML-DSA signer.
+#[derive(Debug, Clone)]
+pub struct SignedDataBuilder {
+    content: Vec<u8>,
+    detached: bool,
+    signed_attributes: bool,
+    digest: DigestAlgorithm,
+    certificates: Vec<Certificate>,
+}
+
+impl SignedDataBuilder {
+    /// Attached content, with signed attributes and SHA-512.
+    pub fn new(content: &[u8]) -> Self {
+        SignedDataBuilder {
+            content: content.to_vec(),
+            detached: false,
+            signed_attributes: true,
+            digest: DigestAlgorithm::Sha512,
+            certificates: Vec::new(),
+        }
+    }
+
+    /// Leave the content out of the encapsulated content info.
+    pub fn detached(mut self, detached: bool) -> Self {
+        self.detached = detached;
+        self
+    }
+
+    /// Sign the content directly instead of over signed attributes.
+    pub fn signed_attributes(mut self, signed_attributes: bool) -> Self {
+        self.signed_attributes = signed_attributes;
+        self
+    }
+
+    pub fn digest(mut self, digest: DigestAlgorithm) -> Self {
+        self.digest = digest;
+        self
+    }
+
+    /// Include a certificate, e.g. the signer's, in the SignedData.
+    pub fn certificate(mut self, cert: Certificate) -> Self {
+        self.certificates.push(cert);
+        self
+    }
+
+    fn signed_attributes_der(&self) -> Vec<u8> {
+        let content_type = sequence(&[&oid(ID_CONTENT_TYPE), &set_of(&[&oid(ID_DATA)])]);
+        let message_digest = sequence(&[&oid(ID_MESSAGE_DIGEST), &set_of(&[&octet_string(&self.digest.digest(&self.content))])]);
+        set_of(&[&content_type, &message_digest])
+    }
+}
+
+/// A decoded SignedData.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedData {
+    pub digest_algorithms: Vec<Vec<u8>>,
+    /// `None` for detached content.
+    pub content: Option<Vec<u8>>,
+    pub certificates: Vec<Certificate>,
+    pub signer_infos: Vec<SignerInfo>,
+}
+
+impl SignedData {
+    /// Decode a ContentInfo holding a SignedData with id-data content.
+    pub fn from_der(der: &[u8]) -> anyhow::Result<Self> {
+        let mut outer = DerReader::new(der);
+        let mut ci = outer.read_sequence()?;
+        outer.finish()?;
+        if ci.read_oid()? != ID_SIGNED_DATA {
+            bail!("ContentInfo does not hold SignedData");
+        }
+        let mut explicit = ci.read_nested(context_constructed(0))?;
+        ci.finish()?;
+        let mut sd = explicit.read_sequence()?;
+        explicit.finish()?;
+
+        let version = sd.read_small_integer()?;
+        if version != 1 && version != 3 {
+            bail!("unsupported SignedData version {}", version);
+        }
+        let mut algs = sd.read_nested(SET)?;
+        let mut digest_algorithms = Vec::new();
+        while !algs.is_empty() {
+            digest_algorithms.push(read_digest_algorithm(&mut algs)?);
+        }
+
+        let mut eci = sd.read_sequence()?;
+        if eci.read_oid()? != ID_DATA {
+            bail!("only id-data content is supported");
+        }
+        let content = match eci.read_optional(context_constructed(0))? {
+            Some(explicit) => {
+                let mut r = DerReader::new(explicit);
+                let content = r.read_octet_string()?.to_vec();
+                r.finish()?;
+                Some(content)
+            }
+            None => None,
+        };
+        eci.finish()?;
+
+        let mut certificates = Vec::new();
+        if let Some(certs) = sd.read_optional(context_constructed(0))? {
+            let mut r = DerReader::new(certs);
+            while !r.is_empty() {
+                let (_, _, cert) = r.read_raw()?;
+                certificates.push(Certificate::from_der(cert)?);
+            }
+        }
+        // CRLs are ignored
+        sd.read_optional(context_constructed(1))?;
+
+        let mut infos = sd.read_nested(SET)?;
+        sd.finish()?;
+        let mut signer_infos = Vec::new();
+        while !infos.is_empty() {
+            signer_infos.push(SignerInfo::decode(&mut infos)?);
+        }
+
+        Ok(SignedData { digest_algorithms, content, certificates, signer_infos })
+    }
+
+    #[cfg(feature = "pem")]
+    pub fn from_pem(pem: &str) -> anyhow::Result<Self> {
+        SignedData::from_der(&pem_decode(PEM_LABEL, pem)?)
+    }
+
+    fn content<'a>(&'a self, detached_content: Option<&'a [u8]>) -> anyhow::Result<&'a [u8]> {
+        match (&self.content, detached_content) {
+            (Some(content), None) => Ok(content),
+            (None, Some(content)) => Ok(content),
+            (Some(_), Some(_)) => bail!("SignedData content is not detached"),
+            (None, None) => bail!("SignedData content is detached"),
+        }
+    }
+
+    /// Verify that a SignerInfo is signed by the key in the DER
+    /// SubjectPublicKeyInfo, and return the content.
+    pub fn verify(&self, detached_content: Option<&[u8]>, spki: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let content = self.content(detached_content)?;
+        let (algorithm, key) = decode_spki(spki)?;
+        let set = ParameterSet::from_oid(algorithm)
+            .ok_or_else(|| anyhow!("unsupported public key algorithm {}", oid_to_string(algorithm)))?;
+        let mut last_err = anyhow!("no SignerInfo for an {} key", set.name());
+        for info in self.signer_infos.iter().filter(|i| i.signature_algorithm == set.oid()) {
+            match info.verify(content, set, key) {
+                Ok(()) => return Ok(content.to_vec()),
+                Err(e) => last_err = e,
+            }
+        }
+        Err(last_err)
+    }
+
+    /// Verify the SignerInfo identifying `cert` with the certificate's key,
+    /// and return the content. The certificate itself is not validated.
+    pub fn verify_with_certificate(&self, detached_content: Option<&[u8]>, cert: &Certificate) -> anyhow::Result<Vec<u8>> {
+        let content = self.content(detached_content)?;
+        let info = self
+            .signer_infos
+            .iter()
+            .find(|i| i.sid.matches(cert))
+            .ok_or_else(|| anyhow!("no SignerInfo for the certificate"))?;
+        let (algorithm, key) = decode_spki(&cert.tbs.subject_public_key_info)?;
+        let set = ParameterSet::from_oid(algorithm)
+            .ok_or_else(|| anyhow!("unsupported public key algorithm {}", oid_to_string(algorithm)))?;
+        info.verify(content, set, key)?;
+        Ok(content.to_vec())
+    }
+}
+
+impl<
+    const K: usize,
+    const L: usize,
+    const ETA: usize,
+    const TAU: usize,
+    const BETA: usize,
+    const GAMMA1: usize,
+    const GAMMA2: usize,
+    const OMEGA: usize,
+    const PUBLICKEYBYTES: usize,
+    const SECRETKEYBYTES: usize,
+    const POLY_UNIFORM_NBLOCKS: usize,
+    S: StreamBackend,
+    const POLY_UNIFORM_ETA_NBLOCKS: usize,
+    const POLYETA_PACKEDBYTES: usize,
+    const SIGNBYTES: usize,
+    const POLYW1_PACKEDBYTES: usize,
+    const CTILDEBYTES: usize,
+    const POLYZ_PACKEDBYTES: usize,
+    const POLYVECH_PACKEDBYTES: usize,
+    const POLY_UNIFORM_GAMMA1_NBLOCKS: usize,
+>
+Dilithium<
+    K,
+    L,
+    ETA,
+    TAU,
+    BETA,
+    GAMMA1,
+    GAMMA2,
+    OMEGA,
+    PUBLICKEYBYTES,
+    SECRETKEYBYTES,
+    POLY_UNIFORM_NBLOCKS,
+    S,
+    POLY_UNIFORM_ETA_NBLOCKS,
+    POLYETA_PACKEDBYTES,
+    SIGNBYTES,
+    POLYW1_PACKEDBYTES,
+    CTILDEBYTES,
+    POLYZ_PACKEDBYTES,
+    POLYVECH_PACKEDBYTES,
+    POLY_UNIFORM_GAMMA1_NBLOCKS,
+>
+{
+    /// Produce a DER ContentInfo holding a SignedData signed with `sk`.
+    pub fn sign_cms(builder: SignedDataBuilder, sk: &[u8], sid: SignerIdentifier) -> anyhow::Result<Vec<u8>> {
+        let set = Self::parameter_set().ok_or_else(|| anyhow!("parameters do not match an ML-DSA parameter set"))?;
+        if sk.len() != SECRETKEYBYTES {
+            bail!("secret key must be {} bytes", SECRETKEYBYTES);
+        }
+        let digest_algorithm = algorithm_identifier(builder.digest.oid());
+
+        let (signed_attributes, to_sign) = if builder.signed_attributes {
+            let attrs = builder.signed_attributes_der();
+            // The SET is signed, but carried as [0] IMPLICIT
+            let mut tagged = attrs.clone();
+            tagged[0] = context_constructed(0);
+            (Some(tagged), attrs)
+        } else {
+            (None, builder.content.clone())
+        };
+        let signature = Self::sign(to_sign, sk.to_vec(), None, true)?;
+
+        let mut signer_info = vec![small_integer(sid.version()), sid.to_der(), digest_algorithm.clone()];
+        signer_info.extend(signed_attributes);
+        signer_info.push(algorithm_identifier(set.oid()));
+        signer_info.push(octet_string(&signature));
+        let signer_info = sequence(&signer_info.iter().map(|x| x.as_slice()).collect::<Vec<_>>());
+
+        let eci = if builder.detached {
+            sequence(&[&oid(ID_DATA)])
+        } else {
+            sequence(&[&oid(ID_DATA), &tlv(context_constructed(0), &octet_string(&builder.content))])
+        };
+        let mut fields = vec![small_integer(sid.version()), set_of(&[&digest_algorithm]), eci];
+        if !builder.certificates.is_empty() {
+            let certs: Vec<Vec<u8>> = builder.certificates.iter().map(|c| c.to_der()).collect();
+            fields.push(tlv(context_constructed(0), &certs.concat()));
+        }
+        fields.push(set_of(&[&signer_info]));
+        let signed_data = sequence(&fields.iter().map(|x| x.as_slice()).collect::<Vec<_>>());
+
+        Ok(sequence(&[&oid(ID_SIGNED_DATA), &tlv(context_constructed(0), &signed_data)]))
+    }
+
+    #[cfg(feature = "pem")]
+    pub fn sign_cms_pem(builder: SignedDataBuilder, sk: &[u8], sid: SignerIdentifier) -> anyhow::Result<String> {
+        Ok(pem_encode(PEM_LABEL, &Self::sign_cms(builder, sk, sid)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::x509::{CertificateBuilder, Validity};
+    use crate::{Dilithium2, Dilithium3};
+
+    fn signer() -> (Vec<u8>, Certificate) {
+        let (sk, pk) = Dilithium3::key_gen(None).unwrap();
+        let builder = CertificateBuilder::new(Name::new().common_name("Firmware signer"), Dilithium3::to_public_key_der(&pk).unwrap(), Validity::for_days(1))
+            .leaf()
+            .unwrap();
+        let cert = Dilithium3::sign_certificate(builder, &sk).unwrap();
+        (sk, cert)
+    }
+
+    #[test]
+    fn attached_with_signed_attributes() {
+        let (sk, cert) = signer();
+        let builder = SignedDataBuilder::new(b"firmware image").certificate(cert.clone());
+        let der = Dilithium3::sign_cms(builder.clone(), &sk, SignerIdentifier::from_certificate(&cert)).unwrap();
+        let sd = SignedData::from_der(&der).unwrap();
+        #[cfg(feature = "pem")]
+        {
+            let pem = Dilithium3::sign_cms_pem(builder, &sk, SignerIdentifier::from_certificate(&cert)).unwrap();
+            let from_pem = SignedData::from_pem(&pem).unwrap();
+            assert_eq!(from_pem.verify_with_certificate(None, &cert).unwrap(), b"firmware image");
+        }
+        assert_eq!(sd.content.as_deref(), Some(&b"firmware image"[..]));
+        assert_eq!(sd.certificates, vec![cert.clone()]);
+        assert_eq!(sd.digest_algorithms, vec![ID_SHA512.to_vec()]);
+        assert!(sd.signer_infos[0].signed_attributes.is_some());
+        assert_eq!(sd.verify_with_certificate(None, &cert).unwrap(), b"firmware image");
+        assert_eq!(sd.verify(None, &cert.tbs.subject_public_key_info).unwrap(), b"firmware image");
+
+        // The messageDigest binds the content
+        let mut tampered = sd.clone();
+        tampered.content = Some(b"firmware imagf".to_vec());
+        assert!(tampered.verify_with_certificate(None, &cert).is_err());
+    }
+
+    #[test]
+    fn detached_and_pure() {
+        let (sk, cert) = signer();
+        let (_, other_pk) = Dilithium2::key_gen(None).unwrap();
+        let content = b"document".to_vec();
+        for (signed_attributes, digest) in [(true, DigestAlgorithm::Shake256), (false, DigestAlgorithm::Sha512)] {
+            let builder = SignedDataBuilder::new(&content).detached(true).signed_attributes(signed_attributes).digest(digest);
+            let sid = SignerIdentifier::SubjectKeyIdentifier(
+                find_key_identifier(&cert.tbs.extensions, ID_CE_SUBJECT_KEY_IDENTIFIER).unwrap().unwrap(),
+            );
+            let sd = SignedData::from_der(&Dilithium3::sign_cms(builder, &sk, sid).unwrap()).unwrap();
+            assert_eq!(sd.content, None);
+            assert_eq!(sd.signer_infos[0].signed_attributes.is_some(), signed_attributes);
+            assert_eq!(sd.verify_with_certificate(Some(&content), &cert).unwrap(), content);
+            assert!(sd.verify_with_certificate(None, &cert).is_err());
+            assert!(sd.verify_with_certificate(Some(b"other"), &cert).is_err());
+            let other_spki = Dilithium2::to_public_key_der(&other_pk).unwrap();
+            assert!(sd.verify(Some(&content), &other_spki).is_err());
+        }
+
+        // A key of the wrong length is an error, not a panic
+        let sid = SignerIdentifier::from_certificate(&cert);
+        assert!(Dilithium3::sign_cms(SignedDataBuilder::new(&content), &sk[..10], sid).is_err());
+    }
+
+    #[test]
+    fn pure_signature_is_over_content() {
+        let (sk, pk) = Dilithium2::key_gen(None).unwrap();
+        let sid = SignerIdentifier::SubjectKeyIdentifier(vec![1, 2, 3]);
+        let der = Dilithium2::sign_cms(SignedDataBuilder::new(b"abc").signed_attributes(false), &sk, sid).unwrap();
+        let sd = SignedData::from_der(&der).unwrap();
+        Dilithium2::verify(b"abc".to_vec(), sd.signer_infos[0].signature.clone(), pk, None).unwrap();
+    }
+}
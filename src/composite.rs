@@ -0,0 +1,307 @@
+//! Composite ML-DSA signatures following the IETF LAMPS composite-sigs
+//! draft: an ML-DSA signature and a traditional Ed25519 or ECDSA-P256
+//! signature over the same message representative, both of which must verify.
+//!
+//! ```text
+//! M' = Prefix || Label || len(ctx) || ctx || PH(M)
+//! mldsaSig = ML-DSA.Sign(mldsaSK, M', ctx = Label)
+//! tradSig  = Trad.Sign(tradSK, M')
+//! ```
+//!
+//! Keys and signatures are the plain concatenations of their components:
+//! `mldsaPK || tradPK`, `mldsaSeed || tradSK` and `mldsaSig || tradSig`.
+//! The Ed25519 private key is its 32 byte seed, the ECDSA private key a DER
+//! ECPrivateKey (RFC 5915), public keys are raw Ed25519 keys or uncompressed
+//! P-256 points and ECDSA signatures are DER Ecdsa-Sig-Value. Algorithm
+//! object identifiers are not assigned yet and are left out.
+
+use anyhow::{anyhow, bail};
+use ed25519_dalek::{Signer as _, Verifier as _};
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::algorithm::ParameterSet;
+use crate::asn1::*;
+use crate::params::SEEDBYTES;
+use crate::randombytes::randombytes;
+
+/// Domain separator shared by all composite algorithms, "CompositeAlgorithmSignatures2025".
+pub const COMPOSITE_PREFIX: &[u8] = b"CompositeAlgorithmSignatures2025";
+
+/// prime256v1, 1.2.840.10045.3.1.7
+const ID_PRIME256V1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+
+const ED25519_KEYBYTES: usize = 32;
+const ED25519_SIGBYTES: usize = 64;
+const P256_POINTBYTES: usize = 65;
+
+/// Supported composite algorithms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompositeAlgorithm {
+    MlDsa44Ed25519,
+    MlDsa44EcdsaP256,
+    MlDsa65Ed25519,
+    MlDsa65EcdsaP256,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Traditional {
+    Ed25519,
+    EcdsaP256,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PreHash {
+    Sha256,
+    Sha512,
+}
+
+impl CompositeAlgorithm {
+    /// Label used as domain separator and ML-DSA context.
+    pub fn label(self) -> &'static str {
+        match self {
+            CompositeAlgorithm::MlDsa44Ed25519 => "COMPSIG-MLDSA44-Ed25519-SHA512",
+            CompositeAlgorithm::MlDsa44EcdsaP256 => "COMPSIG-MLDSA44-ECDSA-P256-SHA256",
+            CompositeAlgorithm::MlDsa65Ed25519 => "COMPSIG-MLDSA65-Ed25519-SHA512",
+            CompositeAlgorithm::MlDsa65EcdsaP256 => "COMPSIG-MLDSA65-ECDSA-P256-SHA512",
+        }
+    }
+
+    pub fn ml_dsa(self) -> ParameterSet {
+        match self {
+            CompositeAlgorithm::MlDsa44Ed25519 | CompositeAlgorithm::MlDsa44EcdsaP256 => ParameterSet::MlDsa44,
+            CompositeAlgorithm::MlDsa65Ed25519 | CompositeAlgorithm::MlDsa65EcdsaP256 => ParameterSet::MlDsa65,
+        }
+    }
+
+    fn traditional(self) -> Traditional {
+        match self {
+            CompositeAlgorithm::MlDsa44Ed25519 | CompositeAlgorithm::MlDsa65Ed25519 => Traditional::Ed25519,
+            CompositeAlgorithm::MlDsa44EcdsaP256 | CompositeAlgorithm::MlDsa65EcdsaP256 => Traditional::EcdsaP256,
+        }
+    }
+
+    fn pre_hash(self) -> PreHash {
+        match self {
+            CompositeAlgorithm::MlDsa44EcdsaP256 => PreHash::Sha256,
+            _ => PreHash::Sha512,
+        }
+    }
+
+    /// Message representative M' for a message and context.
+    pub fn message_representative(self, msg: &[u8], ctx: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if ctx.len() > 255 {
+            bail!("context must be at most 255 bytes");
+        }
+        let ph = match self.pre_hash() {
+            PreHash::Sha256 => Sha256::digest(msg).to_vec(),
+            PreHash::Sha512 => Sha512::digest(msg).to_vec(),
+        };
+        Ok([COMPOSITE_PREFIX, self.label().as_bytes(), &[ctx.len() as u8], ctx, &ph].concat())
+    }
+
+    /// Generate a composite key pair, returning `(sk, pk)`.
+    pub fn key_gen(self) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+        let mut seed = [0u8; SEEDBYTES];
+        randombytes(&mut seed, SEEDBYTES);
+        let (_, ml_pk) = self.ml_dsa().key_gen(Some(seed.to_vec()))?;
+
+        let (trad_sk, trad_pk) = match self.traditional() {
+            Traditional::Ed25519 => {
+                let mut key = [0u8; ED25519_KEYBYTES];
+                randombytes(&mut key, ED25519_KEYBYTES);
+                let signing = ed25519_dalek::SigningKey::from_bytes(&key);
+                (key.to_vec(), signing.verifying_key().to_bytes().to_vec())
+            }
+            Traditional::EcdsaP256 => {
+                // Rejection sample a scalar in [1, n)
+                let secret = loop {
+                    let mut key = [0u8; 32];
+                    randombytes(&mut key, 32);
+                    if let Ok(secret) = p256::SecretKey::from_slice(&key) {
+                        break secret;
+                    }
+                };
+                let point = secret.public_key().to_sec1_bytes().to_vec();
+                (ec_private_key(&secret.to_bytes()), point)
+            }
+        };
+        Ok(([&seed[..], &trad_sk].concat(), [ml_pk, trad_pk].concat()))
+    }
+
+    /// Sign `msg` under an optional application context of at most 255 bytes.
+    pub fn sign(self, msg: &[u8], ctx: Option<&[u8]>, sk: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if sk.len() <= SEEDBYTES {
+            bail!("composite secret key too short");
+        }
+        let (seed, trad_sk) = sk.split_at(SEEDBYTES);
+        let m = self.message_representative(msg, ctx.unwrap_or(&[]))?;
+
+        let (ml_sk, _) = self.ml_dsa().key_gen(Some(seed.to_vec()))?;
+        let ml_sig = self.ml_dsa().sign(m.clone(), ml_sk, Some(self.label().as_bytes().to_vec()), true)?;
+
+        let trad_sig = match self.traditional() {
+            Traditional::Ed25519 => {
+                let key: [u8; ED25519_KEYBYTES] = trad_sk.try_into().map_err(|_| anyhow!("Ed25519 private key must be 32 bytes"))?;
+                ed25519_dalek::SigningKey::from_bytes(&key).sign(&m).to_bytes().to_vec()
+            }
+            Traditional::EcdsaP256 => {
+                let secret = p256::SecretKey::from_slice(&parse_ec_private_key(trad_sk)?)?;
+                let signature: p256::ecdsa::Signature = p256::ecdsa::SigningKey::from(secret).sign(&m);
+                signature.to_der().as_bytes().to_vec()
+            }
+        };
+        Ok([ml_sig, trad_sig].concat())
+    }
+
+    /// Verify a composite signature. Both component signatures must verify.
+    pub fn verify(self, msg: &[u8], ctx: Option<&[u8]>, sig: &[u8], pk: &[u8]) -> anyhow::Result<()> {
+        let (ml_pk_len, _, ml_sig_len) = self.ml_dsa().sizes();
+        if pk.len() < ml_pk_len || sig.len() < ml_sig_len {
+            bail!("composite key or signature too short");
+        }
+        let (ml_pk, trad_pk) = pk.split_at(ml_pk_len);
+        let (ml_sig, trad_sig) = sig.split_at(ml_sig_len);
+        let m = self.message_representative(msg, ctx.unwrap_or(&[]))?;
+
+        let ml_ok = self
+            .ml_dsa()
+            .verify(m.clone(), ml_sig.to_vec(), ml_pk.to_vec(), Some(self.label().as_bytes().to_vec()))
+            .is_ok();
+        let trad_ok = match self.traditional() {
+            Traditional::Ed25519 => {
+                let key: [u8; ED25519_KEYBYTES] = trad_pk.try_into().map_err(|_| anyhow!("Ed25519 public key must be 32 bytes"))?;
+                let sig: [u8; ED25519_SIGBYTES] = trad_sig.try_into().map_err(|_| anyhow!("Ed25519 signature must be 64 bytes"))?;
+                let key = ed25519_dalek::VerifyingKey::from_bytes(&key)?;
+                key.verify(&m, &ed25519_dalek::Signature::from_bytes(&sig)).is_ok()
+            }
+            Traditional::EcdsaP256 => {
+                if trad_pk.len() != P256_POINTBYTES {
+                    bail!("P-256 public key must be an uncompressed point");
+                }
+                let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(trad_pk)?;
+                let sig = p256::ecdsa::Signature::from_der(trad_sig)?;
+                key.verify(&m, &sig).is_ok()
+            }
+        };
+        // Both halves are always checked
+        match (ml_ok, trad_ok) {
+            (true, true) => Ok(()),
+            (false, _) => bail!("ML-DSA component signature does not verify"),
+            (_, false) => bail!("traditional component signature does not verify"),
+        }
+    }
+
+    /// Public key of a composite secret key.
+    pub fn public_key(self, sk: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if sk.len() <= SEEDBYTES {
+            bail!("composite secret key too short");
+        }
+        let (seed, trad_sk) = sk.split_at(SEEDBYTES);
+        let (_, ml_pk) = self.ml_dsa().key_gen(Some(seed.to_vec()))?;
+        let trad_pk = match self.traditional() {
+            Traditional::Ed25519 => {
+                let key: [u8; ED25519_KEYBYTES] = trad_sk.try_into().map_err(|_| anyhow!("Ed25519 private key must be 32 bytes"))?;
+                ed25519_dalek::SigningKey::from_bytes(&key).verifying_key().to_bytes().to_vec()
+            }
+            Traditional::EcdsaP256 => {
+                let secret = p256::SecretKey::from_slice(&parse_ec_private_key(trad_sk)?)?;
+                secret.public_key().to_sec1_bytes().to_vec()
+            }
+        };
+        Ok([ml_pk, trad_pk].concat())
+    }
+}
+
+/// ECPrivateKey with version 1, the private scalar and the named curve.
+fn ec_private_key(scalar: &[u8]) -> Vec<u8> {
+    sequence(&[&small_integer(1), &octet_string(scalar), &tlv(context_constructed(0), &oid(ID_PRIME256V1))])
+}
+
+fn parse_ec_private_key(der: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut outer = DerReader::new(der);
+    let mut key = outer.read_sequence()?;
+    outer.finish()?;
+    if key.read_small_integer()? != 1 {
+        bail!("unsupported ECPrivateKey version");
+    }
+    let scalar = key.read_octet_string()?.to_vec();
+    if let Some(params) = key.read_optional(context_constructed(0))? {
+        let mut r = DerReader::new(params);
+        if r.read_oid()? != ID_PRIME256V1 {
+            bail!("ECPrivateKey is not on P-256");
+        }
+        r.finish()?;
+    }
+    // The public key is recomputed from the scalar
+    key.read_optional(context_constructed(1))?;
+    key.finish()?;
+    Ok(scalar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [CompositeAlgorithm; 4] = [
+        CompositeAlgorithm::MlDsa44Ed25519,
+        CompositeAlgorithm::MlDsa44EcdsaP256,
+        CompositeAlgorithm::MlDsa65Ed25519,
+        CompositeAlgorithm::MlDsa65EcdsaP256,
+    ];
+
+    #[test]
+    fn message_representative() {
+        let alg = CompositeAlgorithm::MlDsa44Ed25519;
+        let m = alg.message_representative(b"abc", b"ctx").unwrap();
+        let mut expected = b"CompositeAlgorithmSignatures2025COMPSIG-MLDSA44-Ed25519-SHA512\x03ctx".to_vec();
+        // SHA-512("abc")
+        expected.extend_from_slice(&hex::decode(
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+        ).unwrap());
+        assert_eq!(m, expected);
+        assert!(alg.message_representative(b"abc", &[0u8; 256]).is_err());
+    }
+
+    #[test]
+    fn sign_and_verify() {
+        for alg in ALL {
+            let (sk, pk) = alg.key_gen().unwrap();
+            assert_eq!(alg.public_key(&sk).unwrap(), pk);
+            let sig = alg.sign(b"message", Some(b"app"), &sk).unwrap();
+            alg.verify(b"message", Some(b"app"), &sig, &pk).unwrap();
+            assert!(alg.verify(b"message", None, &sig, &pk).is_err());
+            assert!(alg.verify(b"massage", Some(b"app"), &sig, &pk).is_err());
+        }
+    }
+
+    #[test]
+    fn both_halves_must_verify() {
+        let alg = CompositeAlgorithm::MlDsa65EcdsaP256;
+        let (sk, pk) = alg.key_gen().unwrap();
+        let sig = alg.sign(b"message", None, &sk).unwrap();
+        let ml_len = alg.ml_dsa().sizes().2;
+
+        // Flip a bit in each component
+        let mut bad = sig.clone();
+        bad[10] ^= 1;
+        assert!(alg.verify(b"message", None, &bad, &pk).unwrap_err().to_string().contains("ML-DSA"));
+        let mut bad = sig.clone();
+        let last = bad.len() - 1;
+        bad[last] ^= 1;
+        assert!(alg.verify(b"message", None, &bad, &pk).is_err());
+
+        // A plain ML-DSA signature over M' without the label context is rejected
+        let m = alg.message_representative(b"message", b"").unwrap();
+        let (ml_sk, _) = alg.ml_dsa().key_gen(Some(sk[..32].to_vec())).unwrap();
+        let plain = alg.ml_dsa().sign(m, ml_sk, None, true).unwrap();
+        let stripped = [plain, sig[ml_len..].to_vec()].concat();
+        assert!(alg.verify(b"message", None, &stripped, &pk).is_err());
+
+        // Signatures do not transfer between algorithms with the same components
+        let other = CompositeAlgorithm::MlDsa44EcdsaP256;
+        let (osk, opk) = other.key_gen().unwrap();
+        let osig = other.sign(b"message", None, &osk).unwrap();
+        other.verify(b"message", None, &osig, &opk).unwrap();
+        assert!(alg.verify(b"message", None, &osig, &opk).is_err());
+    }
+}
@@ -0,0 +1,417 @@
+//! COSE support following the IETF ML-DSA for JOSE and COSE draft:
+//! COSE_Key of key type AKP and COSE_Sign1 with the ML-DSA algorithms.
+//!
+//! ```text
+//! COSE_Key  = { 1 (kty): 7 (AKP), 3 (alg): -48 / -49 / -50,
+//!               -1 (pub): bstr, ? -2 (priv): bstr (seed), ? 2 (kid): bstr }
+//! COSE_Sign1 = 18([ protected: bstr, unprotected: map, payload: bstr / nil, signature: bstr ])
+//! Sig_structure = [ "Signature1", protected, external_aad, payload ]
+//! ```
+//!
+//! The Sig_structure is signed with `Dilithium::sign` and an empty context.
+
+use anyhow::{anyhow, bail};
+
+use crate::algorithm::{Dilithium, ParameterSet};
+use crate::cbor::{self, CborReader};
+use crate::params::SEEDBYTES;
+use crate::symmetric::StreamBackend;
+
+/// COSE key type AKP (algorithm key pair)
+pub const COSE_KTY_AKP: i64 = 7;
+/// CBOR tag of a COSE_Sign1 message
+pub const COSE_SIGN1_TAG: u64 = 18;
+
+// Header and key parameter labels
+const LABEL_KTY: i64 = 1;
+const LABEL_KID: i64 = 2;
+const LABEL_ALG: i64 = 3;
+const LABEL_PUB: i64 = -1;
+const LABEL_PRIV: i64 = -2;
+const HEADER_ALG: i64 = 1;
+const HEADER_CRIT: i64 = 2;
+const HEADER_KID: i64 = 4;
+
+impl ParameterSet {
+    /// COSE algorithm identifier of the parameter set.
+    pub fn cose_alg(self) -> i64 {
+        match self {
+            ParameterSet::MlDsa44 => -48,
+            ParameterSet::MlDsa65 => -49,
+            ParameterSet::MlDsa87 => -50,
+        }
+    }
+
+    pub fn from_cose_alg(alg: i64) -> Option<Self> {
+        [ParameterSet::MlDsa44, ParameterSet::MlDsa65, ParameterSet::MlDsa87]
+            .into_iter()
+            .find(|p| p.cose_alg() == alg)
+    }
+}
+
+/// Read a map with integer labels, rejecting duplicates. Entries with text
+/// labels are skipped; values are returned encoded.
+fn read_int_map<'a>(r: &mut CborReader<'a>) -> anyhow::Result<Vec<(i64, &'a [u8])>> {
+    let n = r.read_map()?;
+    let mut entries: Vec<(i64, &[u8])> = Vec::new();
+    for _ in 0..n {
+        if r.peek_major() == Some(cbor::TEXT) {
+            r.read_text()?;
+            r.read_raw()?;
+            continue;
+        }
+        let label = r.read_int()?;
+        if entries.iter().any(|(l, _)| *l == label) {
+            bail!("duplicate COSE label {}", label);
+        }
+        entries.push((label, r.read_raw()?));
+    }
+    Ok(entries)
+}
+
+fn lookup<'a>(entries: &[(i64, &'a [u8])], label: i64) -> Option<CborReader<'a>> {
+    entries.iter().find(|(l, _)| *l == label).map(|(_, v)| CborReader::new(v))
+}
+
+/// Decode an encoded value that must be a single integer.
+fn single_int(mut r: CborReader) -> anyhow::Result<i64> {
+    let v = r.read_int()?;
+    r.finish()?;
+    Ok(v)
+}
+
+/// Decode an encoded value that must be a single byte string.
+fn single_bytes(mut r: CborReader) -> anyhow::Result<Vec<u8>> {
+    let v = r.read_bytes()?.to_vec();
+    r.finish()?;
+    Ok(v)
+}
+
+/// An AKP COSE_Key. `seed` is only present for private keys.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CoseKey {
+    pub alg: ParameterSet,
+    pub public_key: Vec<u8>,
+    pub seed: Option<Vec<u8>>,
+    pub kid: Option<Vec<u8>>,
+}
+
+/// Seed elided
+impl std::fmt::Debug for CoseKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CoseKey {{ alg: {:?}, public_key: {}, kid: {:?}, seed: <elided> }}", self.alg, hex::encode(&self.public_key), self.kid)
+    }
+}
+
+impl CoseKey {
+    /// Deterministically encoded COSE_Key.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        let (kty, alg, pk) = (cbor::int(LABEL_KTY), cbor::int(LABEL_ALG), cbor::int(LABEL_PUB));
+        let (kty_v, alg_v, pk_v) = (cbor::int(COSE_KTY_AKP), cbor::int(self.alg.cose_alg()), cbor::bytes(&self.public_key));
+        let mut entries: Vec<(&[u8], &[u8])> = vec![(&kty, &kty_v), (&alg, &alg_v), (&pk, &pk_v)];
+        let (kid, kid_v) = (cbor::int(LABEL_KID), self.kid.as_deref().map(cbor::bytes));
+        if let Some(kid_v) = &kid_v {
+            entries.push((&kid, kid_v));
+        }
+        let (sk, sk_v) = (cbor::int(LABEL_PRIV), self.seed.as_deref().map(cbor::bytes));
+        if let Some(sk_v) = &sk_v {
+            entries.push((&sk, sk_v));
+        }
+        cbor::map(&entries)
+    }
+
+    /// The key without its private part.
+    pub fn to_public(&self) -> Self {
+        CoseKey { seed: None, ..self.clone() }
+    }
+
+    /// Parse an AKP COSE_Key. Key lengths are checked by the parameter set
+    /// specific `Dilithium::from_cose_key`.
+    pub fn from_cbor(data: &[u8]) -> anyhow::Result<Self> {
+        let mut r = CborReader::new(data);
+        let entries = read_int_map(&mut r)?;
+        r.finish()?;
+
+        let kty = lookup(&entries, LABEL_KTY).ok_or_else(|| anyhow!("COSE_Key has no kty"))?;
+        if single_int(kty)? != COSE_KTY_AKP {
+            bail!("COSE_Key type must be AKP");
+        }
+        let alg = lookup(&entries, LABEL_ALG).ok_or_else(|| anyhow!("COSE_Key has no alg"))?;
+        let alg = single_int(alg)?;
+        let alg = ParameterSet::from_cose_alg(alg).ok_or_else(|| anyhow!("unsupported COSE alg {}", alg))?;
+        let public_key = lookup(&entries, LABEL_PUB).ok_or_else(|| anyhow!("COSE_Key has no pub"))?;
+        let public_key = single_bytes(public_key)?;
+        let seed = match lookup(&entries, LABEL_PRIV) {
+            Some(v) => Some(single_bytes(v)?),
+            None => None,
+        };
+        let kid = match lookup(&entries, LABEL_KID) {
+            Some(v) => Some(single_bytes(v)?),
+            None => None,
+        };
+        Ok(CoseKey { alg, public_key, seed, kid })
+    }
+}
+
+/// Encode the Sig_structure for a COSE_Sign1 message.
+pub fn sig_structure(protected: &[u8], external_aad: &[u8], payload: &[u8]) -> Vec<u8> {
+    cbor::array(&[&cbor::text("Signature1"), &cbor::bytes(protected), &cbor::bytes(external_aad), &cbor::bytes(payload)])
+}
+
+/// A decoded COSE_Sign1 message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoseSign1 {
+    /// Encoded protected header map, as carried in the bstr.
+    pub protected: Vec<u8>,
+    pub alg: ParameterSet,
+    pub kid: Option<Vec<u8>>,
+    /// `None` for a detached payload.
+    pub payload: Option<Vec<u8>>,
+    pub signature: Vec<u8>,
+}
+
+impl CoseSign1 {
+    /// Decode a tagged or untagged COSE_Sign1 message.
+    pub fn from_cbor(data: &[u8]) -> anyhow::Result<Self> {
+        let mut r = CborReader::new(data);
+        if r.peek_major() == Some(cbor::TAG) && r.read_tag()? != COSE_SIGN1_TAG {
+            bail!("not a COSE_Sign1 message");
+        }
+        if r.read_array()? != 4 {
+            bail!("COSE_Sign1 must be an array of four items");
+        }
+        let protected = r.read_bytes()?;
+        let unprotected = read_int_map(&mut r)?;
+        let payload = if r.peek_null() {
+            r.read_null()?;
+            None
+        } else {
+            Some(r.read_bytes()?.to_vec())
+        };
+        let signature = r.read_bytes()?.to_vec();
+        r.finish()?;
+
+        let mut hr = CborReader::new(protected);
+        let headers = if hr.is_empty() { Vec::new() } else { read_int_map(&mut hr)? };
+        hr.finish()?;
+        if lookup(&headers, HEADER_CRIT).is_some() {
+            bail!("critical COSE header parameters are not supported");
+        }
+        if lookup(&unprotected, HEADER_ALG).is_some() {
+            bail!("alg must be a protected header parameter");
+        }
+        let alg = lookup(&headers, HEADER_ALG).ok_or_else(|| anyhow!("COSE_Sign1 has no protected alg"))?;
+        let alg = single_int(alg)?;
+        let alg = ParameterSet::from_cose_alg(alg).ok_or_else(|| anyhow!("unsupported COSE alg {}", alg))?;
+        let kid = match lookup(&headers, HEADER_KID).or_else(|| lookup(&unprotected, HEADER_KID)) {
+            Some(v) => Some(single_bytes(v)?),
+            None => None,
+        };
+
+        Ok(CoseSign1 { protected: protected.to_vec(), alg, kid, payload, signature })
+    }
+}
+
+impl<
+    const K: usize,
+    const L: usize,
+    const ETA: usize,
+    const TAU: usize,
+    const BETA: usize,
+    const GAMMA1: usize,
+    const GAMMA2: usize,
+    const OMEGA: usize,
+    const PUBLICKEYBYTES: usize,
+    const SECRETKEYBYTES: usize,
+    const POLY_UNIFORM_NBLOCKS: usize,
+    S: StreamBackend,
+    const POLY_UNIFORM_ETA_NBLOCKS: usize,
+    const POLYETA_PACKEDBYTES: usize,
+    const SIGNBYTES: usize,
+    const POLYW1_PACKEDBYTES: usize,
+    const CTILDEBYTES: usize,
+    const POLYZ_PACKEDBYTES: usize,
+    const POLYVECH_PACKEDBYTES: usize,
+    const POLY_UNIFORM_GAMMA1_NBLOCKS: usize,
+>
+Dilithium<
+    K,
+    L,
+    ETA,
+    TAU,
+    BETA,
+    GAMMA1,
+    GAMMA2,
+    OMEGA,
+    PUBLICKEYBYTES,
+    SECRETKEYBYTES,
+    POLY_UNIFORM_NBLOCKS,
+    S,
+    POLY_UNIFORM_ETA_NBLOCKS,
+    POLYETA_PACKEDBYTES,
+    SIGNBYTES,
+    POLYW1_PACKEDBYTES,
+    CTILDEBYTES,
+    POLYZ_PACKEDBYTES,
+    POLYVECH_PACKEDBYTES,
+    POLY_UNIFORM_GAMMA1_NBLOCKS,
+>
+{
+    fn cose_parameter_set() -> anyhow::Result<ParameterSet> {
+        Self::parameter_set().ok_or_else(|| anyhow!("parameters do not match an ML-DSA parameter set"))
+    }
+
+    fn cose_secret_key(pk: &[u8], seed: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if seed.len() != SEEDBYTES {
+            bail!("seed must be {} bytes", SEEDBYTES);
+        }
+        let (sk, expected) = Self::key_gen(Some(seed.to_vec()))?;
+        if expected != pk {
+            bail!("public key does not match the seed");
+        }
+        Ok(sk)
+    }
+
+    /// Encode a public key, or a private key if the key generation seed is
+    /// given, as a COSE_Key. The seed must produce `pk`.
+    pub fn to_cose_key(pk: &[u8], seed: Option<&[u8]>, kid: Option<&[u8]>) -> anyhow::Result<Vec<u8>> {
+        let alg = Self::cose_parameter_set()?;
+        if pk.len() != PUBLICKEYBYTES {
+            bail!("public key must be {} bytes", PUBLICKEYBYTES);
+        }
+        if let Some(seed) = seed {
+            Self::cose_secret_key(pk, seed)?;
+        }
+        let key = CoseKey { alg, public_key: pk.to_vec(), seed: seed.map(<[u8]>::to_vec), kid: kid.map(<[u8]>::to_vec) };
+        Ok(key.to_cbor())
+    }
+
+    /// Decode a COSE_Key of this parameter set into the packed public key
+    /// and, for private keys, the secret key expanded from the seed.
+    pub fn from_cose_key(data: &[u8]) -> anyhow::Result<(Vec<u8>, Option<Vec<u8>>)> {
+        let key = CoseKey::from_cbor(data)?;
+        if key.alg != Self::cose_parameter_set()? {
+            bail!("COSE alg {} does not match the parameter set", key.alg.cose_alg());
+        }
+        if key.public_key.len() != PUBLICKEYBYTES {
+            bail!("public key must be {} bytes", PUBLICKEYBYTES);
+        }
+        let sk = match &key.seed {
+            Some(seed) => Some(Self::cose_secret_key(&key.public_key, seed)?),
+            None => None,
+        };
+        Ok((key.public_key, sk))
+    }
+
+    /// Create a tagged COSE_Sign1 message with `alg` in the protected header
+    /// and `kid` in the unprotected header. With `detached` the payload is
+    /// signed but carried as nil.
+    pub fn sign_cose_sign1(payload: &[u8], external_aad: &[u8], sk: &[u8], kid: Option<&[u8]>, detached: bool) -> anyhow::Result<Vec<u8>> {
+        let alg = Self::cose_parameter_set()?;
+        if sk.len() != SECRETKEYBYTES {
+            bail!("COSE signing key must be {} bytes", SECRETKEYBYTES);
+        }
+        let protected = cbor::map(&[(&cbor::int(HEADER_ALG), &cbor::int(alg.cose_alg()))]);
+        let unprotected = match kid {
+            Some(kid) => cbor::map(&[(&cbor::int(HEADER_KID), &cbor::bytes(kid))]),
+            None => cbor::map(&[]),
+        };
+        let to_sign = sig_structure(&protected, external_aad, payload);
+        let signature = Self::sign(to_sign, sk.to_vec(), None, true)?;
+        let payload = if detached { vec![cbor::NULL] } else { cbor::bytes(payload) };
+        let message = cbor::array(&[&cbor::bytes(&protected), &unprotected, &payload, &cbor::bytes(&signature)]);
+        Ok(cbor::tag(COSE_SIGN1_TAG, &message))
+    }
+
+    /// Verify a COSE_Sign1 message and return its payload. A detached payload
+    /// must be supplied in `detached_payload`.
+    pub fn verify_cose_sign1(message: &[u8], detached_payload: Option<&[u8]>, external_aad: &[u8], pk: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let msg = CoseSign1::from_cbor(message)?;
+        if msg.alg != Self::cose_parameter_set()? {
+            bail!("COSE alg {} does not match the parameter set", msg.alg.cose_alg());
+        }
+        let payload = match (msg.payload, detached_payload) {
+            (Some(payload), None) => payload,
+            (None, Some(payload)) => payload.to_vec(),
+            (Some(_), Some(_)) => bail!("COSE_Sign1 payload is not detached"),
+            (None, None) => bail!("COSE_Sign1 payload is detached"),
+        };
+        let to_verify = sig_structure(&msg.protected, external_aad, &payload);
+        Self::verify(to_verify, msg.signature, pk.to_vec(), None).map_err(|e| anyhow!("invalid COSE_Sign1 signature: {:?}", e))?;
+        Ok(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dilithium2, Dilithium3, Dilithium5};
+
+    #[test]
+    fn cose_key_matches_hand_built_cbor() {
+        let seed = [5u8; 32];
+        let (sk, pk) = Dilithium2::key_gen(Some(seed.to_vec())).unwrap();
+        let encoded = Dilithium2::to_cose_key(&pk, None, None).unwrap();
+        // {1: 7, 3: -48, -1: h'<1312 bytes>'}
+        let mut expected = vec![0xa3, 0x01, 0x07, 0x03, 0x38, 0x2f, 0x20, 0x59, 0x05, 0x20];
+        expected.extend_from_slice(&pk);
+        assert_eq!(encoded, expected);
+        assert_eq!(Dilithium2::from_cose_key(&expected).unwrap(), (pk.clone(), None));
+
+        // {1: 7, 2: h'6b31', 3: -48, -1: pub, -2: seed}
+        let private = Dilithium2::to_cose_key(&pk, Some(&seed), Some(b"k1")).unwrap();
+        let mut expected = vec![0xa5, 0x01, 0x07, 0x02, 0x42, 0x6b, 0x31, 0x03, 0x38, 0x2f, 0x20, 0x59, 0x05, 0x20];
+        expected.extend_from_slice(&pk);
+        expected.extend_from_slice(&[0x21, 0x58, 0x20]);
+        expected.extend_from_slice(&seed);
+        assert_eq!(private, expected);
+        assert_eq!(Dilithium2::from_cose_key(&private).unwrap(), (pk.clone(), Some(sk)));
+        assert_eq!(CoseKey::from_cbor(&private).unwrap().to_public().to_cbor(), {
+            let mut v = vec![0xa4, 0x01, 0x07, 0x02, 0x42, 0x6b, 0x31, 0x03, 0x38, 0x2f, 0x20, 0x59, 0x05, 0x20];
+            v.extend_from_slice(&pk);
+            v
+        });
+
+        assert!(Dilithium3::from_cose_key(&private).is_err());
+        assert!(Dilithium2::to_cose_key(&pk, Some(&[6u8; 32]), None).is_err());
+    }
+
+    #[test]
+    fn sig_structure_matches_hand_built_cbor() {
+        // ["Signature1", h'a101382f', h'', h'6869']
+        let expected = [
+            0x84, 0x6a, b'S', b'i', b'g', b'n', b'a', b't', b'u', b'r', b'e', b'1', 0x44, 0xa1, 0x01, 0x38, 0x2f, 0x40,
+            0x42, 0x68, 0x69,
+        ];
+        assert_eq!(sig_structure(&[0xa1, 0x01, 0x38, 0x2f], b"", b"hi"), expected);
+
+        // A hand-assembled message signed over that Sig_structure verifies
+        let (sk, pk) = Dilithium2::key_gen(None).unwrap();
+        let sig = Dilithium2::sign(expected.to_vec(), sk, None, false).unwrap();
+        let mut message = vec![0xd2, 0x84, 0x44, 0xa1, 0x01, 0x38, 0x2f, 0xa0, 0x42, 0x68, 0x69, 0x59, 0x09, 0x74];
+        message.extend_from_slice(&sig);
+        assert_eq!(Dilithium2::verify_cose_sign1(&message, None, b"", &pk).unwrap(), b"hi");
+        assert!(Dilithium2::verify_cose_sign1(&message, None, b"aad", &pk).is_err());
+    }
+
+    #[test]
+    fn sign1_roundtrip() {
+        let (sk, pk) = Dilithium5::key_gen(None).unwrap();
+        let message = Dilithium5::sign_cose_sign1(b"attestation", b"aad", &sk, Some(b"device"), false).unwrap();
+        assert_eq!(&message[..2], [0xd2, 0x84]);
+        let decoded = CoseSign1::from_cbor(&message).unwrap();
+        assert_eq!(decoded.alg, ParameterSet::MlDsa87);
+        assert_eq!(decoded.kid.as_deref(), Some(&b"device"[..]));
+        assert_eq!(Dilithium5::verify_cose_sign1(&message, None, b"aad", &pk).unwrap(), b"attestation");
+        assert!(Dilithium5::verify_cose_sign1(&message, None, b"", &pk).is_err());
+        assert!(Dilithium3::verify_cose_sign1(&message, None, b"aad", &pk).is_err());
+
+        let detached = Dilithium5::sign_cose_sign1(b"attestation", b"", &sk, None, true).unwrap();
+        assert!(Dilithium5::verify_cose_sign1(&detached, None, b"", &pk).is_err());
+        assert_eq!(Dilithium5::verify_cose_sign1(&detached, Some(b"attestation"), b"", &pk).unwrap(), b"attestation");
+        assert!(Dilithium5::verify_cose_sign1(&detached, Some(b"other"), b"", &pk).is_err());
+
+        // A signing key of the wrong length is an error, not a panic
+        assert!(Dilithium5::sign_cose_sign1(b"attestation", b"", &sk[..100], None, false).is_err());
+    }
+}
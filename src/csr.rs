@@ -0,0 +1,296 @@
+//! PKCS#10 certification requests (RFC 2986) signed with ML-DSA.
+//!
+//! ```text
+//! CertificationRequestInfo ::= SEQUENCE {
+//!   version       INTEGER { v1(0) },
+//!   subject       Name,
+//!   subjectPKInfo SubjectPublicKeyInfo,
+//!   attributes    [0] IMPLICIT SET OF Attribute }
+//! ```
+//!
+//! The request is signed with the key it carries as proof of possession.
+//! Requested extensions go into a single extensionRequest attribute.
+
+use anyhow::{anyhow, bail};
+
+use crate::algorithm::{Dilithium, ParameterSet};
+use crate::asn1::*;
+use crate::fips202::shake256;
+use crate::params::{SEEDBYTES, TRBYTES};
+use crate::spki::{decode_spki, encode_spki};
+use crate::x509::{CertificateBuilder, Extension, Name, Validity};
+use crate::symmetric::StreamBackend;
+
+#[cfg(feature = "pem")]
+const PEM_LABEL: &str = "CERTIFICATE REQUEST";
+
+/// pkcs-9-at-extensionRequest, 1.2.840.113549.1.9.14
+pub const ID_EXTENSION_REQUEST: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x0e];
+
+/// Builder for the CertificationRequestInfo of a new request.
+#[derive(Debug, Clone)]
+pub struct CsrBuilder {
+    subject: Name,
+    extensions: Vec<Extension>,
+}
+
+impl CsrBuilder {
+    pub fn new(subject: Name) -> Self {
+        CsrBuilder { subject, extensions: Vec::new() }
+    }
+
+    /// Request an extension, replacing an earlier one with the same OID.
+    pub fn extension(mut self, extension: Extension) -> Self {
+        self.extensions.retain(|e| e.oid != extension.oid);
+        self.extensions.push(extension);
+        self
+    }
+
+    fn info_der(&self, subject_public_key_info: &[u8]) -> Vec<u8> {
+        let attributes = if self.extensions.is_empty() {
+            tlv(context_constructed(0), &[])
+        } else {
+            let request = sequence(&[&oid(ID_EXTENSION_REQUEST), &set_of(&[&Extension::encode_list(&self.extensions)])]);
+            tlv(context_constructed(0), &request)
+        };
+        sequence(&[&small_integer(0), &self.subject.to_der(), subject_public_key_info, &attributes])
+    }
+}
+
+/// A signed certification request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificationRequest {
+    pub subject: Name,
+    pub subject_public_key_info: Vec<u8>,
+    pub extensions: Vec<Extension>,
+    pub signature_algorithm: Vec<u8>,
+    pub signature: Vec<u8>,
+    info_der: Vec<u8>,
+    der: Vec<u8>,
+}
+
+impl CertificationRequest {
+    pub fn from_der(der: &[u8]) -> anyhow::Result<Self> {
+        let mut outer = DerReader::new(der);
+        let mut req = outer.read_sequence()?;
+        outer.finish()?;
+        let (_, _, info_der) = req.read_raw()?;
+        let signature_algorithm = req.read_algorithm_identifier()?.to_vec();
+        let signature = req.read_bit_string()?.to_vec();
+        req.finish()?;
+
+        let mut outer = DerReader::new(info_der);
+        let mut info = outer.read_sequence()?;
+        outer.finish()?;
+        if info.read_small_integer()? != 0 {
+            bail!("unsupported certification request version");
+        }
+        let subject = Name::decode(&mut info)?;
+        let (_, _, subject_public_key_info) = info.read_raw()?;
+        let mut attributes = info.read_nested(context_constructed(0))?;
+        info.finish()?;
+
+        let mut extensions = None;
+        while !attributes.is_empty() {
+            let mut attribute = attributes.read_sequence()?;
+            let attr_type = attribute.read_oid()?;
+            let mut values = attribute.read_nested(SET)?;
+            attribute.finish()?;
+            // Other attributes, e.g. challengePassword, are ignored
+            if attr_type == ID_EXTENSION_REQUEST {
+                if extensions.is_some() {
+                    bail!("duplicate extensionRequest attribute");
+                }
+                extensions = Some(Extension::decode_list(&mut values)?);
+                values.finish()?;
+            }
+        }
+
+        Ok(CertificationRequest {
+            subject,
+            subject_public_key_info: subject_public_key_info.to_vec(),
+            extensions: extensions.unwrap_or_default(),
+            signature_algorithm,
+            signature,
+            info_der: info_der.to_vec(),
+            der: der.to_vec(),
+        })
+    }
+
+    #[cfg(feature = "pem")]
+    pub fn from_pem(pem: &str) -> anyhow::Result<Self> {
+        CertificationRequest::from_der(&pem_decode(PEM_LABEL, pem)?)
+    }
+
+    pub fn to_der(&self) -> Vec<u8> {
+        self.der.clone()
+    }
+
+    #[cfg(feature = "pem")]
+    pub fn to_pem(&self) -> String {
+        pem_encode(PEM_LABEL, &self.der)
+    }
+
+    /// The exact DER of the signed CertificationRequestInfo.
+    pub fn info_der(&self) -> &[u8] {
+        &self.info_der
+    }
+
+    /// Check the proof of possession: the request must be signed by the key
+    /// in its SubjectPublicKeyInfo, with the matching parameter set.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        let (algorithm, key) = decode_spki(&self.subject_public_key_info)?;
+        let set = ParameterSet::from_oid(algorithm)
+            .ok_or_else(|| anyhow!("unsupported public key algorithm {}", oid_to_string(algorithm)))?;
+        if self.signature_algorithm != set.oid() {
+            bail!("signature algorithm {} does not match the public key", oid_to_string(&self.signature_algorithm));
+        }
+        set.verify(self.info_der.clone(), self.signature.clone(), key.to_vec(), None)
+            .map_err(|e| anyhow!("invalid certification request signature: {:?}", e))
+    }
+}
+
+impl CertificateBuilder {
+    /// Start a certificate for a verified certification request, copying the
+    /// subject, public key and requested extensions.
+    pub fn from_request(request: &CertificationRequest, validity: Validity) -> anyhow::Result<Self> {
+        request.verify()?;
+        Ok(request
+            .extensions
+            .iter()
+            .cloned()
+            .fold(CertificateBuilder::new(request.subject.clone(), request.subject_public_key_info.clone(), validity), |b, e| b.extension(e)))
+    }
+}
+
+impl<
+    const K: usize,
+    const L: usize,
+    const ETA: usize,
+    const TAU: usize,
+    const BETA: usize,
+    const GAMMA1: usize,
+    const GAMMA2: usize,
+    const OMEGA: usize,
+    const PUBLICKEYBYTES: usize,
+    const SECRETKEYBYTES: usize,
+    const POLY_UNIFORM_NBLOCKS: usize,
+    S: StreamBackend,
+    const POLY_UNIFORM_ETA_NBLOCKS: usize,
+    const POLYETA_PACKEDBYTES: usize,
+    const SIGNBYTES: usize,
+    const POLYW1_PACKEDBYTES: usize,
+    const CTILDEBYTES: usize,
+    const POLYZ_PACKEDBYTES: usize,
+    const POLYVECH_PACKEDBYTES: usize,
+    const POLY_UNIFORM_GAMMA1_NBLOCKS: usize,
+>
+Dilithium<
+    K,
+    L,
+    ETA,
+    TAU,
+    BETA,
+    GAMMA1,
+    GAMMA2,
+    OMEGA,
+    PUBLICKEYBYTES,
+    SECRETKEYBYTES,
+    POLY_UNIFORM_NBLOCKS,
+    S,
+    POLY_UNIFORM_ETA_NBLOCKS,
+    POLYETA_PACKEDBYTES,
+    SIGNBYTES,
+    POLYW1_PACKEDBYTES,
+    CTILDEBYTES,
+    POLYZ_PACKEDBYTES,
+    POLYVECH_PACKEDBYTES,
+    POLY_UNIFORM_GAMMA1_NBLOCKS,
+>
+{
+    /// Sign a certification request for the key pair `sk`, `pk`.
+    pub fn sign_csr(builder: CsrBuilder, sk: &[u8], pk: &[u8]) -> anyhow::Result<CertificationRequest> {
+        let set = Self::parameter_set().ok_or_else(|| anyhow!("parameters do not match an ML-DSA parameter set"))?;
+        if sk.len() != SECRETKEYBYTES || pk.len() != PUBLICKEYBYTES {
+            bail!("key pair must be {} and {} bytes", SECRETKEYBYTES, PUBLICKEYBYTES);
+        }
+        // The secret key holds tr = H(pk)
+        let mut tr = [0u8; TRBYTES];
+        shake256(&mut tr, TRBYTES, pk, pk.len());
+        if tr[..] != sk[2 * SEEDBYTES..2 * SEEDBYTES + TRBYTES] {
+            bail!("public key does not match the secret key");
+        }
+
+        let info_der = builder.info_der(&encode_spki(set.oid(), pk));
+        let signature = Self::sign(info_der.clone(), sk.to_vec(), None, true)?;
+        let der = sequence(&[&info_der, &algorithm_identifier(set.oid()), &bit_string(&signature)]);
+        CertificationRequest::from_der(&der)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::x509::{key_usage, Certificate};
+    use crate::{Dilithium2, Dilithium3, Dilithium5};
+
+    #[test]
+    fn roundtrip_and_verify() {
+        let (sk, pk) = Dilithium3::key_gen(None).unwrap();
+        let builder = CsrBuilder::new(Name::new().organization("Example").common_name("client"))
+            .extension(Extension::key_usage(key_usage::DIGITAL_SIGNATURE))
+            .extension(Extension::subject_alt_name_dns(&["client.example"]));
+        let csr = Dilithium3::sign_csr(builder, &sk, &pk).unwrap();
+        csr.verify().unwrap();
+        assert_eq!(csr.signature_algorithm, ID_ML_DSA_65);
+        assert_eq!(csr.extensions.len(), 2);
+
+        let parsed = CertificationRequest::from_der(&csr.to_der()).unwrap();
+        #[cfg(feature = "pem")]
+        {
+            let pem = csr.to_pem();
+            assert!(pem.starts_with("-----BEGIN CERTIFICATE REQUEST-----\n"));
+            assert_eq!(CertificationRequest::from_pem(&pem).unwrap(), parsed);
+        }
+        assert_eq!(parsed, csr);
+        assert_eq!(Dilithium3::from_public_key_der(&parsed.subject_public_key_info).unwrap(), pk);
+
+        // Without extensions the attributes are an empty SET
+        let csr = Dilithium3::sign_csr(CsrBuilder::new(Name::new().common_name("bare")), &sk, &pk).unwrap();
+        csr.verify().unwrap();
+        assert!(csr.info_der().ends_with(&[0xa0, 0x00]));
+    }
+
+    #[test]
+    fn rejects_bad_requests() {
+        let (sk, pk) = Dilithium2::key_gen(None).unwrap();
+        let (_, other_pk) = Dilithium2::key_gen(None).unwrap();
+        let builder = CsrBuilder::new(Name::new().common_name("client"));
+        assert!(Dilithium2::sign_csr(builder.clone(), &sk, &other_pk).is_err());
+
+        let csr = Dilithium2::sign_csr(builder, &sk, &pk).unwrap();
+        let mut der = csr.to_der();
+        let pos = der.windows(6).position(|w| w == b"client").unwrap();
+        der[pos] = b'C';
+        assert!(CertificationRequest::from_der(&der).unwrap().verify().is_err());
+    }
+
+    #[test]
+    fn issue_from_request() {
+        let (ca_sk, ca_pk) = Dilithium5::key_gen(None).unwrap();
+        let ca_builder = CertificateBuilder::new(Name::new().common_name("CA"), Dilithium5::to_public_key_der(&ca_pk).unwrap(), Validity::for_days(30))
+            .ca(None)
+            .unwrap();
+        let ca = Dilithium5::sign_certificate(ca_builder, &ca_sk).unwrap();
+
+        let (sk, pk) = Dilithium2::key_gen(None).unwrap();
+        let builder = CsrBuilder::new(Name::new().common_name("client")).extension(Extension::key_usage(key_usage::DIGITAL_SIGNATURE));
+        let csr = CertificationRequest::from_der(&Dilithium2::sign_csr(builder, &sk, &pk).unwrap().to_der()).unwrap();
+
+        let builder = CertificateBuilder::from_request(&csr, Validity::for_days(1)).unwrap().issued_by(&ca).unwrap();
+        let cert: Certificate = Dilithium5::sign_certificate(builder, &ca_sk).unwrap();
+        cert.verify_issued_by(&ca).unwrap();
+        assert_eq!(cert.tbs.subject, csr.subject);
+        assert_eq!(cert.key_usage().unwrap(), Some(key_usage::DIGITAL_SIGNATURE));
+    }
+}
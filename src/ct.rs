@@ -0,0 +1,84 @@
+//! Constant-time building blocks for the signing path.
+//!
+//! Masks are passed through `barrier` before use so the optimizer cannot
+//! tell that they are all-zeros or all-ones and turn the selects back into
+//! branches.
+
+/// Return x unchanged while hiding its value from the optimizer.
+#[inline(always)]
+pub fn barrier(x: i32) -> i32 {
+  #[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+  {
+    let mut x = x as i64;
+    // SAFETY: the empty asm block only ties x to a register.
+    unsafe {
+      core::arch::asm!("/* {0} */", inout(reg) x, options(pure, nomem, nostack, preserves_flags));
+    }
+    x as i32
+  }
+  #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+  {
+    core::hint::black_box(x)
+  }
+}
+
+/// All-ones if x < 0, zero otherwise.
+#[inline(always)]
+pub fn neg_mask(x: i32) -> i32 {
+  barrier(x >> 31)
+}
+
+/// All-ones if x != 0, zero otherwise.
+#[inline(always)]
+pub fn nonzero_mask(x: i32) -> i32 {
+  neg_mask(x | x.wrapping_neg())
+}
+
+/// a if mask is all-ones, b if it is zero.
+#[inline(always)]
+pub fn select(mask: i32, a: i32, b: i32) -> i32 {
+  b ^ (mask & (a ^ b))
+}
+
+/// Compare two byte strings in time that depends only on their lengths.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+  if a.len() != b.len() {
+    return false;
+  }
+  let diff = a.iter().zip(b).fold(0u8, |d, (x, y)| d | (x ^ y));
+  nonzero_mask(diff as i32) == 0
+}
+
+/// Overwrite a secret with zeros in a way the optimizer cannot elide.
+pub fn wipe(buf: &mut [u8]) {
+  for b in buf.iter_mut() {
+    // SAFETY: b is a valid, aligned reference to a u8.
+    unsafe { core::ptr::write_volatile(b, 0) };
+  }
+  core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn masks() {
+    for x in [i32::MIN, -2, -1, 0, 1, 2, i32::MAX] {
+      assert_eq!(neg_mask(x), if x < 0 { -1 } else { 0 });
+      assert_eq!(nonzero_mask(x), if x != 0 { -1 } else { 0 });
+      assert_eq!(select(-1, x, 7), x);
+      assert_eq!(select(0, x, 7), 7);
+    }
+  }
+
+  #[test]
+  fn eq_and_wipe() {
+    let mut a = [1u8, 2, 3];
+    assert!(ct_eq(&a, &[1, 2, 3]));
+    assert!(!ct_eq(&a, &[1, 2, 4]));
+    assert!(!ct_eq(&a, &[1, 2]));
+    wipe(&mut a);
+    assert_eq!(a, [0; 3]);
+  }
+}
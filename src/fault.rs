@@ -0,0 +1,322 @@
+//! Fault countermeasures for signing, enabled with `with_fault_checks`.
+//!
+//! Deterministic signing is open to differential fault attacks: a single
+//! fault in one of two signings of the same message, for example in the
+//! challenge, gives two signatures with the same y and lets an attacker
+//! solve for s1. In the hardened mode an accepted signature is checked
+//! before it is released, using computations that share as little as
+//! possible with the rejection loop:
+//!
+//! - t = A*s1 + s2 is recomputed from the secret key, and its low bits and
+//!   the hash of its high bits must match t0 and tr;
+//! - c is expanded again from the signature, and z = y + c*s1 is recomputed
+//!   with a sparse product instead of the NTT and must match;
+//! - the rejection checks are evaluated again on the recomputed values, so
+//!   a skipped check is caught: ||z|| < GAMMA1 - BETA, the high bits of
+//!   A*z - c*t must be w1 with low bits below GAMMA2 - BETA, and
+//!   ||c*t0|| < GAMMA2;
+//! - the packed signature is verified with the public key recomputed from
+//!   t, so a fault in packing, or in z and h after the checks, is caught
+//!   before the signature is released.
+//!
+//! Any mismatch makes signing fail with `SignError::Fault`. Only the
+//! signers built on `crypto_sign_signature` run these checks, not
+//! `sign_lowmem` and `sign_masked`.
+
+use std::cell::Cell;
+
+use crate::{
+    fips202::*, packing::*, params::*, poly::*, polyvec::*,
+};
+use crate::api::SignError;
+use crate::scoped::with_scoped;
+use crate::sign::verify_mu;
+use crate::symmetric::StreamBackend;
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f` with the fault checks enabled for every signature made on the
+/// current thread. The previous setting is restored afterwards, also when
+/// `f` panics.
+pub fn with_fault_checks<T>(f: impl FnOnce() -> T) -> T {
+    with_scoped(&ENABLED, true, f)
+}
+
+pub(crate) fn enabled() -> bool {
+    ENABLED.with(|e| e.get())
+}
+
+/// The inputs and outputs of an accepted iteration of the signing loop,
+/// with the signature already packed. The secret vectors are in normal,
+/// not NTT, representation.
+pub(crate) struct Signed<'a, const K: usize, const L: usize> {
+    pub sig: &'a [u8],
+    pub mu: &'a [u8],
+    pub rho: &'a [u8],
+    pub tr: &'a [u8],
+    pub mat: &'a [Polyvecl<L>; K],
+    pub s1: &'a Polyvecl<L>,
+    pub s2: &'a Polyveck<K>,
+    pub t0: &'a Polyveck<K>,
+    pub y: &'a Polyvecl<L>,
+    pub z: &'a Polyvecl<L>,
+    pub w1: &'a Polyveck<K>,
+}
+
+/// r = c*a for a challenge c with coefficients in {-1, 0, 1}, by adding up
+/// rotated copies of a. Input coefficients must be below 2^31 / TAU in
+/// absolute value; the output is reduced.
+fn challenge_times(r: &mut Poly, c: &Poly, a: &Poly) {
+    r.coeffs.fill(0);
+    for (i, &ci) in c.coeffs.iter().enumerate().filter(|(_, &ci)| ci != 0) {
+        for (j, &aj) in a.coeffs.iter().enumerate() {
+            if i + j < N {
+                r.coeffs[i + j] += ci * aj;
+            } else {
+                r.coeffs[i + j - N] -= ci * aj;
+            }
+        }
+    }
+    poly_reduce(r);
+}
+
+/// A*v in normal representation with standard representatives.
+fn matrix_times<const K: usize, const L: usize>(r: &mut Polyveck<K>, mat: &[Polyvecl<L>; K], v: &Polyvecl<L>) {
+    let mut vhat = *v;
+    polyvecl_ntt(&mut vhat);
+    polyvec_matrix_pointwise_montgomery(r, mat, &vhat);
+    polyveck_reduce(r);
+    polyveck_invntt_tomont(r);
+    polyveck_caddq(r);
+}
+
+fn check(ok: bool) -> Result<(), SignError> {
+    if ok {
+        Ok(())
+    } else {
+        Err(SignError::Fault)
+    }
+}
+
+/// Run all checks on an accepted signature, see the module documentation.
+pub(crate) fn check_signature<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const POLYZ_PACKEDBYTES: usize, const TAU: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend>(
+    s: &Signed<K, L>,
+) -> Result<(), SignError> {
+    let (mut t, mut t1, mut t0) = (Polyveck::<K>::default(), Polyveck::<K>::default(), Polyveck::<K>::default());
+    let (mut r, mut r0) = (Polyveck::<K>::default(), Polyveck::<K>::default());
+    let (mut cp, mut p) = (Poly::default(), Poly::default());
+    let mut z = Polyvecl::<L>::default();
+    let mut pk = vec![0u8; SEEDBYTES + K * POLYT1_PACKEDBYTES];
+    let mut tr = vec![0u8; s.tr.len()];
+
+    // Recompute t = A*s1 + s2 and compare with t0 and tr
+    matrix_times(&mut t, s.mat, s.s1);
+    polyveck_add(&mut t, s.s2);
+    polyveck_reduce(&mut t);
+    polyveck_caddq(&mut t);
+    t1.clone_from(&t);
+    polyveck_power2round(&mut t1, &mut t0);
+    check((0..K).all(|i| t0.vec[i].coeffs == s.t0.vec[i].coeffs))?;
+    pack_pk(&mut pk, s.rho, &t1);
+    shake256(&mut tr, s.tr.len(), &pk, pk.len());
+    check(tr == s.tr)?;
+
+    // Recompute z = y + c*s1 without the NTT
+    poly_challenge::<CTILDEBYTES, TAU>(&mut cp, s.sig);
+    for i in 0..L {
+        challenge_times(&mut z.vec[i], &cp, &s.s1.vec[i]);
+        poly_add(&mut z.vec[i], &s.y.vec[i]);
+        poly_reduce(&mut z.vec[i]);
+    }
+    check((0..L).all(|i| z.vec[i].coeffs == s.z.vec[i].coeffs))?;
+    check(polyvecl_chknorm(&z, (GAMMA1 - BETA) as i32) == 0)?;
+
+    // A*z - c*t = w - c*s2 must have high bits w1 and small low bits
+    matrix_times(&mut r, s.mat, &z);
+    for i in 0..K {
+        challenge_times(&mut p, &cp, &t.vec[i]);
+        poly_sub(&mut r.vec[i], &p);
+    }
+    polyveck_reduce(&mut r);
+    polyveck_caddq(&mut r);
+    polyveck_decompose::<K, GAMMA2>(&mut r, &mut r0);
+    check((0..K).all(|i| r.vec[i].coeffs == s.w1.vec[i].coeffs))?;
+    check(polyveck_chknorm(&r0, (GAMMA2 - BETA) as i32) == 0)?;
+
+    for i in 0..K {
+        challenge_times(&mut p, &cp, &t0.vec[i]);
+        check(poly_chknorm(&p, GAMMA2 as i32) == 0)?;
+    }
+
+    // Verify the bytes that are released against t1
+    verify_mu::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYZ_PACKEDBYTES, TAU, POLY_UNIFORM_NBLOCKS, S>(s.sig, s.mu, &pk)
+        .map_err(|_| SignError::Fault)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{Dilithium2, Dilithium3};
+    use crate::legacy::Dilithium3Legacy;
+    use crate::symmetric::Shake;
+
+    // ML-DSA-44
+    const K: usize = 4;
+    const L: usize = 4;
+    const ETA: usize = 2;
+    const TAU: usize = 39;
+    const BETA: usize = 78;
+    const GAMMA1: usize = 1 << 17;
+    const GAMMA2: usize = (Q - 1) / 88;
+    const OMEGA: usize = 80;
+    const CTILDEBYTES: usize = 32;
+    const POLYW1_PACKEDBYTES: usize = 192;
+    const POLYZ_PACKEDBYTES: usize = 576;
+    const POLYETA_PACKEDBYTES: usize = 96;
+
+    struct Transcript {
+        sig: Vec<u8>,
+        mu: [u8; CRHBYTES],
+        rho: [u8; SEEDBYTES],
+        tr: [u8; TRBYTES],
+        mat: [Polyvecl<L>; K],
+        s1: Polyvecl<L>,
+        s2: Polyveck<K>,
+        t0: Polyveck<K>,
+        y: Polyvecl<L>,
+        z: Polyvecl<L>,
+        w1: Polyveck<K>,
+        h: Polyveck<K>,
+    }
+
+    impl Transcript {
+        /// Reconstruct the accepted iteration from a valid signature.
+        fn new() -> Self {
+            let (sk, _) = Dilithium2::key_gen(Some(vec![1u8; 32])).unwrap();
+            let sig = Dilithium2::sign_internal(b"message".to_vec(), sk.clone(), vec![0u8; RNDBYTES]).unwrap();
+            let mut t = Transcript {
+                sig,
+                mu: [0u8; CRHBYTES],
+                rho: [0u8; SEEDBYTES],
+                tr: [0u8; TRBYTES],
+                mat: [Polyvecl::default(); K],
+                s1: Polyvecl::default(),
+                s2: Polyveck::default(),
+                t0: Polyveck::default(),
+                y: Polyvecl::default(),
+                z: Polyvecl::default(),
+                w1: Polyveck::default(),
+                h: Polyveck::default(),
+            };
+            let mut key = [0u8; SEEDBYTES];
+            unpack_sk::<K, L, POLYETA_PACKEDBYTES, ETA>(&mut t.rho, &mut t.tr, &mut key, &mut t.t0, &mut t.s1, &mut t.s2, &sk);
+            let mut c = [0u8; CTILDEBYTES];
+            unpack_sig::<K, L, CTILDEBYTES, POLYZ_PACKEDBYTES, OMEGA, GAMMA1>(&mut c, &mut t.z, &mut t.h, &t.sig).unwrap();
+            let mut state = KeccakState::default();
+            shake256_absorb(&mut state, &t.tr, TRBYTES);
+            shake256_absorb(&mut state, b"message", 7);
+            shake256_finalize(&mut state);
+            shake256_squeeze(&mut t.mu, CRHBYTES, &mut state);
+            polyvec_matrix_expand::<K, L, 5, Shake>(&mut t.mat, &t.rho);
+
+            // y = z - c*s1, w1 = HighBits(A*y)
+            let (mut cp, mut p) = (Poly::default(), Poly::default());
+            poly_challenge::<CTILDEBYTES, TAU>(&mut cp, &t.sig);
+            t.y = t.z;
+            for i in 0..L {
+                challenge_times(&mut p, &cp, &t.s1.vec[i]);
+                poly_sub(&mut t.y.vec[i], &p);
+                poly_reduce(&mut t.y.vec[i]);
+            }
+            let mut w0 = Polyveck::default();
+            matrix_times(&mut t.w1, &t.mat, &t.y);
+            polyveck_decompose::<K, GAMMA2>(&mut t.w1, &mut w0);
+            t
+        }
+
+        fn check(&self) -> Result<(), SignError> {
+            let signed = Signed {
+                sig: &self.sig,
+                mu: &self.mu,
+                rho: &self.rho,
+                tr: &self.tr,
+                mat: &self.mat,
+                s1: &self.s1,
+                s2: &self.s2,
+                t0: &self.t0,
+                y: &self.y,
+                z: &self.z,
+                w1: &self.w1,
+            };
+            check_signature::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYZ_PACKEDBYTES, TAU, 5, Shake>(&signed)
+        }
+    }
+
+    #[test]
+    fn detects_faults() {
+        let t = Transcript::new();
+        assert_eq!(t.check(), Ok(()));
+
+        // Faulty challenge, z computed with the correct one
+        let mut f = Transcript::new();
+        f.sig[0] ^= 1;
+        assert_eq!(f.check(), Err(SignError::Fault));
+
+        // Faulty z
+        let mut f = Transcript::new();
+        f.z.vec[1].coeffs[7] += 1;
+        assert_eq!(f.check(), Err(SignError::Fault));
+
+        // Faulty secret key
+        let mut f = Transcript::new();
+        f.s1.vec[0].coeffs[3] += 1;
+        assert_eq!(f.check(), Err(SignError::Fault));
+        let mut f = Transcript::new();
+        f.t0.vec[2].coeffs[0] += 1;
+        assert_eq!(f.check(), Err(SignError::Fault));
+
+        // Skipped z norm check: y and z consistent, but z too large
+        let mut f = Transcript::new();
+        let d = (GAMMA1 - BETA) as i32 - f.z.vec[0].coeffs[0];
+        f.z.vec[0].coeffs[0] += d;
+        f.y.vec[0].coeffs[0] += d;
+        assert_eq!(f.check(), Err(SignError::Fault));
+
+        // Faulty w1
+        let mut f = Transcript::new();
+        f.w1.vec[3].coeffs[9] = (f.w1.vec[3].coeffs[9] + 1) % 44;
+        assert_eq!(f.check(), Err(SignError::Fault));
+
+        // Faulty packing of z or of the hints
+        let hints = CTILDEBYTES + L * POLYZ_PACKEDBYTES;
+        let mut f = Transcript::new();
+        f.sig[CTILDEBYTES + 100] ^= 1;
+        assert_eq!(f.check(), Err(SignError::Fault));
+        let mut f = Transcript::new();
+        f.sig[hints] ^= 1;
+        assert_eq!(f.check(), Err(SignError::Fault));
+    }
+
+    #[test]
+    fn hardened_signatures_are_unchanged() {
+        let data = b"hardened".to_vec();
+        let (sk, pk) = Dilithium3::key_gen(Some(vec![2u8; 32])).unwrap();
+        let sig = Dilithium3::sign(data.clone(), sk.clone(), None, false).unwrap();
+        let hardened = with_fault_checks(|| Dilithium3::sign(data.clone(), sk.clone(), None, false)).unwrap();
+        assert_eq!(sig, hardened);
+        let hardened = with_fault_checks(|| Dilithium3::sign(data.clone(), sk.clone(), None, true)).unwrap();
+        assert!(Dilithium3::verify(data.clone(), hardened, pk, None).is_ok());
+
+        let (sk, _) = Dilithium3Legacy::key_gen(Some(vec![3u8; 32])).unwrap();
+        let sig = Dilithium3Legacy::sign(data.clone(), sk.clone(), false).unwrap();
+        assert_eq!(with_fault_checks(|| Dilithium3Legacy::sign(data.clone(), sk.clone(), false)).unwrap(), sig);
+
+        // A corrupted secret key is refused instead of producing a bad signature
+        let (mut sk, _) = Dilithium2::key_gen(Some(vec![4u8; 32])).unwrap();
+        sk[2 * SEEDBYTES + TRBYTES] ^= 0x01;
+        let err = with_fault_checks(|| Dilithium2::sign(data.clone(), sk.clone(), None, false)).unwrap_err();
+        assert_eq!(err.downcast::<SignError>().unwrap(), SignError::Fault);
+    }
+}
@@ -1,6 +1,5 @@
 #![allow(clippy::needless_range_loop)]
 
-#[cfg(not(feature = "aes"))]
 pub const SHAKE128_RATE: usize = 168;
 pub const SHAKE256_RATE: usize = 136;
 
@@ -29,28 +28,54 @@ impl KeccakState {
   }
 }
 
-fn rol(a: u64, offset: u64) -> u64 {
-  (a << offset) ^ (a >> (64 - offset))
-}
-
 /// Load 8 bytes into uint64_t in little-endian order/
 pub fn load64(x: &[u8]) -> u64 {
-  let mut r = 0u64;
-  for i in 0..8 {
-    r |= (x[i] as u64) << (8 * i);
-  }
-  r
+  u64::from_le_bytes(x[..8].try_into().unwrap())
 }
 
 /// Store a 64-bit integer to array of 8 bytes in little-endian order
 pub fn store64(x: &mut [u8], u: u64) {
-  for i in 0..8 {
-    x[i] = (u >> 8 * i) as u8;
+  x[..8].copy_from_slice(&u.to_le_bytes());
+}
+
+/// XOR bytes into the state starting at byte position pos, a word at a
+/// time where possible.
+fn xor_bytes(s: &mut [u64], mut pos: usize, mut data: &[u8]) {
+  while pos & 7 != 0 && !data.is_empty() {
+    s[pos / 8] ^= (data[0] as u64) << (8 * (pos % 8));
+    pos += 1;
+    data = &data[1..];
+  }
+  let mut words = data.chunks_exact(8);
+  for w in &mut words {
+    s[pos / 8] ^= load64(w);
+    pos += 8;
+  }
+  for (i, &b) in words.remainder().iter().enumerate() {
+    s[pos / 8] ^= (b as u64) << (8 * i);
+  }
+}
+
+/// Copy bytes out of the state starting at byte position pos, a word at a
+/// time where possible.
+fn extract_bytes(s: &[u64], mut pos: usize, mut out: &mut [u8]) {
+  while pos & 7 != 0 && !out.is_empty() {
+    out[0] = (s[pos / 8] >> (8 * (pos % 8))) as u8;
+    pos += 1;
+    out = &mut out[1..];
+  }
+  let mut words = out.chunks_exact_mut(8);
+  for w in &mut words {
+    store64(w, s[pos / 8]);
+    pos += 8;
   }
+  let rest = words.into_remainder();
+  let last = s.get(pos / 8).map_or([0u8; 8], |w| w.to_le_bytes());
+  rest.copy_from_slice(&last[..rest.len()]);
 }
 
 /// Keccak round constants
-const KECCAKF_ROUNDCONSTANTS: [u64; NROUNDS] = [
+pub(crate) const KECCAKF_ROUNDCONSTANTS: [u64; NROUNDS] = [
   0x0000000000000001u64,
   0x0000000000008082u64,
   0x800000000000808au64,
@@ -77,252 +102,130 @@ const KECCAKF_ROUNDCONSTANTS: [u64; NROUNDS] = [
   0x8000000080008008u64,
 ];
 
+/// One round from the lanes a into the lanes e, with the lanes be, bi, go,
+/// ki, mi and sa complemented on input and output (the lane complementing
+/// transform of the Keccak team), so that chi needs one NOT per plane
+/// instead of five. c holds the column parities of a on input and those of
+/// e on output.
+macro_rules! round {
+  ($rc:expr, [$ca:ident, $ce:ident, $ci:ident, $co:ident, $cu:ident],
+   [$aba:ident, $abe:ident, $abi:ident, $abo:ident, $abu:ident, $aga:ident, $age:ident, $agi:ident, $ago:ident, $agu:ident, $aka:ident, $ake:ident, $aki:ident, $ako:ident, $aku:ident, $ama:ident, $ame:ident, $ami:ident, $amo:ident, $amu:ident, $asa:ident, $ase:ident, $asi:ident, $aso:ident, $asu:ident],
+   [$eba:ident, $ebe:ident, $ebi:ident, $ebo:ident, $ebu:ident, $ega:ident, $ege:ident, $egi:ident, $ego:ident, $egu:ident, $eka:ident, $eke:ident, $eki:ident, $eko:ident, $eku:ident, $ema:ident, $eme:ident, $emi:ident, $emo:ident, $emu:ident, $esa:ident, $ese:ident, $esi:ident, $eso:ident, $esu:ident]) => {
+    let da = $cu ^ $ce.rotate_left(1);
+    let de = $ca ^ $ci.rotate_left(1);
+    let di = $ce ^ $co.rotate_left(1);
+    let d_o = $ci ^ $cu.rotate_left(1);
+    let du = $co ^ $ca.rotate_left(1);
+
+    let b0 = $aba ^ da;
+    let b1 = ($age ^ de).rotate_left(44);
+    let b2 = ($aki ^ di).rotate_left(43);
+    let b3 = ($amo ^ d_o).rotate_left(21);
+    let b4 = ($asu ^ du).rotate_left(14);
+    $eba = b0 ^ (b1 | b2) ^ $rc;
+    $ebe = b1 ^ (!b2 | b3);
+    $ebi = b2 ^ (b3 & b4);
+    $ebo = b3 ^ (b4 | b0);
+    $ebu = b4 ^ (b0 & b1);
+    $ca = $eba;
+    $ce = $ebe;
+    $ci = $ebi;
+    $co = $ebo;
+    $cu = $ebu;
+
+    let b0 = ($abo ^ d_o).rotate_left(28);
+    let b1 = ($agu ^ du).rotate_left(20);
+    let b2 = ($aka ^ da).rotate_left(3);
+    let b3 = ($ame ^ de).rotate_left(45);
+    let b4 = ($asi ^ di).rotate_left(61);
+    $ega = b0 ^ (b1 | b2);
+    $ege = b1 ^ (b2 & b3);
+    $egi = b2 ^ (b3 | !b4);
+    $ego = b3 ^ (b4 | b0);
+    $egu = b4 ^ (b0 & b1);
+    $ca ^= $ega;
+    $ce ^= $ege;
+    $ci ^= $egi;
+    $co ^= $ego;
+    $cu ^= $egu;
+
+    let b0 = ($abe ^ de).rotate_left(1);
+    let b1 = ($agi ^ di).rotate_left(6);
+    let b2 = ($ako ^ d_o).rotate_left(25);
+    let b3 = ($amu ^ du).rotate_left(8);
+    let b4 = ($asa ^ da).rotate_left(18);
+    $eka = b0 ^ (b1 | b2);
+    $eke = b1 ^ (b2 & b3);
+    $eki = b2 ^ (!b3 & b4);
+    $eko = !b3 ^ (b4 | b0);
+    $eku = b4 ^ (b0 & b1);
+    $ca ^= $eka;
+    $ce ^= $eke;
+    $ci ^= $eki;
+    $co ^= $eko;
+    $cu ^= $eku;
+
+    let b0 = ($abu ^ du).rotate_left(27);
+    let b1 = ($aga ^ da).rotate_left(36);
+    let b2 = ($ake ^ de).rotate_left(10);
+    let b3 = ($ami ^ di).rotate_left(15);
+    let b4 = ($aso ^ d_o).rotate_left(56);
+    $ema = b0 ^ (b1 & b2);
+    $eme = b1 ^ (b2 | b3);
+    $emi = b2 ^ (!b3 | b4);
+    $emo = !b3 ^ (b4 & b0);
+    $emu = b4 ^ (b0 | b1);
+    $ca ^= $ema;
+    $ce ^= $eme;
+    $ci ^= $emi;
+    $co ^= $emo;
+    $cu ^= $emu;
+
+    let b0 = ($abi ^ di).rotate_left(62);
+    let b1 = ($ago ^ d_o).rotate_left(55);
+    let b2 = ($aku ^ du).rotate_left(39);
+    let b3 = ($ama ^ da).rotate_left(41);
+    let b4 = ($ase ^ de).rotate_left(2);
+    $esa = b0 ^ (!b1 & b2);
+    $ese = !b1 ^ (b2 | b3);
+    $esi = b2 ^ (b3 & b4);
+    $eso = b3 ^ (b4 | b0);
+    $esu = b4 ^ (b0 & b1);
+    $ca ^= $esa;
+    $ce ^= $ese;
+    $ci ^= $esi;
+    $co ^= $eso;
+    $cu ^= $esu;
+  };
+}
+
+/// Lanes kept complemented during the permutation, see `round!`.
+const COMPLEMENTED: [usize; 6] = [1, 2, 8, 12, 17, 20];
+
 /// The Keccak F1600 Permutation
 pub fn keccakf1600_statepermute(state: &mut [u64]) {
-  //copyFromState(A, state)
-  let mut aba = state[0];
-  let mut abe = state[1];
-  let mut abi = state[2];
-  let mut abo = state[3];
-  let mut abu = state[4];
-  let mut aga = state[5];
-  let mut age = state[6];
-  let mut agi = state[7];
-  let mut ago = state[8];
-  let mut agu = state[9];
-  let mut aka = state[10];
-  let mut ake = state[11];
-  let mut aki = state[12];
-  let mut ako = state[13];
-  let mut aku = state[14];
-  let mut ama = state[15];
-  let mut ame = state[16];
-  let mut ami = state[17];
-  let mut amo = state[18];
-  let mut amu = state[19];
-  let mut asa = state[20];
-  let mut ase = state[21];
-  let mut asi = state[22];
-  let mut aso = state[23];
-  let mut asu = state[24];
-
-  for round in (0..NROUNDS).step_by(2) {
-    //    prepareTheta
-    let mut bca = aba ^ aga ^ aka ^ ama ^ asa;
-    let mut bce = abe ^ age ^ ake ^ ame ^ ase;
-    let mut bci = abi ^ agi ^ aki ^ ami ^ asi;
-    let mut bco = abo ^ ago ^ ako ^ amo ^ aso;
-    let mut bcu = abu ^ agu ^ aku ^ amu ^ asu;
-
-    //thetaRhoPiChiIotaPrepareTheta(round  , A, E)
-    let mut da = bcu ^ rol(bce, 1);
-    let mut de = bca ^ rol(bci, 1);
-    let mut di = bce ^ rol(bco, 1);
-    let mut d_o = bci ^ rol(bcu, 1);
-    let mut du = bco ^ rol(bca, 1);
-
-    aba ^= da;
-    bca = aba;
-    age ^= de;
-    bce = rol(age, 44);
-    aki ^= di;
-    bci = rol(aki, 43);
-    amo ^= d_o;
-    bco = rol(amo, 21);
-    asu ^= du;
-    bcu = rol(asu, 14);
-    let mut eba = bca ^ ((!bce) & bci);
-    eba ^= KECCAKF_ROUNDCONSTANTS[round];
-    let mut ebe = bce ^ ((!bci) & bco);
-    let mut ebi = bci ^ ((!bco) & bcu);
-    let mut ebo = bco ^ ((!bcu) & bca);
-    let mut ebu = bcu ^ ((!bca) & bce);
-
-    abo ^= d_o;
-    bca = rol(abo, 28);
-    agu ^= du;
-    bce = rol(agu, 20);
-    aka ^= da;
-    bci = rol(aka, 3);
-    ame ^= de;
-    bco = rol(ame, 45);
-    asi ^= di;
-    bcu = rol(asi, 61);
-    let mut ega = bca ^ ((!bce) & bci);
-    let mut ege = bce ^ ((!bci) & bco);
-    let mut egi = bci ^ ((!bco) & bcu);
-    let mut ego = bco ^ ((!bcu) & bca);
-    let mut egu = bcu ^ ((!bca) & bce);
-
-    abe ^= de;
-    bca = rol(abe, 1);
-    agi ^= di;
-    bce = rol(agi, 6);
-    ako ^= d_o;
-    bci = rol(ako, 25);
-    amu ^= du;
-    bco = rol(amu, 8);
-    asa ^= da;
-    bcu = rol(asa, 18);
-    let mut eka = bca ^ ((!bce) & bci);
-    let mut eke = bce ^ ((!bci) & bco);
-    let mut eki = bci ^ ((!bco) & bcu);
-    let mut eko = bco ^ ((!bcu) & bca);
-    let mut eku = bcu ^ ((!bca) & bce);
-
-    abu ^= du;
-    bca = rol(abu, 27);
-    aga ^= da;
-    bce = rol(aga, 36);
-    ake ^= de;
-    bci = rol(ake, 10);
-    ami ^= di;
-    bco = rol(ami, 15);
-    aso ^= d_o;
-    bcu = rol(aso, 56);
-    let mut ema = bca ^ ((!bce) & bci);
-    let mut eme = bce ^ ((!bci) & bco);
-    let mut emi = bci ^ ((!bco) & bcu);
-    let mut emo = bco ^ ((!bcu) & bca);
-    let mut emu = bcu ^ ((!bca) & bce);
-
-    abi ^= di;
-    bca = rol(abi, 62);
-    ago ^= d_o;
-    bce = rol(ago, 55);
-    aku ^= du;
-    bci = rol(aku, 39);
-    ama ^= da;
-    bco = rol(ama, 41);
-    ase ^= de;
-    bcu = rol(ase, 2);
-    let mut esa = bca ^ ((!bce) & bci);
-    let mut ese = bce ^ ((!bci) & bco);
-    let mut esi = bci ^ ((!bco) & bcu);
-    let mut eso = bco ^ ((!bcu) & bca);
-    let mut esu = bcu ^ ((!bca) & bce);
-
-    //    prepareTheta
-    bca = eba ^ ega ^ eka ^ ema ^ esa;
-    bce = ebe ^ ege ^ eke ^ eme ^ ese;
-    bci = ebi ^ egi ^ eki ^ emi ^ esi;
-    bco = ebo ^ ego ^ eko ^ emo ^ eso;
-    bcu = ebu ^ egu ^ eku ^ emu ^ esu;
-
-    //thetaRhoPiChiIotaPrepareTheta(round+1, E, A)
-    da = bcu ^ rol(bce, 1);
-    de = bca ^ rol(bci, 1);
-    di = bce ^ rol(bco, 1);
-    d_o = bci ^ rol(bcu, 1);
-    du = bco ^ rol(bca, 1);
-
-    eba ^= da;
-    bca = eba;
-    ege ^= de;
-    bce = rol(ege, 44);
-    eki ^= di;
-    bci = rol(eki, 43);
-    emo ^= d_o;
-    bco = rol(emo, 21);
-    esu ^= du;
-    bcu = rol(esu, 14);
-    aba = bca ^ ((!bce) & bci);
-    aba ^= KECCAKF_ROUNDCONSTANTS[round + 1];
-    abe = bce ^ ((!bci) & bco);
-    abi = bci ^ ((!bco) & bcu);
-    abo = bco ^ ((!bcu) & bca);
-    abu = bcu ^ ((!bca) & bce);
-
-    ebo ^= d_o;
-    bca = rol(ebo, 28);
-    egu ^= du;
-    bce = rol(egu, 20);
-    eka ^= da;
-    bci = rol(eka, 3);
-    eme ^= de;
-    bco = rol(eme, 45);
-    esi ^= di;
-    bcu = rol(esi, 61);
-    aga = bca ^ ((!bce) & bci);
-    age = bce ^ ((!bci) & bco);
-    agi = bci ^ ((!bco) & bcu);
-    ago = bco ^ ((!bcu) & bca);
-    agu = bcu ^ ((!bca) & bce);
-
-    ebe ^= de;
-    bca = rol(ebe, 1);
-    egi ^= di;
-    bce = rol(egi, 6);
-    eko ^= d_o;
-    bci = rol(eko, 25);
-    emu ^= du;
-    bco = rol(emu, 8);
-    esa ^= da;
-    bcu = rol(esa, 18);
-    aka = bca ^ ((!bce) & bci);
-    ake = bce ^ ((!bci) & bco);
-    aki = bci ^ ((!bco) & bcu);
-    ako = bco ^ ((!bcu) & bca);
-    aku = bcu ^ ((!bca) & bce);
-
-    ebu ^= du;
-    bca = rol(ebu, 27);
-    ega ^= da;
-    bce = rol(ega, 36);
-    eke ^= de;
-    bci = rol(eke, 10);
-    emi ^= di;
-    bco = rol(emi, 15);
-    eso ^= d_o;
-    bcu = rol(eso, 56);
-    ama = bca ^ ((!bce) & bci);
-    ame = bce ^ ((!bci) & bco);
-    ami = bci ^ ((!bco) & bcu);
-    amo = bco ^ ((!bcu) & bca);
-    amu = bcu ^ ((!bca) & bce);
-
-    ebi ^= di;
-    bca = rol(ebi, 62);
-    ego ^= d_o;
-    bce = rol(ego, 55);
-    eku ^= du;
-    bci = rol(eku, 39);
-    ema ^= da;
-    bco = rol(ema, 41);
-    ese ^= de;
-    bcu = rol(ese, 2);
-    asa = bca ^ ((!bce) & bci);
-    ase = bce ^ ((!bci) & bco);
-    asi = bci ^ ((!bco) & bcu);
-    aso = bco ^ ((!bcu) & bca);
-    asu = bcu ^ ((!bca) & bce);
+  for &i in &COMPLEMENTED {
+    state[i] = !state[i];
+  }
+  let [mut aba, mut abe, mut abi, mut abo, mut abu, mut aga, mut age, mut agi, mut ago, mut agu, mut aka, mut ake, mut aki, mut ako, mut aku, mut ama, mut ame, mut ami, mut amo, mut amu, mut asa, mut ase, mut asi, mut aso, mut asu]: [u64; 25] = state[..25].try_into().unwrap();
+  #[allow(clippy::type_complexity)]
+  let (mut eba, mut ebe, mut ebi, mut ebo, mut ebu, mut ega, mut ege, mut egi, mut ego, mut egu, mut eka, mut eke, mut eki, mut eko, mut eku, mut ema, mut eme, mut emi, mut emo, mut emu, mut esa, mut ese, mut esi, mut eso, mut esu): (u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64, u64);
+
+  let mut ca = aba ^ aga ^ aka ^ ama ^ asa;
+  let mut ce = abe ^ age ^ ake ^ ame ^ ase;
+  let mut ci = abi ^ agi ^ aki ^ ami ^ asi;
+  let mut co = abo ^ ago ^ ako ^ amo ^ aso;
+  let mut cu = abu ^ agu ^ aku ^ amu ^ asu;
+
+  for rc in KECCAKF_ROUNDCONSTANTS.chunks_exact(2) {
+    round!(rc[0], [ca, ce, ci, co, cu], [aba, abe, abi, abo, abu, aga, age, agi, ago, agu, aka, ake, aki, ako, aku, ama, ame, ami, amo, amu, asa, ase, asi, aso, asu], [eba, ebe, ebi, ebo, ebu, ega, ege, egi, ego, egu, eka, eke, eki, eko, eku, ema, eme, emi, emo, emu, esa, ese, esi, eso, esu]);
+    round!(rc[1], [ca, ce, ci, co, cu], [eba, ebe, ebi, ebo, ebu, ega, ege, egi, ego, egu, eka, eke, eki, eko, eku, ema, eme, emi, emo, emu, esa, ese, esi, eso, esu], [aba, abe, abi, abo, abu, aga, age, agi, ago, agu, aka, ake, aki, ako, aku, ama, ame, ami, amo, amu, asa, ase, asi, aso, asu]);
   }
 
-  state[0] = aba;
-  state[1] = abe;
-  state[2] = abi;
-  state[3] = abo;
-  state[4] = abu;
-  state[5] = aga;
-  state[6] = age;
-  state[7] = agi;
-  state[8] = ago;
-  state[9] = agu;
-  state[10] = aka;
-  state[11] = ake;
-  state[12] = aki;
-  state[13] = ako;
-  state[14] = aku;
-  state[15] = ama;
-  state[16] = ame;
-  state[17] = ami;
-  state[18] = amo;
-  state[19] = amu;
-  state[20] = asa;
-  state[21] = ase;
-  state[22] = asi;
-  state[23] = aso;
-  state[24] = asu;
+  state[..25].copy_from_slice(&[aba, abe, abi, abo, abu, aga, age, agi, ago, agu, aka, ake, aki, ako, aku, ama, ame, ami, amo, amu, asa, ase, asi, aso, asu]);
+  for &i in &COMPLEMENTED {
+    state[i] = !state[i];
+  }
 }
 
 /// Absorb step of Keccak; incremental.
@@ -330,26 +233,18 @@ fn keccak_absorb(
   state: &mut KeccakState,
   r: usize,
   input: &[u8],
-  mut inlen: usize,
+  inlen: usize,
 ) {
-  let mut idx = 0;
+  let mut input = &input[..inlen];
   let mut pos = state.pos;
-  while pos + inlen >= r {
-    for i in pos..r {
-      state.s[i / 8] ^= (input[idx] as u64) << 8 * (i % 8);
-      idx += 1;
-    }
-    inlen -= r - pos;
+  while pos + input.len() >= r {
+    xor_bytes(&mut state.s, pos, &input[..r - pos]);
+    input = &input[r - pos..];
     keccakf1600_statepermute(&mut state.s);
     pos = 0;
   }
-  let mut i = pos;
-  while i < pos + inlen {
-    state.s[i / 8] ^= (input[idx] as u64) << 8 * (i % 8);
-    idx += 1;
-    i += 1
-  }
-  state.pos = i;
+  xor_bytes(&mut state.s, pos, input);
+  state.pos = pos + input.len();
 }
 
 /// Finalize absorb step.
@@ -365,28 +260,24 @@ fn keccak_finalize(s: &mut [u64; 25], pos: usize, r: usize, p: u8) {
 // Returns new position pos in current block
 fn keccak_squeeze(
   out: &mut [u8],
-  mut outlen: usize,
+  outlen: usize,
   s: &mut [u64; 25],
   mut pos: usize,
   r: usize,
 ) -> usize {
-  while outlen != 0 {
+  let mut out = &mut out[..outlen];
+  while !out.is_empty() {
     if pos == r {
       keccakf1600_statepermute(s);
       pos = 0;
     }
-    let mut i = pos;
-    let mut idx = 0;
-    while i < r && i < pos + outlen {
-      out[idx] = (s[i / 8] >> 8 * (i % 8)) as u8;
-      idx += 1;
-      i += 1;
-    }
-    outlen -= i - pos;
-    pos = i;
+    let n = (r - pos).min(out.len());
+    let (chunk, rest) = out.split_at_mut(n);
+    extract_bytes(s, pos, chunk);
+    out = rest;
+    pos += n;
   }
-
-  return pos;
+  pos
 }
 
 /// Absorb step of Keccak;
@@ -409,9 +300,7 @@ fn keccak_absorb_once(
     keccakf1600_statepermute(s);
   }
 
-  for i in 0..inlen {
-    s[i / 8] ^= (input[idx + i] as u64) << 8 * (i % 8);
-  }
+  xor_bytes(s, 0, &input[idx..idx + inlen]);
 
   s[inlen / 8] ^= (p as u64) << 8 * (inlen % 8);
   s[(r - 1) / 8] ^= 1u64 << 63;
@@ -439,13 +328,11 @@ fn keccak_squeezeblocks(
 }
 
 /// Description: Absorb step of the SHAKE128 XOF; incremental.
-#[cfg(not(feature = "aes"))]
 pub fn shake128_absorb(state: &mut KeccakState, input: &[u8], inlen: usize) {
   keccak_absorb(state, SHAKE128_RATE, input, inlen);
 }
 
 /// Finalize absorb step of the SHAKE128 XOF.
-#[cfg(not(feature = "aes"))]
 pub fn shake128_finalize(state: &mut KeccakState) {
   keccak_finalize(&mut state.s, state.pos as usize, SHAKE128_RATE, 0x1F);
   state.pos = SHAKE128_RATE;
@@ -455,7 +342,6 @@ pub fn shake128_finalize(state: &mut KeccakState) {
 ///  SHAKE128_RATE bytes each. Can be called multiple times
 ///  to keep squeezing. Assumes new block has not yet been
 ///  started (state->pos = SHAKE128_RATE).
-#[cfg(not(feature = "aes"))]
 pub fn shake128_squeezeblocks(
   output: &mut [u8],
   nblocks: usize,
@@ -523,3 +409,76 @@ pub fn shake256(
   let idx = nblocks * SHAKE256_RATE;
   shake256_squeeze(&mut output[idx..], outlen, &mut state);
 }
+
+#[cfg(test)]
+mod tests {
+  use sha3::digest::{ExtendableOutput, Update, XofReader};
+
+  use super::*;
+
+  #[test]
+  fn permutation_of_zero_state() {
+    let expected: [u64; 25] = [
+      0xF1258F7940E1DDE7, 0x84D5CCF933C0478A, 0xD598261EA65AA9EE,
+      0xBD1547306F80494D, 0x8B284E056253D057, 0xFF97A42D7F8E6FD4,
+      0x90FEE5A0A44647C4, 0x8C5BDA0CD6192E76, 0xAD30A6F71B19059C,
+      0x30935AB7D08FFC64, 0xEB5AA93F2317D635, 0xA9A6E6260D712103,
+      0x81A57C16DBCF555F, 0x43B831CD0347C826, 0x01F22F1A11A5569F,
+      0x05E5635A21D9AE61, 0x64BEFEF28CC970F2, 0x613670957BC46611,
+      0xB87C5A554FD00ECB, 0x8C3EE88A1CCF32C8, 0x940C7922AE3A2614,
+      0x1841F924A2C509E4, 0x16F53526E70465C2, 0x75F644E97F30A13B,
+      0xEAF1FF7B5CECA249,
+    ];
+    let mut s = [0u64; 25];
+    keccakf1600_statepermute(&mut s);
+    assert_eq!(s, expected);
+  }
+
+  // Split input and output at offsets that are not multiples of 8 so the
+  // partial-word paths of absorb and squeeze are exercised.
+  #[test]
+  fn incremental_shake256_unaligned() {
+    let input: Vec<u8> = (0..500u32).map(|i| (i * 7 + 3) as u8).collect();
+    let mut expected = [0u8; 400];
+    let mut h = sha3::Shake256::default();
+    h.update(&input);
+    h.finalize_xof().read(&mut expected);
+
+    for split in [0, 1, 5, 13, 135, 136, 137, 271, 500] {
+      let mut state = KeccakState::default();
+      shake256_absorb(&mut state, &input[..split], split);
+      shake256_absorb(&mut state, &input[split..], input.len() - split);
+      shake256_finalize(&mut state);
+      let mut out = [0u8; 400];
+      let mut done = 0;
+      for step in [3, 9, 1, 150, 237] {
+        shake256_squeeze(&mut out[done..], step, &mut state);
+        done += step;
+      }
+      assert_eq!(out, expected, "split {}", split);
+    }
+
+    let mut out = [0u8; 400];
+    shake256(&mut out, 400, &input, 301);
+    let mut h = sha3::Shake256::default();
+    h.update(&input[..301]);
+    h.finalize_xof().read(&mut expected);
+    assert_eq!(out, expected);
+  }
+
+  #[test]
+  fn shake128_blocks() {
+    let input = [0xA5u8; 43];
+    let mut expected = [0u8; 2 * SHAKE128_RATE];
+    let mut h = sha3::Shake128::default();
+    h.update(&input);
+    h.finalize_xof().read(&mut expected);
+
+    let mut state = KeccakState::default();
+    shake128_absorb(&mut state, &input, input.len());
+    shake128_finalize(&mut state);
+    let mut out = [0u8; 2 * SHAKE128_RATE];
+    shake128_squeezeblocks(&mut out, 2, &mut state);
+    assert_eq!(out, expected);
+  }
+}
@@ -0,0 +1,312 @@
+//! Four Keccak states permuted in parallel, after fips202x4.c of the avx2
+//! reference implementation. Each lane produces exactly the output of the
+//! scalar functions in fips202.rs. The permutation uses AVX2 when the CPU
+//! supports it, two 2-way NEON permutations on aarch64, and runs the four
+//! states one after another otherwise.
+
+#![allow(clippy::needless_range_loop)]
+
+use crate::fips202::{
+  keccakf1600_statepermute, load64, store64, SHAKE128_RATE, SHAKE256_RATE,
+};
+
+/// Lane-interleaved states: `s[i][lane]` is word i of state `lane`.
+#[derive(Copy, Clone, Default)]
+pub struct KeccakStateX4 {
+  pub s: [[u64; 4]; 25],
+}
+
+pub fn keccakf1600_statepermute_x4(s: &mut [[u64; 4]; 25]) {
+  #[cfg(target_arch = "x86_64")]
+  if is_x86_feature_detected!("avx2") {
+    // SAFETY: AVX2 support was checked
+    return unsafe { avx2::keccakf1600_statepermute_x4(s) };
+  }
+  #[cfg(target_arch = "aarch64")]
+  if std::arch::is_aarch64_feature_detected!("neon") {
+    // SAFETY: NEON support was checked
+    unsafe {
+      neon::keccakf1600_statepermute_x2(s, 0);
+      neon::keccakf1600_statepermute_x2(s, 2);
+    }
+    return;
+  }
+  statepermute_lanes(s);
+}
+
+fn statepermute_lanes(s: &mut [[u64; 4]; 25]) {
+  for lane in 0..4 {
+    let mut t = [0u64; 25];
+    for i in 0..25 {
+      t[i] = s[i][lane];
+    }
+    keccakf1600_statepermute(&mut t);
+    for i in 0..25 {
+      s[i][lane] = t[i];
+    }
+  }
+}
+
+/// Initialize, absorb four inputs of equal length and finalize.
+fn keccakx4_absorb_once(s: &mut [[u64; 4]; 25], r: usize, input: [&[u8]; 4], mut inlen: usize, p: u8) {
+  *s = [[0u64; 4]; 25];
+  let mut idx = 0;
+  while inlen >= r {
+    for i in 0..r / 8 {
+      for lane in 0..4 {
+        s[i][lane] ^= load64(&input[lane][idx + 8 * i..]);
+      }
+    }
+    idx += r;
+    inlen -= r;
+    keccakf1600_statepermute_x4(s);
+  }
+
+  for i in 0..inlen {
+    for lane in 0..4 {
+      s[i / 8][lane] ^= (input[lane][idx + i] as u64) << (8 * (i % 8));
+    }
+  }
+  for lane in 0..4 {
+    s[inlen / 8][lane] ^= (p as u64) << (8 * (inlen % 8));
+    s[(r - 1) / 8][lane] ^= 1u64 << 63;
+  }
+}
+
+fn keccakx4_squeezeblocks(mut out: [&mut [u8]; 4], nblocks: usize, s: &mut [[u64; 4]; 25], r: usize) {
+  for b in 0..nblocks {
+    keccakf1600_statepermute_x4(s);
+    for i in 0..r / 8 {
+      for (lane, o) in out.iter_mut().enumerate() {
+        store64(&mut o[b * r + 8 * i..], s[i][lane]);
+      }
+    }
+  }
+}
+
+pub fn shake128x4_absorb_once(state: &mut KeccakStateX4, input: [&[u8]; 4], inlen: usize) {
+  keccakx4_absorb_once(&mut state.s, SHAKE128_RATE, input, inlen, 0x1F);
+}
+
+pub fn shake128x4_squeezeblocks(out: [&mut [u8]; 4], nblocks: usize, state: &mut KeccakStateX4) {
+  keccakx4_squeezeblocks(out, nblocks, &mut state.s, SHAKE128_RATE);
+}
+
+pub fn shake256x4_absorb_once(state: &mut KeccakStateX4, input: [&[u8]; 4], inlen: usize) {
+  keccakx4_absorb_once(&mut state.s, SHAKE256_RATE, input, inlen, 0x1F);
+}
+
+pub fn shake256x4_squeezeblocks(out: [&mut [u8]; 4], nblocks: usize, state: &mut KeccakStateX4) {
+  keccakx4_squeezeblocks(out, nblocks, &mut state.s, SHAKE256_RATE);
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+const ROUNDCONSTANTS: [u64; 24] = [
+  0x0000000000000001, 0x0000000000008082, 0x800000000000808A, 0x8000000080008000,
+  0x000000000000808B, 0x0000000080000001, 0x8000000080008081, 0x8000000000008009,
+  0x000000000000008A, 0x0000000000000088, 0x0000000080008009, 0x000000008000000A,
+  0x000000008000808B, 0x800000000000008B, 0x8000000000008089, 0x8000000000008003,
+  0x8000000000008002, 0x8000000000000080, 0x000000000000800A, 0x800000008000000A,
+  0x8000000080008081, 0x8000000000008080, 0x0000000080000001, 0x8000000080008008,
+];
+
+/// Rotation of word x + 5y in rho.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+const RHO: [u32; 25] = [
+  0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8, 18, 2, 61, 56, 14,
+];
+
+/// Position y + 5((2x + 3y) mod 5) that word x + 5y moves to in pi.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+const PI: [usize; 25] = {
+  let mut pi = [0; 25];
+  let mut i = 0;
+  while i < 25 {
+    let (x, y) = (i % 5, i / 5);
+    pi[i] = y + 5 * ((2 * x + 3 * y) % 5);
+    i += 1;
+  }
+  pi
+};
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+  use std::arch::x86_64::*;
+
+  use super::{PI, RHO, ROUNDCONSTANTS};
+
+  #[target_feature(enable = "avx2")]
+  #[inline]
+  fn rol(a: __m256i, r: u32) -> __m256i {
+    let l = _mm_cvtsi32_si128(r as i32);
+    let rr = _mm_cvtsi32_si128((64 - r) as i32 & 63);
+    _mm256_or_si256(_mm256_sll_epi64(a, l), _mm256_srl_epi64(a, rr))
+  }
+
+  #[target_feature(enable = "avx2")]
+  pub fn keccakf1600_statepermute_x4(s: &mut [[u64; 4]; 25]) {
+    // SAFETY: each word is four u64, unaligned loads
+    let mut a = s.map(|w| unsafe { _mm256_loadu_si256(w.as_ptr() as *const __m256i) });
+    let mut b = [_mm256_setzero_si256(); 25];
+    for rc in ROUNDCONSTANTS {
+      // theta
+      let mut c = [_mm256_setzero_si256(); 5];
+      for x in 0..5 {
+        c[x] = _mm256_xor_si256(
+          _mm256_xor_si256(_mm256_xor_si256(a[x], a[x + 5]), _mm256_xor_si256(a[x + 10], a[x + 15])),
+          a[x + 20],
+        );
+      }
+      for x in 0..5 {
+        let d = _mm256_xor_si256(c[(x + 4) % 5], rol(c[(x + 1) % 5], 1));
+        for y in 0..5 {
+          a[x + 5 * y] = _mm256_xor_si256(a[x + 5 * y], d);
+        }
+      }
+      // rho and pi
+      for i in 0..25 {
+        b[PI[i]] = if RHO[i] == 0 { a[i] } else { rol(a[i], RHO[i]) };
+      }
+      // chi
+      for y in 0..5 {
+        for x in 0..5 {
+          let t = _mm256_andnot_si256(b[(x + 1) % 5 + 5 * y], b[(x + 2) % 5 + 5 * y]);
+          a[x + 5 * y] = _mm256_xor_si256(b[x + 5 * y], t);
+        }
+      }
+      // iota
+      a[0] = _mm256_xor_si256(a[0], _mm256_set1_epi64x(rc as i64));
+    }
+    for (w, a) in s.iter_mut().zip(a) {
+      // SAFETY: each word is four u64, unaligned stores
+      unsafe { _mm256_storeu_si256(w.as_mut_ptr() as *mut __m256i, a) };
+    }
+  }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+  use std::arch::aarch64::*;
+
+  use super::{PI, RHO, ROUNDCONSTANTS};
+
+  #[target_feature(enable = "neon")]
+  fn rol(a: uint64x2_t, r: u32) -> uint64x2_t {
+    let l = vshlq_u64(a, vdupq_n_s64(r as i64));
+    vorrq_u64(l, vshlq_u64(a, vdupq_n_s64(r as i64 - 64)))
+  }
+
+  /// Permute lanes `first` and `first + 1` of the four states.
+  #[target_feature(enable = "neon")]
+  pub fn keccakf1600_statepermute_x2(s: &mut [[u64; 4]; 25], first: usize) {
+    assert!(first <= 2);
+    // SAFETY: lanes first and first + 1 exist
+    let mut a = s.map(|w| unsafe { vld1q_u64(w[first..].as_ptr()) });
+    let mut b = [vdupq_n_u64(0); 25];
+    for rc in ROUNDCONSTANTS {
+      // theta
+      let mut c = [vdupq_n_u64(0); 5];
+      for x in 0..5 {
+        c[x] = veorq_u64(veorq_u64(veorq_u64(a[x], a[x + 5]), veorq_u64(a[x + 10], a[x + 15])), a[x + 20]);
+      }
+      for x in 0..5 {
+        let d = veorq_u64(c[(x + 4) % 5], rol(c[(x + 1) % 5], 1));
+        for y in 0..5 {
+          a[x + 5 * y] = veorq_u64(a[x + 5 * y], d);
+        }
+      }
+      // rho and pi
+      for i in 0..25 {
+        b[PI[i]] = if RHO[i] == 0 { a[i] } else { rol(a[i], RHO[i]) };
+      }
+      // chi
+      for y in 0..5 {
+        for x in 0..5 {
+          let t = vbicq_u64(b[(x + 2) % 5 + 5 * y], b[(x + 1) % 5 + 5 * y]);
+          a[x + 5 * y] = veorq_u64(b[x + 5 * y], t);
+        }
+      }
+      // iota
+      a[0] = veorq_u64(a[0], vdupq_n_u64(rc));
+    }
+    for (w, a) in s.iter_mut().zip(a) {
+      // SAFETY: as for the loads
+      unsafe { vst1q_u64(w[first..].as_mut_ptr(), a) };
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::fips202::*;
+
+  #[test]
+  #[cfg(target_arch = "x86_64")]
+  fn avx2_permutation() {
+    if !is_x86_feature_detected!("avx2") {
+      return;
+    }
+    let mut a = [[0u64; 4]; 25];
+    for (i, w) in a.iter_mut().enumerate() {
+      *w = [0, 1, 2, 3].map(|lane| (i as u64 + 1).wrapping_mul(0x9E3779B97F4A7C15) ^ lane);
+    }
+    let mut b = a;
+    for _ in 0..3 {
+      unsafe { avx2::keccakf1600_statepermute_x4(&mut a) };
+      statepermute_lanes(&mut b);
+      assert_eq!(a, b);
+    }
+  }
+
+  #[test]
+  #[cfg(target_arch = "aarch64")]
+  fn neon_permutation() {
+    let mut a = [[0u64; 4]; 25];
+    for (i, w) in a.iter_mut().enumerate() {
+      *w = [0, 1, 2, 3].map(|lane| (i as u64 + 1).wrapping_mul(0x9E3779B97F4A7C15) ^ lane);
+    }
+    let mut b = a;
+    for _ in 0..3 {
+      unsafe {
+        neon::keccakf1600_statepermute_x2(&mut a, 0);
+        neon::keccakf1600_statepermute_x2(&mut a, 2);
+      }
+      statepermute_lanes(&mut b);
+      assert_eq!(a, b);
+    }
+  }
+
+  #[test]
+  fn lanes_match_scalar_shake() {
+    for inlen in [34, 66, 200] {
+      let inputs: Vec<Vec<u8>> =
+        (0..4).map(|lane| (0..inlen).map(|i| (i * 13 + lane * 71) as u8).collect()).collect();
+      let input = [&inputs[0][..], &inputs[1][..], &inputs[2][..], &inputs[3][..]];
+
+      let mut out = [[0u8; 3 * SHAKE128_RATE]; 4];
+      let mut state = KeccakStateX4::default();
+      shake128x4_absorb_once(&mut state, input, inlen);
+      let [o0, o1, o2, o3] = &mut out;
+      shake128x4_squeezeblocks([&mut o0[..], &mut o1[..], &mut o2[..], &mut o3[..]], 3, &mut state);
+      for lane in 0..4 {
+        let mut s = KeccakState::default();
+        shake128_absorb(&mut s, input[lane], inlen);
+        shake128_finalize(&mut s);
+        let mut expected = [0u8; 3 * SHAKE128_RATE];
+        shake128_squeezeblocks(&mut expected, 3, &mut s);
+        assert_eq!(out[lane], expected);
+      }
+
+      let mut out = [[0u8; 2 * SHAKE256_RATE]; 4];
+      shake256x4_absorb_once(&mut state, input, inlen);
+      let [o0, o1, o2, o3] = &mut out;
+      shake256x4_squeezeblocks([&mut o0[..], &mut o1[..], &mut o2[..], &mut o3[..]], 2, &mut state);
+      for lane in 0..4 {
+        let mut expected = [0u8; 2 * SHAKE256_RATE];
+        shake256(&mut expected, 2 * SHAKE256_RATE, input[lane], inlen);
+        assert_eq!(out[lane], expected);
+      }
+    }
+  }
+}
@@ -0,0 +1,345 @@
+//! JOSE support following the IETF ML-DSA for JOSE and COSE draft: JWKs of
+//! key type "AKP" and JWS with `alg` "ML-DSA-44", "ML-DSA-65" or "ML-DSA-87".
+//!
+//! ```text
+//! { "kty": "AKP", "alg": "ML-DSA-44", "pub": <base64url pk>, "priv": <base64url seed> }
+//! ```
+//!
+//! The private key is the 32 byte key generation seed. JWS signing input is
+//! signed with `Dilithium::sign` and an empty context.
+
+use anyhow::{anyhow, bail};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde_json::{json, Map, Value};
+
+use crate::algorithm::{Dilithium, ParameterSet};
+use crate::params::SEEDBYTES;
+use crate::symmetric::StreamBackend;
+
+/// An AKP JSON Web Key. `seed` is only present for private keys.
+#[derive(Clone, PartialEq, Eq)]
+pub struct AkpJwk {
+    pub alg: ParameterSet,
+    pub public_key: Vec<u8>,
+    pub seed: Option<Vec<u8>>,
+    pub kid: Option<String>,
+}
+
+/// Seed elided
+impl std::fmt::Debug for AkpJwk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AkpJwk {{ alg: {:?}, public_key: {}, kid: {:?}, seed: <elided> }}", self.alg, hex::encode(&self.public_key), self.kid)
+    }
+}
+
+impl AkpJwk {
+    pub fn to_json(&self) -> String {
+        let mut jwk = Map::new();
+        jwk.insert("kty".into(), "AKP".into());
+        jwk.insert("alg".into(), self.alg.name().into());
+        if let Some(kid) = &self.kid {
+            jwk.insert("kid".into(), kid.as_str().into());
+        }
+        jwk.insert("pub".into(), URL_SAFE_NO_PAD.encode(&self.public_key).into());
+        if let Some(seed) = &self.seed {
+            jwk.insert("priv".into(), URL_SAFE_NO_PAD.encode(seed).into());
+        }
+        Value::Object(jwk).to_string()
+    }
+
+    /// The JWK without its private part.
+    pub fn to_public(&self) -> Self {
+        AkpJwk { seed: None, ..self.clone() }
+    }
+
+    /// Parse an AKP JWK. Key lengths are checked by the parameter set
+    /// specific `Dilithium::from_jwk`.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let jwk: Value = serde_json::from_str(json)?;
+        if jwk.get("kty").and_then(Value::as_str) != Some("AKP") {
+            bail!("JWK key type must be AKP");
+        }
+        let alg = jwk.get("alg").and_then(Value::as_str).ok_or_else(|| anyhow!("JWK has no alg"))?;
+        let alg = ParameterSet::from_name(alg).ok_or_else(|| anyhow!("unsupported JWK alg {}", alg))?;
+        let public_key = b64_member(&jwk, "pub")?.ok_or_else(|| anyhow!("JWK has no pub"))?;
+        let seed = b64_member(&jwk, "priv")?;
+        let kid = jwk.get("kid").and_then(Value::as_str).map(str::to_string);
+        Ok(AkpJwk { alg, public_key, seed, kid })
+    }
+}
+
+fn b64_member(object: &Value, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+    match object.get(name) {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(URL_SAFE_NO_PAD.decode(s).map_err(|_| anyhow!("invalid base64url in {}", name))?)),
+        Some(_) => bail!("{} must be a string", name),
+    }
+}
+
+fn b64_decode(s: &str) -> anyhow::Result<Vec<u8>> {
+    URL_SAFE_NO_PAD.decode(s).map_err(|_| anyhow!("invalid base64url"))
+}
+
+/// Check a decoded protected header: `alg` must name the expected parameter
+/// set, and extensions marked critical are not supported.
+fn check_header(protected: &[u8], alg: ParameterSet) -> anyhow::Result<()> {
+    let header: Value = serde_json::from_slice(protected)?;
+    if !header.is_object() {
+        bail!("JWS protected header must be an object");
+    }
+    match header.get("alg").and_then(Value::as_str) {
+        Some(a) if a == alg.name() => {}
+        Some(a) => bail!("JWS alg {} does not match {}", a, alg.name()),
+        None => bail!("JWS protected header has no alg"),
+    }
+    if header.get("crit").is_some() {
+        bail!("critical JWS header parameters are not supported");
+    }
+    Ok(())
+}
+
+impl<
+    const K: usize,
+    const L: usize,
+    const ETA: usize,
+    const TAU: usize,
+    const BETA: usize,
+    const GAMMA1: usize,
+    const GAMMA2: usize,
+    const OMEGA: usize,
+    const PUBLICKEYBYTES: usize,
+    const SECRETKEYBYTES: usize,
+    const POLY_UNIFORM_NBLOCKS: usize,
+    S: StreamBackend,
+    const POLY_UNIFORM_ETA_NBLOCKS: usize,
+    const POLYETA_PACKEDBYTES: usize,
+    const SIGNBYTES: usize,
+    const POLYW1_PACKEDBYTES: usize,
+    const CTILDEBYTES: usize,
+    const POLYZ_PACKEDBYTES: usize,
+    const POLYVECH_PACKEDBYTES: usize,
+    const POLY_UNIFORM_GAMMA1_NBLOCKS: usize,
+>
+Dilithium<
+    K,
+    L,
+    ETA,
+    TAU,
+    BETA,
+    GAMMA1,
+    GAMMA2,
+    OMEGA,
+    PUBLICKEYBYTES,
+    SECRETKEYBYTES,
+    POLY_UNIFORM_NBLOCKS,
+    S,
+    POLY_UNIFORM_ETA_NBLOCKS,
+    POLYETA_PACKEDBYTES,
+    SIGNBYTES,
+    POLYW1_PACKEDBYTES,
+    CTILDEBYTES,
+    POLYZ_PACKEDBYTES,
+    POLYVECH_PACKEDBYTES,
+    POLY_UNIFORM_GAMMA1_NBLOCKS,
+>
+{
+    fn jose_alg() -> anyhow::Result<ParameterSet> {
+        Self::parameter_set().ok_or_else(|| anyhow!("parameters do not match an ML-DSA parameter set"))
+    }
+
+    /// Build a JWK for a public key, or for a private key if the key
+    /// generation seed is given. The seed must produce `pk`.
+    pub fn to_jwk(pk: &[u8], seed: Option<&[u8]>, kid: Option<&str>) -> anyhow::Result<AkpJwk> {
+        let alg = Self::jose_alg()?;
+        if pk.len() != PUBLICKEYBYTES {
+            bail!("public key must be {} bytes", PUBLICKEYBYTES);
+        }
+        if let Some(seed) = seed {
+            Self::jwk_secret_key(pk, seed)?;
+        }
+        Ok(AkpJwk { alg, public_key: pk.to_vec(), seed: seed.map(<[u8]>::to_vec), kid: kid.map(str::to_string) })
+    }
+
+    /// Decode a JWK of this parameter set into the packed public key and, for
+    /// private JWKs, the secret key expanded from the seed.
+    pub fn from_jwk(json: &str) -> anyhow::Result<(Vec<u8>, Option<Vec<u8>>)> {
+        let jwk = AkpJwk::from_json(json)?;
+        if jwk.alg != Self::jose_alg()? {
+            bail!("JWK alg {} does not match the parameter set", jwk.alg.name());
+        }
+        if jwk.public_key.len() != PUBLICKEYBYTES {
+            bail!("public key must be {} bytes", PUBLICKEYBYTES);
+        }
+        let sk = match &jwk.seed {
+            Some(seed) => Some(Self::jwk_secret_key(&jwk.public_key, seed)?),
+            None => None,
+        };
+        Ok((jwk.public_key, sk))
+    }
+
+    fn jwk_secret_key(pk: &[u8], seed: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if seed.len() != SEEDBYTES {
+            bail!("seed must be {} bytes", SEEDBYTES);
+        }
+        let (sk, expected) = Self::key_gen(Some(seed.to_vec()))?;
+        if expected != pk {
+            bail!("public key does not match the seed");
+        }
+        Ok(sk)
+    }
+
+    fn jws_protected(kid: Option<&str>) -> anyhow::Result<String> {
+        let mut header = json!({ "alg": Self::jose_alg()?.name() });
+        if let Some(kid) = kid {
+            header["kid"] = kid.into();
+        }
+        Ok(URL_SAFE_NO_PAD.encode(header.to_string()))
+    }
+
+    fn jws_signature(protected: &str, payload: &str, sk: &[u8]) -> anyhow::Result<String> {
+        if sk.len() != SECRETKEYBYTES {
+            bail!("JWS signing key must be {} bytes", SECRETKEYBYTES);
+        }
+        let input = format!("{}.{}", protected, payload);
+        let sig = Self::sign(input.into_bytes(), sk.to_vec(), None, true)?;
+        Ok(URL_SAFE_NO_PAD.encode(sig))
+    }
+
+    fn jws_verify(protected: &str, payload: &str, signature: &str, pk: &[u8]) -> anyhow::Result<Vec<u8>> {
+        check_header(&b64_decode(protected)?, Self::jose_alg()?)?;
+        let input = format!("{}.{}", protected, payload);
+        Self::verify(input.into_bytes(), b64_decode(signature)?, pk.to_vec(), None)
+            .map_err(|e| anyhow!("invalid JWS signature: {:?}", e))?;
+        b64_decode(payload)
+    }
+
+    /// Sign `payload` as a JWS in compact serialization.
+    pub fn sign_jws_compact(payload: &[u8], sk: &[u8], kid: Option<&str>) -> anyhow::Result<String> {
+        let protected = Self::jws_protected(kid)?;
+        let payload = URL_SAFE_NO_PAD.encode(payload);
+        let signature = Self::jws_signature(&protected, &payload, sk)?;
+        Ok(format!("{}.{}.{}", protected, payload, signature))
+    }
+
+    /// Verify a compact JWS and return its payload.
+    pub fn verify_jws_compact(jws: &str, pk: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let parts: Vec<&str> = jws.split('.').collect();
+        match parts.as_slice() {
+            [protected, payload, signature] => Self::jws_verify(protected, payload, signature, pk),
+            _ => bail!("compact JWS must have three parts"),
+        }
+    }
+
+    /// Sign `payload` as a JWS in flattened JSON serialization, with `kid`
+    /// in the protected header.
+    pub fn sign_jws_json(payload: &[u8], sk: &[u8], kid: Option<&str>) -> anyhow::Result<String> {
+        let protected = Self::jws_protected(kid)?;
+        let payload = URL_SAFE_NO_PAD.encode(payload);
+        let signature = Self::jws_signature(&protected, &payload, sk)?;
+        Ok(json!({ "payload": payload, "protected": protected, "signature": signature }).to_string())
+    }
+
+    /// Verify a JWS in flattened or general JSON serialization and return its
+    /// payload. For the general form one signature of this parameter set must
+    /// verify with `pk`.
+    pub fn verify_jws_json(jws: &str, pk: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let jws: Value = serde_json::from_str(jws)?;
+        let payload = jws.get("payload").and_then(Value::as_str).ok_or_else(|| anyhow!("JWS has no payload"))?;
+        let signatures = match jws.get("signatures") {
+            Some(Value::Array(signatures)) => signatures.iter().collect(),
+            Some(_) => bail!("JWS signatures must be an array"),
+            None => vec![&jws],
+        };
+
+        let mut last_err = anyhow!("JWS has no signatures");
+        for entry in signatures {
+            let protected = entry.get("protected").and_then(Value::as_str);
+            let signature = entry.get("signature").and_then(Value::as_str);
+            let (protected, signature) = match (protected, signature) {
+                (Some(p), Some(s)) => (p, s),
+                _ => bail!("JWS signature needs protected and signature members"),
+            };
+            match Self::jws_verify(protected, payload, signature, pk) {
+                Ok(payload) => return Ok(payload),
+                Err(e) => last_err = e,
+            }
+        }
+        Err(last_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dilithium2, Dilithium3, Dilithium5};
+
+    #[test]
+    fn jwk_roundtrip() {
+        let seed = vec![7u8; 32];
+        let (sk, pk) = Dilithium2::key_gen(Some(seed.clone())).unwrap();
+        let jwk = Dilithium2::to_jwk(&pk, Some(&seed), Some("key-1")).unwrap();
+        let json = jwk.to_json();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["kty"], "AKP");
+        assert_eq!(value["alg"], "ML-DSA-44");
+        assert_eq!(value["priv"], URL_SAFE_NO_PAD.encode(&seed));
+        assert_eq!(AkpJwk::from_json(&json).unwrap(), jwk);
+        assert_eq!(Dilithium2::from_jwk(&json).unwrap(), (pk.clone(), Some(sk)));
+
+        let public = jwk.to_public().to_json();
+        assert!(!public.contains("priv"));
+        assert_eq!(Dilithium2::from_jwk(&public).unwrap(), (pk.clone(), None));
+        assert!(Dilithium3::from_jwk(&public).is_err());
+
+        // Seed that does not belong to the public key
+        assert!(Dilithium2::to_jwk(&pk, Some(&[8u8; 32]), None).is_err());
+        let bad = json!({ "kty": "AKP", "alg": "ML-DSA-44", "pub": value["pub"], "priv": URL_SAFE_NO_PAD.encode([8u8; 32]) });
+        assert!(Dilithium2::from_jwk(&bad.to_string()).is_err());
+    }
+
+    #[test]
+    fn jws_compact() {
+        let (sk, pk) = Dilithium3::key_gen(None).unwrap();
+        let jws = Dilithium3::sign_jws_compact(b"{\"sub\":\"alice\"}", &sk, Some("k")).unwrap();
+        let header: Value = serde_json::from_slice(&b64_decode(jws.split('.').next().unwrap()).unwrap()).unwrap();
+        assert_eq!(header, json!({ "alg": "ML-DSA-65", "kid": "k" }));
+        assert_eq!(Dilithium3::verify_jws_compact(&jws, &pk).unwrap(), b"{\"sub\":\"alice\"}");
+
+        // Changed payload
+        let parts: Vec<&str> = jws.split('.').collect();
+        let forged = format!("{}.{}.{}", parts[0], URL_SAFE_NO_PAD.encode(b"{\"sub\":\"eve\"}"), parts[2]);
+        assert!(Dilithium3::verify_jws_compact(&forged, &pk).is_err());
+
+        // alg must match the verifying parameter set
+        let none = format!("{}.{}.{}", URL_SAFE_NO_PAD.encode(b"{\"alg\":\"ML-DSA-44\"}"), parts[1], parts[2]);
+        assert!(Dilithium3::verify_jws_compact(&none, &pk).is_err());
+
+        // A signing key of the wrong length is an error, not a panic
+        assert!(Dilithium3::sign_jws_compact(b"payload", &sk[..200], None).is_err());
+        assert!(Dilithium3::sign_jws_json(b"payload", &sk[..200], None).is_err());
+    }
+
+    #[test]
+    fn jws_json() {
+        let (sk, pk) = Dilithium5::key_gen(None).unwrap();
+        let (sk2, pk2) = Dilithium2::key_gen(None).unwrap();
+        let flattened = Dilithium5::sign_jws_json(b"payload", &sk, None).unwrap();
+        assert_eq!(Dilithium5::verify_jws_json(&flattened, &pk).unwrap(), b"payload");
+
+        // General serialization with signatures from two parameter sets
+        let other: Value = serde_json::from_str(&Dilithium2::sign_jws_json(b"payload", &sk2, None).unwrap()).unwrap();
+        let flattened: Value = serde_json::from_str(&flattened).unwrap();
+        let general = json!({
+            "payload": flattened["payload"],
+            "signatures": [
+                { "protected": other["protected"], "signature": other["signature"] },
+                { "protected": flattened["protected"], "signature": flattened["signature"] },
+            ],
+        })
+        .to_string();
+        assert_eq!(Dilithium5::verify_jws_json(&general, &pk).unwrap(), b"payload");
+        assert_eq!(Dilithium2::verify_jws_json(&general, &pk2).unwrap(), b"payload");
+        assert!(Dilithium3::verify_jws_json(&general, &pk).is_err());
+    }
+}
@@ -0,0 +1,297 @@
+//! CRYSTALS-Dilithium as submitted to round 3 of the NIST PQC process
+//! (specification v3.1), for verifying keys and signatures made before the
+//! move to FIPS 204.
+//!
+//! The lattice arithmetic and packing are shared with ML-DSA. Round 3
+//! differs only in its hashing: the key generation seed is not bound to
+//! (K, L), tr = H(pk) is 32 bytes, mu = H(tr || m) has no domain separator or
+//! context, rhoprime = H(key || mu) or 64 random bytes, and the challenge
+//! seed is always 32 bytes.
+
+use std::marker::PhantomData;
+
+use anyhow::bail;
+
+use crate::api::SignError;
+use crate::fips202::*;
+use crate::params::{Params, CRHBYTES, SEEDBYTES};
+use crate::randombytes::randombytes;
+use crate::sign::{crypto_sign_keypair_expanded, crypto_sign_signature_rhoprime, crypto_sign_verify_mu};
+use crate::symmetric::{Shake, StreamBackend};
+
+/// Length of tr in round 3.
+const TRBYTES_LEGACY: usize = SEEDBYTES;
+
+pub fn crypto_sign_keypair_legacy<const K: usize, const L: usize, const PUBLICKEYBYTES: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend, const POLY_UNIFORM_ETA_NBLOCKS: usize, const ETA: usize, const POLYETA_PACKEDBYTES: usize>(
+    pk: &mut [u8],
+    sk: &mut [u8],
+    seed: Option<&[u8]>,
+) {
+    let mut init_seed = [0u8; SEEDBYTES];
+    match seed {
+        Some(x) => init_seed.copy_from_slice(x),
+        None => randombytes(&mut init_seed, SEEDBYTES),
+    };
+
+    // Get randomness for rho, rhoprime and key
+    let mut seedbuf = [0u8; 2 * SEEDBYTES + CRHBYTES];
+    shake256(&mut seedbuf, 2 * SEEDBYTES + CRHBYTES, &init_seed, SEEDBYTES);
+    crypto_sign_keypair_expanded::<K, L, PUBLICKEYBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_ETA_NBLOCKS, ETA, POLYETA_PACKEDBYTES>(pk, sk, &seedbuf, &mut [0u8; TRBYTES_LEGACY]);
+}
+
+pub fn crypto_sign_signature_legacy<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const POLYETA_PACKEDBYTES: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend, const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const TAU: usize, const POLYZ_PACKEDBYTES: usize, const ETA: usize>
+(sig: &mut [u8], m: &[u8], sk: &[u8], using_randomized_signing: bool) -> Result<(), SignError> {
+    // `key` and `mu` are concatenated
+    let mut keymu = [0u8; SEEDBYTES + CRHBYTES];
+    let mut rhoprime = [0u8; CRHBYTES];
+    let mut state = KeccakState::default();
+
+    // Compute CRH(tr, msg)
+    keymu[..SEEDBYTES].copy_from_slice(&sk[SEEDBYTES..2 * SEEDBYTES]);
+    shake256_absorb(&mut state, &sk[2 * SEEDBYTES..2 * SEEDBYTES + TRBYTES_LEGACY], TRBYTES_LEGACY);
+    shake256_absorb(&mut state, m, m.len());
+    shake256_finalize(&mut state);
+    shake256_squeeze(&mut keymu[SEEDBYTES..], CRHBYTES, &mut state);
+
+    if using_randomized_signing {
+        randombytes(&mut rhoprime, CRHBYTES);
+    } else {
+        shake256(&mut rhoprime, CRHBYTES, &keymu, SEEDBYTES + CRHBYTES);
+    }
+
+    crypto_sign_signature_rhoprime::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYETA_PACKEDBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_GAMMA1_NBLOCKS, TAU, POLYZ_PACKEDBYTES, ETA>(sig, &keymu[SEEDBYTES..], &rhoprime, sk, TRBYTES_LEGACY)
+}
+
+pub fn crypto_sign_verify_legacy<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const SIGNBYTES: usize, const PUBLICKEYBYTES: usize, const POLYZ_PACKEDBYTES: usize, const TAU: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend>(
+    sig: &[u8],
+    m: &[u8],
+    pk: &[u8],
+) -> Result<(), SignError> {
+    let mut mu = [0u8; CRHBYTES];
+    let mut state = KeccakState::default();
+
+    if pk.len() != PUBLICKEYBYTES {
+        return Err(SignError::Input);
+    }
+
+    // Compute CRH(CRH(rho, t1), msg)
+    shake256(&mut mu, TRBYTES_LEGACY, pk, PUBLICKEYBYTES);
+    shake256_absorb(&mut state, &mu, TRBYTES_LEGACY);
+    shake256_absorb(&mut state, m, m.len());
+    shake256_finalize(&mut state);
+    shake256_squeeze(&mut mu, CRHBYTES, &mut state);
+
+    crypto_sign_verify_mu::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, SIGNBYTES, PUBLICKEYBYTES, POLYZ_PACKEDBYTES, TAU, POLY_UNIFORM_NBLOCKS, S>(sig, &mu, pk)
+}
+
+/// Round-3 Dilithium, with the same parameters as `Dilithium`. Keys and
+/// signatures are not interchangeable with ML-DSA ones. Inconsistent
+/// parameters are rejected at compile time:
+///
+/// ```compile_fail
+/// use dilithium_lib::{DilithiumLegacy, Shake};
+///
+/// // Dilithium2 with OMEGA = 300
+/// type Broken = DilithiumLegacy<4, 4, 2, 39, 78, 0x20000, 95232, 300, 1312, 2528, 5, Shake, 1, 96, 2640, 192, 576, 5>;
+/// let _ = Broken::key_gen(None);
+/// ```
+pub struct DilithiumLegacy<
+    const K: usize,
+    const L: usize,
+    const ETA: usize,
+    const TAU: usize,
+    const BETA: usize,
+    const GAMMA1: usize,
+    const GAMMA2: usize,
+    const OMEGA: usize,
+    const PUBLICKEYBYTES: usize,
+    const SECRETKEYBYTES: usize,
+    const POLY_UNIFORM_NBLOCKS: usize,
+    S: StreamBackend,
+    const POLY_UNIFORM_ETA_NBLOCKS: usize,
+    const POLYETA_PACKEDBYTES: usize,
+    const SIGNBYTES: usize,
+    const POLYW1_PACKEDBYTES: usize,
+    const POLYZ_PACKEDBYTES: usize,
+    const POLY_UNIFORM_GAMMA1_NBLOCKS: usize,
+>(PhantomData<S>);
+
+impl<
+    const K: usize,
+    const L: usize,
+    const ETA: usize,
+    const TAU: usize,
+    const BETA: usize,
+    const GAMMA1: usize,
+    const GAMMA2: usize,
+    const OMEGA: usize,
+    const PUBLICKEYBYTES: usize,
+    const SECRETKEYBYTES: usize,
+    const POLY_UNIFORM_NBLOCKS: usize,
+    S: StreamBackend,
+    const POLY_UNIFORM_ETA_NBLOCKS: usize,
+    const POLYETA_PACKEDBYTES: usize,
+    const SIGNBYTES: usize,
+    const POLYW1_PACKEDBYTES: usize,
+    const POLYZ_PACKEDBYTES: usize,
+    const POLY_UNIFORM_GAMMA1_NBLOCKS: usize,
+>
+DilithiumLegacy<
+    K,
+    L,
+    ETA,
+    TAU,
+    BETA,
+    GAMMA1,
+    GAMMA2,
+    OMEGA,
+    PUBLICKEYBYTES,
+    SECRETKEYBYTES,
+    POLY_UNIFORM_NBLOCKS,
+    S,
+    POLY_UNIFORM_ETA_NBLOCKS,
+    POLYETA_PACKEDBYTES,
+    SIGNBYTES,
+    POLYW1_PACKEDBYTES,
+    POLYZ_PACKEDBYTES,
+    POLY_UNIFORM_GAMMA1_NBLOCKS,
+>
+{
+    /// Compile-time consistency checks of the parameters, as for `Dilithium`
+    /// but with the round-3 lengths of tr and the challenge seed.
+    #[inline(always)]
+    fn check_params() {
+        const {
+            Params {
+                k: K,
+                l: L,
+                eta: ETA,
+                tau: TAU,
+                beta: BETA,
+                gamma1: GAMMA1,
+                gamma2: GAMMA2,
+                omega: OMEGA,
+                trbytes: TRBYTES_LEGACY,
+                ctildebytes: SEEDBYTES,
+                publickeybytes: PUBLICKEYBYTES,
+                secretkeybytes: SECRETKEYBYTES,
+                signbytes: SIGNBYTES,
+                polyeta_packedbytes: POLYETA_PACKEDBYTES,
+                polyz_packedbytes: POLYZ_PACKEDBYTES,
+                polyw1_packedbytes: POLYW1_PACKEDBYTES,
+                polyvech_packedbytes: OMEGA + K,
+                poly_uniform_nblocks: POLY_UNIFORM_NBLOCKS,
+                poly_uniform_eta_nblocks: POLY_UNIFORM_ETA_NBLOCKS,
+                poly_uniform_gamma1_nblocks: POLY_UNIFORM_GAMMA1_NBLOCKS,
+                stream256_blockbytes: S::STREAM256_BLOCKBYTES,
+            }
+            .check()
+        }
+    }
+
+    pub fn key_gen(zeta: Option<Vec<u8>>) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+        Self::check_params();
+        if zeta.as_ref().is_some_and(|z| z.len() != SEEDBYTES) {
+            bail!("seed must be {} bytes", SEEDBYTES);
+        }
+        let mut pk = [0u8; PUBLICKEYBYTES];
+        let mut sk = [0u8; SECRETKEYBYTES];
+        crypto_sign_keypair_legacy::<K, L, PUBLICKEYBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_ETA_NBLOCKS, ETA, POLYETA_PACKEDBYTES>(&mut pk, &mut sk, zeta.as_deref());
+        Ok((sk.to_vec(), pk.to_vec()))
+    }
+
+    pub fn sign(data: Vec<u8>, sk: Vec<u8>, using_randomized_signing: bool) -> anyhow::Result<Vec<u8>> {
+        Self::check_params();
+        if sk.len() != SECRETKEYBYTES {
+            bail!("invalid secret key length");
+        }
+        let mut sig = [0u8; SIGNBYTES];
+        crypto_sign_signature_legacy::<K, L, POLYW1_PACKEDBYTES, SEEDBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYETA_PACKEDBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_GAMMA1_NBLOCKS, TAU, POLYZ_PACKEDBYTES, ETA>(&mut sig, &data, &sk, using_randomized_signing)?;
+        Ok(sig.to_vec())
+    }
+
+    pub fn verify(data: Vec<u8>, sig: Vec<u8>, pk: Vec<u8>) -> Result<(), SignError> {
+        Self::check_params();
+        crypto_sign_verify_legacy::<K, L, POLYW1_PACKEDBYTES, SEEDBYTES, GAMMA1, GAMMA2, BETA, OMEGA, SIGNBYTES, PUBLICKEYBYTES, POLYZ_PACKEDBYTES, TAU, POLY_UNIFORM_NBLOCKS, S>(&sig, &data, &pk)
+    }
+}
+
+pub type Dilithium2Legacy = DilithiumLegacy::<
+    4, 4, 2, 39, 78, 0x20000, 95232, 80, 1312, 2528, 5, Shake, 1, 96, 2420, 192, 576, 5
+>;
+
+pub type Dilithium3Legacy = DilithiumLegacy::<
+    6, 5, 4, 49, 196, 0x80000, 261888, 55, 1952, 4000, 5, Shake, 2, 128, 3293, 128, 640, 5
+>;
+
+pub type Dilithium5Legacy = DilithiumLegacy::<
+    8, 7, 2, 60, 120, 0x80000, 261888, 75, 2592, 4864, 5, Shake, 1, 96, 4595, 128, 640, 5
+>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::Dilithium2;
+    use crate::randombytes::{with_random_source, KatDrbg, RandomSource};
+
+    #[test]
+    fn round_trip() {
+        let data = b"signed before FIPS 204".to_vec();
+        let (sk, pk) = Dilithium3Legacy::key_gen(None).unwrap();
+        assert_eq!((sk.len(), pk.len()), (4000, 1952));
+        let sig = Dilithium3Legacy::sign(data.clone(), sk.clone(), true).unwrap();
+        assert_eq!(sig.len(), 3293);
+        assert!(Dilithium3Legacy::verify(data.clone(), sig, pk.clone()).is_ok());
+
+        let (sk, pk) = Dilithium5Legacy::key_gen(None).unwrap();
+        let sig = Dilithium5Legacy::sign(data.clone(), sk, false).unwrap();
+        assert!(Dilithium5Legacy::verify(data.clone(), sig.clone(), pk.clone()).is_ok());
+        assert!(Dilithium5Legacy::verify(b"forged".to_vec(), sig, pk).is_err());
+    }
+
+    #[test]
+    fn differs_from_ml_dsa() {
+        let seed = vec![5u8; 32];
+        let data = b"message".to_vec();
+        let (sk, pk) = Dilithium2Legacy::key_gen(Some(seed.clone())).unwrap();
+        let (sk_fips, pk_fips) = Dilithium2::key_gen(Some(seed)).unwrap();
+        assert_ne!(pk, pk_fips);
+
+        // tr = H(pk) truncated to 32 bytes follows rho and key
+        let mut tr = [0u8; 32];
+        shake256(&mut tr, 32, &pk, pk.len());
+        assert_eq!(sk[64..96], tr);
+
+        let sig = Dilithium2Legacy::sign(data.clone(), sk, false).unwrap();
+        assert!(Dilithium2Legacy::verify(data.clone(), sig.clone(), pk).is_ok());
+        assert!(Dilithium2::verify(data.clone(), sig, pk_fips.clone(), None).is_err());
+        let sig_fips = Dilithium2::sign(data.clone(), sk_fips, None, false).unwrap();
+        assert!(Dilithium2Legacy::verify(data, sig_fips, pk_fips).is_err());
+    }
+
+    #[test]
+    fn round3_kat() {
+        // count = 0 of PQCsignKAT_2528.rsp from the round-3 reference code:
+        // the DRBG seeded with 0..48 gives the test case seed and message,
+        // and key generation draws from a DRBG on that seed. The full pk, sk
+        // and sm are compared against the reference file in tests/kat.rs.
+        let entropy: [u8; 48] = core::array::from_fn(|i| i as u8);
+        let mut drbg = KatDrbg::new(&entropy);
+        let mut seed = [0u8; 48];
+        drbg.fill_bytes(&mut seed);
+        let mut msg = vec![0u8; 33];
+        drbg.fill_bytes(&mut msg);
+
+        let (sk, pk) = with_random_source(KatDrbg::new(&seed), || Dilithium2Legacy::key_gen(None)).unwrap();
+        // rho followed by the first packed coefficients of t1
+        let expected = "1C0EE1111B08003F28E65E8B3BDEB037CF8F221DFCDAF5950EDB38D506D85BEF\
+                        6177E3DE0D4F1EF5847735947B56D08E841DB2444FA2B729ADEB1417CA7ADF42";
+        let hex: String = pk[..64].iter().map(|b| format!("{:02X}", b)).collect();
+        assert_eq!(hex, expected);
+        assert_eq!(sk[..32], pk[..32]);
+
+        let sig = Dilithium2Legacy::sign(msg.clone(), sk.clone(), false).unwrap();
+        assert_eq!(sig, Dilithium2Legacy::sign(msg.clone(), sk, false).unwrap());
+        assert!(Dilithium2Legacy::verify(msg, sig, pk).is_ok());
+    }
+}
@@ -1,19 +1,83 @@
 mod algorithm;
 mod aes256ctr;
+#[cfg(target_arch = "x86_64")]
+mod aesni;
+mod asn1;
+#[cfg(target_arch = "x86_64")]
+mod avx2;
+#[cfg(feature = "cose")]
+mod cbor;
+#[cfg(feature = "x509")]
+mod chain;
+#[cfg(feature = "cms")]
+mod cms;
+#[cfg(feature = "composite")]
+mod composite;
+#[cfg(feature = "cose")]
+mod cose;
+#[cfg(feature = "x509")]
+mod csr;
+mod ct;
+mod fault;
 mod fips202;
+mod fips202x4;
+mod kat;
+mod legacy;
+mod lowmem;
+mod masked;
+#[cfg(target_arch = "aarch64")]
+mod neon;
 mod ntt;
 mod params;
 mod reduce;
 mod rounding;
 mod randombytes;
+mod rejection;
+mod scoped;
 mod polyvec;
 mod poly;
 mod symmetric;
 mod sign;
 mod packing;
 mod api;
+mod pkcs8;
+mod prehash;
+mod spki;
+#[cfg(feature = "x509")]
+mod x509;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "jose")]
+mod jose;
 
 pub use algorithm::{
-    Dilithium2, Dilithium3, Dilithium5,
+    Dilithium, Dilithium2, Dilithium2Aes, Dilithium3, Dilithium3Aes, Dilithium5, Dilithium5Aes, ParameterSet,
+};
+pub use api::{PublicKey, SecretKey, SignError, Signature};
+#[cfg(feature = "x509")]
+pub use chain::{ChainError, ChainErrorKind, ChainValidator};
+#[cfg(feature = "cms")]
+pub use cms::{DigestAlgorithm, SignedData, SignedDataBuilder, SignerIdentifier, SignerInfo};
+#[cfg(feature = "composite")]
+pub use composite::CompositeAlgorithm;
+#[cfg(feature = "cose")]
+pub use cose::{CoseKey, CoseSign1};
+#[cfg(feature = "x509")]
+pub use csr::{CertificationRequest, CsrBuilder};
+pub use fault::with_fault_checks;
+#[cfg(feature = "jose")]
+pub use jose::AkpJwk;
+pub use kat::generate_kat;
+pub use legacy::{Dilithium2Legacy, Dilithium3Legacy, Dilithium5Legacy, DilithiumLegacy};
+pub use masked::MaskedSecretKey;
+pub use pkcs8::{Pkcs8PrivateKey, PrivateKeyFormat};
+pub use prehash::PreHashAlgorithm;
+pub use randombytes::{with_random_source, KatDrbg, RandomSource};
+pub use rejection::{with_max_sign_iterations, with_sign_stats, SignStats};
+pub use symmetric::{Aes, Shake, StreamBackend};
+#[cfg(feature = "x509")]
+pub use x509::{
+    key_usage, BasicConstraints, Certificate, CertificateBuilder, Extension, Name, NameAttribute, SignatureVerifier,
+    TbsCertificate, Validity,
 };
 
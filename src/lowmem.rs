@@ -0,0 +1,265 @@
+//! Low-memory signing and verification.
+//!
+//! Instead of expanding the full matrix A, every entry a_{i,j} is sampled on
+//! the fly while computing the matrix-vector product, and the secret vectors
+//! s1, s2 and t0 stay in their bit-packed form inside the secret key. Only a
+//! handful of polynomials live on the stack at any time, independent of K and
+//! L, at the cost of resampling A and y several times per iteration.
+//!
+//! Signatures are bit-identical to the ones produced by `crypto_sign_signature`.
+
+use crate::{
+    fips202::*, packing::*, params::*, poly::*, randombytes::*,
+};
+use crate::api::SignError;
+use crate::rejection::{Rejection, RejectionLoop};
+use crate::symmetric::StreamBackend;
+
+/// Compute row i of w = A*y, where A and y are sampled on the fly.
+/// Output coefficients are standard representatives.
+fn matrix_row_times_y<const L: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend, const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const GAMMA1: usize>(
+    w: &mut Poly,
+    i: usize,
+    rho: &[u8],
+    rhoprime: &[u8],
+    nonce: u16,
+) {
+    let (mut a, mut y, mut t) = (Poly::default(), Poly::default(), Poly::default());
+    w.coeffs.fill(0);
+    for j in 0..L {
+        poly_uniform_gamma1::<S, POLY_UNIFORM_GAMMA1_NBLOCKS, GAMMA1>(&mut y, rhoprime, (L as u16) * nonce + j as u16);
+        poly_ntt(&mut y);
+        poly_uniform::<S, POLY_UNIFORM_NBLOCKS>(&mut a, rho, ((i << 8) + j) as u16);
+        poly_pointwise_montgomery(&mut t, &a, &y);
+        poly_add(w, &t);
+    }
+    poly_reduce(w);
+    poly_invntt_tomont(w);
+    poly_caddq(w);
+}
+
+/// Compute c*s for a bit-packed secret polynomial s with coefficients in
+/// [-ETA, ETA]. `cp` is the challenge in NTT domain.
+fn challenge_times_eta<const ETA: usize>(r: &mut Poly, cp: &Poly, packed: &[u8]) {
+    let mut t = Poly::default();
+    polyeta_unpack::<ETA>(&mut t, packed);
+    poly_ntt(&mut t);
+    poly_pointwise_montgomery(r, cp, &t);
+    poly_invntt_tomont(r);
+}
+
+pub fn crypto_sign_signature_lowmem<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const POLYETA_PACKEDBYTES: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend, const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const TAU: usize, const POLYZ_PACKEDBYTES: usize, const ETA: usize>
+(sig: &mut [u8], m: &[u8], sk: &[u8], ctx: Option<&[u8]>, using_randomized_signing: bool) -> Result<(), SignError> {
+    // `key`, `rnd` and `mu` are concatenated
+    let mut keymu = [0u8; SEEDBYTES + RNDBYTES + CRHBYTES];
+    let mut rhoprime = [0u8; CRHBYTES];
+    let mut wbuf = [0u8; POLYW1_PACKEDBYTES];
+    let mut rej = RejectionLoop::<L>::new();
+    let (mut w1, mut w0, mut t) = (Poly::default(), Poly::default(), Poly::default());
+    let mut cp = Poly::default();
+    let mut state = KeccakState::default();
+
+    let ctx = match ctx {
+        Some(x) => x,
+        None => &[0u8; 0],
+    };
+
+    if ctx.len() > 255 {
+        return Err(SignError::Input);
+    }
+
+    // Secret key layout: rho | key | tr | s1 | s2 | t0
+    let rho = &sk[..SEEDBYTES];
+    keymu[..SEEDBYTES].copy_from_slice(&sk[SEEDBYTES..2 * SEEDBYTES]);
+    let tr = &sk[2 * SEEDBYTES..2 * SEEDBYTES + TRBYTES];
+    let s1 = &sk[2 * SEEDBYTES + TRBYTES..];
+    let s2 = &s1[L * POLYETA_PACKEDBYTES..];
+    let t0 = &s2[K * POLYETA_PACKEDBYTES..];
+
+    // Compute CRH(tr, 0, ctxlen, ctx, msg)
+    let mu = [0u8, ctx.len() as u8];
+    shake256_absorb(&mut state, tr, TRBYTES);
+    shake256_absorb(&mut state, &mu, 2);
+    shake256_absorb(&mut state, ctx, ctx.len());
+    shake256_absorb(&mut state, m, m.len());
+    shake256_finalize(&mut state);
+    shake256_squeeze(&mut keymu[SEEDBYTES + RNDBYTES..], CRHBYTES, &mut state);
+
+    // rhoprime = CRH(key, rnd, mu), with rnd all zero in deterministic mode
+    if using_randomized_signing {
+        randombytes(&mut keymu[SEEDBYTES..SEEDBYTES + RNDBYTES], RNDBYTES);
+    }
+    shake256(&mut rhoprime, CRHBYTES, &keymu, SEEDBYTES + RNDBYTES + CRHBYTES);
+    let mu = &keymu[SEEDBYTES + RNDBYTES..];
+
+    'rej: loop {
+        let nonce = rej.next()?;
+
+        // Compute w1 row by row and absorb it into the random oracle
+        state.init();
+        shake256_absorb(&mut state, mu, CRHBYTES);
+        for i in 0..K {
+            matrix_row_times_y::<L, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_GAMMA1_NBLOCKS, GAMMA1>(&mut w1, i, rho, &rhoprime, nonce);
+            poly_decompose::<GAMMA2>(&mut w1, &mut w0);
+            polyw1_pack::<GAMMA2>(&mut wbuf, &w1);
+            shake256_absorb(&mut state, &wbuf, POLYW1_PACKEDBYTES);
+        }
+        shake256_finalize(&mut state);
+        shake256_squeeze(sig, CTILDEBYTES, &mut state);
+        poly_challenge::<CTILDEBYTES, TAU>(&mut cp, sig);
+        poly_ntt(&mut cp);
+
+        // Compute z = y + c*s1 one polynomial at a time, reject if it reveals secret
+        for j in 0..L {
+            challenge_times_eta::<ETA>(&mut w0, &cp, &s1[j * POLYETA_PACKEDBYTES..]);
+            poly_uniform_gamma1::<S, POLY_UNIFORM_GAMMA1_NBLOCKS, GAMMA1>(&mut t, &rhoprime, (L as u16) * nonce + j as u16);
+            poly_add(&mut w0, &t);
+            poly_reduce(&mut w0);
+            if poly_chknorm(&w0, (GAMMA1 - BETA) as i32) > 0 {
+                rej.reject(Rejection::ZNorm);
+                continue 'rej;
+            }
+            polyz_pack::<GAMMA1>(&mut sig[CTILDEBYTES + j * POLYZ_PACKEDBYTES..], &w0);
+        }
+
+        // Recompute w row by row, check low bits and compute hints
+        let hints = &mut sig[CTILDEBYTES + L * POLYZ_PACKEDBYTES..];
+        hints[..OMEGA + K].fill(0);
+        let mut n = 0usize;
+        for i in 0..K {
+            matrix_row_times_y::<L, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_GAMMA1_NBLOCKS, GAMMA1>(&mut w1, i, rho, &rhoprime, nonce);
+            poly_decompose::<GAMMA2>(&mut w1, &mut w0);
+
+            /* Check that subtracting cs2 does not change high bits of w and low bits
+             * do not reveal secret information */
+            challenge_times_eta::<ETA>(&mut t, &cp, &s2[i * POLYETA_PACKEDBYTES..]);
+            poly_sub(&mut w0, &t);
+            poly_reduce(&mut w0);
+            if poly_chknorm(&w0, (GAMMA2 - BETA) as i32) > 0 {
+                rej.reject(Rejection::R0Norm);
+                continue 'rej;
+            }
+
+            polyt0_unpack(&mut t, &t0[i * POLYT0_PACKEDBYTES..]);
+            poly_ntt(&mut t);
+            let t0hat = t;
+            poly_pointwise_montgomery(&mut t, &cp, &t0hat);
+            poly_invntt_tomont(&mut t);
+            poly_reduce(&mut t);
+            if poly_chknorm(&t, GAMMA2 as i32) > 0 {
+                rej.reject(Rejection::Ct0Norm);
+                continue 'rej;
+            }
+
+            poly_add(&mut w0, &t);
+            let cnt = poly_make_hint::<GAMMA2>(&mut t, &w0, &w1) as usize;
+            if n + cnt > OMEGA {
+                rej.reject(Rejection::HintCount);
+                continue 'rej;
+            }
+            for j in 0..N {
+                if t.coeffs[j] != 0 {
+                    hints[n] = j as u8;
+                    n += 1;
+                }
+            }
+            hints[OMEGA + i] = n as u8;
+        }
+        return Ok(());
+    }
+}
+
+pub fn crypto_sign_verify_lowmem<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const SIGNBYTES: usize, const PUBLICKEYBYTES: usize, const POLYZ_PACKEDBYTES: usize, const TAU: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend>(
+    sig: &[u8],
+    m: &[u8],
+    pk: &[u8],
+    ctx: Option<&[u8]>,
+) -> Result<(), SignError> {
+    let mut wbuf = [0u8; POLYW1_PACKEDBYTES];
+    let mut mu = [0u8; CRHBYTES];
+    let mut c2 = [0u8; CTILDEBYTES];
+    let (mut w1, mut z, mut a, mut t) = (Poly::default(), Poly::default(), Poly::default(), Poly::default());
+    let mut cp = Poly::default();
+    let mut state = KeccakState::default();
+
+    let ctx = match ctx {
+        Some(x) => x,
+        None => &[0u8; 0],
+    };
+
+    if ctx.len() > 255 {
+        return Err(SignError::Input);
+    }
+
+    if sig.len() != SIGNBYTES || pk.len() != PUBLICKEYBYTES {
+        return Err(SignError::Input);
+    }
+
+    let rho = &pk[..SEEDBYTES];
+    let c = &sig[..CTILDEBYTES];
+    let zbytes = &sig[CTILDEBYTES..];
+    let hints = &sig[CTILDEBYTES + L * POLYZ_PACKEDBYTES..];
+
+    // Validate the hint encoding and the norm of z before doing any real work
+    for i in 0..K {
+        unpack_hint_row::<K, OMEGA>(&mut t, i, hints)?;
+    }
+    for j in 0..L {
+        polyz_unpack::<GAMMA1>(&mut z, &zbytes[j * POLYZ_PACKEDBYTES..]);
+        if poly_chknorm(&z, (GAMMA1 - BETA) as i32) > 0 {
+            return Err(SignError::Input);
+        }
+    }
+
+    // Compute CRH(CRH(rho, t1), msg)
+    shake256(&mut mu, TRBYTES, pk, PUBLICKEYBYTES);
+    shake256_absorb(&mut state, &mu, TRBYTES);
+    let mm = [0u8, ctx.len() as u8];
+    shake256_absorb(&mut state, &mm, 2);
+    shake256_absorb(&mut state, ctx, ctx.len());
+    shake256_absorb(&mut state, m, m.len());
+    shake256_finalize(&mut state);
+    shake256_squeeze(&mut mu, CRHBYTES, &mut state);
+
+    poly_challenge::<CTILDEBYTES, TAU>(&mut cp, c);
+    poly_ntt(&mut cp);
+
+    // Reconstruct w1 = UseHint(h, Az - c2^dt1) row by row
+    state.init();
+    shake256_absorb(&mut state, &mu, CRHBYTES);
+    for i in 0..K {
+        w1.coeffs.fill(0);
+        for j in 0..L {
+            polyz_unpack::<GAMMA1>(&mut z, &zbytes[j * POLYZ_PACKEDBYTES..]);
+            poly_ntt(&mut z);
+            poly_uniform::<S, POLY_UNIFORM_NBLOCKS>(&mut a, rho, ((i << 8) + j) as u16);
+            poly_pointwise_montgomery(&mut t, &a, &z);
+            poly_add(&mut w1, &t);
+        }
+
+        polyt1_unpack(&mut a, &pk[SEEDBYTES + i * POLYT1_PACKEDBYTES..]);
+        poly_shiftl(&mut a);
+        poly_ntt(&mut a);
+        poly_pointwise_montgomery(&mut t, &cp, &a);
+
+        poly_sub(&mut w1, &t);
+        poly_reduce(&mut w1);
+        poly_invntt_tomont(&mut w1);
+        poly_caddq(&mut w1);
+
+        unpack_hint_row::<K, OMEGA>(&mut t, i, hints)?;
+        poly_use_hint::<GAMMA2>(&mut w1, &t);
+        polyw1_pack::<GAMMA2>(&mut wbuf, &w1);
+        shake256_absorb(&mut state, &wbuf, POLYW1_PACKEDBYTES);
+    }
+
+    // Call random oracle and verify challenge
+    shake256_finalize(&mut state);
+    shake256_squeeze(&mut c2, CTILDEBYTES, &mut state);
+    // Doesn't require constant time equality check
+    if c != c2 {
+        Err(SignError::Verify)
+    } else {
+        Ok(())
+    }
+}
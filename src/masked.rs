@@ -0,0 +1,695 @@
+//! First-order masked signing.
+//!
+//! A `MaskedSecretKey` keeps the secret key in two shares between
+//! signatures: the seed `key` as Boolean shares and s1, s2, t0 as arithmetic
+//! shares mod Q, and every signature refreshes them. rhoprime is derived from
+//! the shares of `key` with a masked SHAKE256, and y is expanded from the
+//! shares of rhoprime with the same masked SHAKE256 and converted to
+//! arithmetic shares. The masked Keccak-f runs theta, rho, pi and iota on
+//! each share and chi with ISW multiplication.
+//!
+//! The linear steps (NTT, multiplication by the public challenge c,
+//! additions) run on each share separately. The nonlinear steps work on
+//! Boolean shares, obtained by a masked conversion: the norm checks on z, r0
+//! and c*t0, Decompose and MakeHint. These use ISW multiplication and a
+//! Kogge-Stone masked adder. The only values unmasked are those the
+//! signature publishes anyway: w1, the reject decision of each check, the
+//! hints (once the norm checks passed) and the final z.
+//!
+//! The packed secret key is handled in the clear once, when it is split by
+//! `MaskedSecretKey::new`. The masking is at the algorithm level; the
+//! compiler and the CPU may still combine shares, so leakage must be
+//! evaluated on the target device. Only the SHAKE stream backend is
+//! supported, as the AES one would need a masked AES.
+//!
+//! Signatures are bit-identical to the ones produced by `crypto_sign_signature`.
+
+use rand::prelude::*;
+
+use crate::{
+    fips202::*, packing::*, params::*, poly::*, polyvec::*, randombytes::*,
+};
+use crate::api::SignError;
+use crate::rejection::{Rejection, RejectionLoop};
+use crate::symmetric::StreamBackend;
+
+/// Boolean shares x[0] ^ x[1] of a 32-bit word.
+type Bool = [u32; 2];
+
+fn xor(x: Bool, y: Bool) -> Bool {
+    [x[0] ^ y[0], x[1] ^ y[1]]
+}
+
+fn shl(x: Bool, s: u32) -> Bool {
+    [x[0] << s, x[1] << s]
+}
+
+fn refresh(x: Bool, rng: &mut impl RngCore) -> Bool {
+    let r = rng.next_u32();
+    [x[0] ^ r, x[1] ^ r]
+}
+
+/// ISW multiplication: shares of x & y.
+fn sec_and(x: Bool, y: Bool, rng: &mut impl RngCore) -> Bool {
+    let r = rng.next_u32();
+    let z0 = (x[0] & y[0]) ^ r;
+    let z1 = (x[1] & y[1]) ^ ((r ^ (x[0] & y[1])) ^ (x[1] & y[0]));
+    [z0, z1]
+}
+
+/// Shares of x + y mod 2^32 with a Kogge-Stone carry chain.
+fn sec_add(x: Bool, y: Bool, rng: &mut impl RngCore) -> Bool {
+    let mut p = xor(x, y);
+    let mut g = sec_and(x, y, rng);
+    for i in 0..5 {
+        let s = 1 << i;
+        g = xor(g, sec_and(p, refresh(shl(g, s), rng), rng));
+        if i < 4 {
+            p = sec_and(p, refresh(shl(p, s), rng), rng);
+        }
+    }
+    xor(xor(x, y), shl(g, 1))
+}
+
+/// Boolean shares of a public constant.
+fn constant(c: u32, rng: &mut impl RngCore) -> Bool {
+    refresh([c, 0], rng)
+}
+
+/// Shares of s mod Q, for s < 2Q.
+fn reduce_once(s: Bool, rng: &mut impl RngCore) -> Bool {
+    let t = sec_add(s, constant(Q_I32.wrapping_neg() as u32, rng), rng);
+    // Keep s if s - Q is negative
+    let m = [(t[0] >> 31).wrapping_neg(), (t[1] >> 31).wrapping_neg()];
+    xor(t, sec_and(refresh(xor(s, t), rng), m, rng))
+}
+
+/// Boolean shares of a0 + a1 mod Q for arithmetic shares in [0, Q).
+fn a2b(a0: i32, a1: i32, rng: &mut impl RngCore) -> Bool {
+    let x = refresh([a0 as u32, 0], rng);
+    let y = refresh([a1 as u32, 0], rng);
+    reduce_once(sec_add(x, y, rng), rng)
+}
+
+/// Arithmetic shares mod Q of x < Q given in Boolean shares. For uniform r
+/// the unmasked x - r mod Q is uniform as well.
+fn b2a(x: Bool, rng: &mut impl Rng) -> (i32, i32) {
+    let r = rng.random_range(0..Q_I32);
+    let d = reduce_once(sec_add(x, constant((Q_I32 - r) as u32, rng), rng), rng);
+    (unmask(d) as i32, r)
+}
+
+/// Boolean shares of a 64-bit Keccak lane.
+type Lane = [u64; 2];
+
+fn refresh64(x: Lane, rng: &mut impl RngCore) -> Lane {
+    let r = rng.next_u64();
+    [x[0] ^ r, x[1] ^ r]
+}
+
+/// ISW multiplication on lanes.
+fn sec_and64(x: Lane, y: Lane, rng: &mut impl RngCore) -> Lane {
+    let r = rng.next_u64();
+    let z0 = (x[0] & y[0]) ^ r;
+    let z1 = (x[1] & y[1]) ^ ((r ^ (x[0] & y[1])) ^ (x[1] & y[0]));
+    [z0, z1]
+}
+
+/// Rotation offsets of rho, indexed by x + 5*y.
+const KECCAK_RHO: [u32; 25] = [0, 1, 62, 28, 27, 36, 44, 6, 55, 20, 3, 10, 43, 25, 39, 41, 45, 15, 21, 8, 18, 2, 61, 56, 14];
+
+/// Keccak-f[1600] on Boolean shares of the state. theta, rho, pi and iota
+/// are linear and run on each share, chi uses `sec_and64`.
+fn keccakf1600_masked(s: &mut [[u64; 25]; 2], rng: &mut impl RngCore) {
+    for rc in KECCAKF_ROUNDCONSTANTS {
+        let mut b = [[0u64; 25]; 2];
+        for (s, b) in s.iter_mut().zip(b.iter_mut()) {
+            let c: [u64; 5] = core::array::from_fn(|x| s[x] ^ s[x + 5] ^ s[x + 10] ^ s[x + 15] ^ s[x + 20]);
+            for x in 0..5 {
+                let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+                for y in 0..5 {
+                    s[x + 5 * y] ^= d;
+                }
+            }
+            for x in 0..5 {
+                for y in 0..5 {
+                    b[y + 5 * ((2 * x + 3 * y) % 5)] = s[x + 5 * y].rotate_left(KECCAK_RHO[x + 5 * y]);
+                }
+            }
+        }
+        for y in 0..5 {
+            for x in 0..5 {
+                let (i, i1, i2) = (x + 5 * y, (x + 1) % 5 + 5 * y, (x + 2) % 5 + 5 * y);
+                let t = sec_and64([!b[0][i1], b[1][i1]], refresh64([b[0][i2], b[1][i2]], rng), rng);
+                s[0][i] = b[0][i] ^ t[0];
+                s[1][i] = b[1][i] ^ t[1];
+            }
+        }
+        s[0][0] ^= rc;
+    }
+}
+
+/// SHAKE256 of an input shorter than one block given in Boolean shares,
+/// with the output in Boolean shares. Public parts of the input are passed
+/// in the first share and zeros in the second.
+fn shake256_masked(out: [&mut [u8]; 2], input: [&[u8]; 2], rng: &mut impl RngCore) {
+    let inlen = input[0].len();
+    debug_assert!(inlen < SHAKE256_RATE && input[1].len() == inlen);
+    let mut s = [[0u64; 25]; 2];
+    for (s, input) in s.iter_mut().zip(input) {
+        for (i, &b) in input.iter().enumerate() {
+            s[i / 8] ^= (b as u64) << (8 * (i % 8));
+        }
+    }
+    s[0][inlen / 8] ^= 0x1F << (8 * (inlen % 8));
+    s[0][(SHAKE256_RATE - 1) / 8] ^= 1 << 63;
+
+    let [out0, out1] = out;
+    for (c0, c1) in out0.chunks_mut(SHAKE256_RATE).zip(out1.chunks_mut(SHAKE256_RATE)) {
+        keccakf1600_masked(&mut s, rng);
+        for (c, s) in [(c0, &s[0]), (c1, &s[1])] {
+            for (i, c) in c.iter_mut().enumerate() {
+                *c = (s[i / 8] >> (8 * (i % 8))) as u8;
+            }
+        }
+    }
+}
+
+/// Bits pos..pos+len of a little-endian bit string, for len <= 25.
+fn bits_at(buf: &[u8], pos: usize, len: usize) -> u32 {
+    let mut w = [0u8; 4];
+    let src = &buf[pos / 8..];
+    let n = src.len().min(4);
+    w[..n].copy_from_slice(&src[..n]);
+    (u32::from_le_bytes(w) >> (pos % 8)) & ((1 << len) - 1)
+}
+
+/// `polyvecl_uniform_gamma1` with the SHAKE backend on Boolean shares of
+/// rhoprime. The packed coefficients are cut out of the stream on each share
+/// and converted to arithmetic shares of y = GAMMA1 - x.
+fn polyvecl_uniform_gamma1_masked<const L: usize, const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const GAMMA1: usize>(y: &mut [Polyvecl<L>; 2], rhoprime: &[[u8; CRHBYTES]; 2], nonce: u16, rng: &mut impl Rng) {
+    let bits = if GAMMA1 == 1 << 17 { 18 } else { 20 };
+    let mut buf = [vec![0u8; POLY_UNIFORM_GAMMA1_NBLOCKS * SHAKE256_RATE], vec![0u8; POLY_UNIFORM_GAMMA1_NBLOCKS * SHAKE256_RATE]];
+    for i in 0..L {
+        let n = (L as u16 * nonce + i as u16).to_le_bytes();
+        let seed = [[&rhoprime[0][..], &n].concat(), [&rhoprime[1][..], &[0u8; 2]].concat()];
+        let [b0, b1] = &mut buf;
+        shake256_masked([b0, b1], [&seed[0], &seed[1]], rng);
+        for j in 0..N {
+            let x = [bits_at(&buf[0], j * bits, bits), bits_at(&buf[1], j * bits, bits)];
+            let (a, b) = b2a(x, rng);
+            y[0].vec[i].coeffs[j] = GAMMA1 as i32 - a;
+            y[1].vec[i].coeffs[j] = -b;
+        }
+    }
+}
+
+/// Shares of the bit x < c, for x < 2^31 and c <= 2^31.
+fn lt(x: Bool, c: u32, rng: &mut impl RngCore) -> Bool {
+    let d = sec_add(x, constant(c.wrapping_neg(), rng), rng);
+    [d[0] >> 31, d[1] >> 31]
+}
+
+fn not(b: Bool) -> Bool {
+    [b[0] ^ 1, b[1]]
+}
+
+fn unmask(b: Bool) -> u32 {
+    b[0] ^ b[1]
+}
+
+/// Shares of the bit c0 <= x < c1.
+fn in_range(x: Bool, c0: u32, c1: u32, rng: &mut impl RngCore) -> Bool {
+    let lo = not(lt(x, c0, rng));
+    let hi = lt(x, c1, rng);
+    sec_and(lo, hi, rng)
+}
+
+/// Whether some coefficient of the shared polynomials a + b has a centered
+/// representative of absolute value at least `bound`. Only the combined
+/// result is unmasked. The checked values are always far smaller than Q/2,
+/// so the centered representative is the one `poly_chknorm` sees.
+fn chknorm_masked(a: &[Poly], b: &[Poly], bound: i32, rng: &mut impl RngCore) -> bool {
+    let mut ok = constant(1, rng);
+    for (a, b) in a.iter().zip(b) {
+        for (&a, &b) in a.coeffs.iter().zip(&b.coeffs) {
+            let x = a2b(a, b, rng);
+            let bad = in_range(x, bound as u32, (Q_I32 - bound + 1) as u32, rng);
+            ok = sec_and(ok, not(bad), rng);
+        }
+    }
+    unmask(ok) == 0
+}
+
+/// `poly_decompose` on shares: w1 is returned in the clear and the shares
+/// of w are replaced by shares of w0. With m = (Q-1)/(2*GAMMA2) and the
+/// masked bits b_k = [w > (2k+1)*GAMMA2], w1 counts the b_k for k < m,
+/// except that all of them are set when w1 wraps around to 0. The bits
+/// e_k = b_k ^ b_{m-1} for k < m-1 are [k < w1] in both cases, so they can
+/// be unmasked, and w1 is found by binary search over them. Since
+/// w0 = w - w1*2*GAMMA2 mod Q, the shares of w0 follow linearly.
+fn poly_decompose_masked<const GAMMA2: usize>(w1: &mut Poly, w: &mut Poly, v: &Poly, rng: &mut impl RngCore) {
+    let m = (Q - 1) / (2 * GAMMA2);
+    let above = |x, k: usize, rng: &mut _| not(lt(x, ((2 * k + 1) * GAMMA2 + 1) as u32, rng));
+    for i in 0..N {
+        let x = a2b(w.coeffs[i], v.coeffs[i], rng);
+        let wrap = above(x, m - 1, rng);
+        let (mut lo, mut hi) = (0, m - 1);
+        while lo < hi {
+            let k = (lo + hi) / 2;
+            if unmask(xor(above(x, k, rng), wrap)) == 1 {
+                lo = k + 1;
+            } else {
+                hi = k;
+            }
+        }
+        w1.coeffs[i] = lo as i32;
+        w.coeffs[i] -= lo as i32 * 2 * GAMMA2 as i32;
+    }
+}
+
+/// `poly_make_hint` on shares of a0 with public high bits a1. The hint is
+/// GAMMA2 < a0 mod Q < Q - GAMMA2, where the upper end is included if a1 is
+/// not 0.
+fn poly_make_hint_masked<const GAMMA2: usize>(h: &mut Poly, a: &Poly, b: &Poly, a1: &Poly, rng: &mut impl RngCore) -> usize {
+    let mut n = 0;
+    for i in 0..N {
+        let x = a2b(a.coeffs[i], b.coeffs[i], rng);
+        let hi = Q - GAMMA2 + (a1.coeffs[i] != 0) as usize;
+        h.coeffs[i] = unmask(in_range(x, GAMMA2 as u32 + 1, hi as u32, rng)) as i32;
+        n += h.coeffs[i] as usize;
+    }
+    n
+}
+
+/// Split each coefficient into a - r and r for uniform r in [0, Q).
+fn mask_polys(a: &mut [Poly], r: &mut [Poly], rng: &mut impl Rng) {
+    for (a, r) in a.iter_mut().zip(r.iter_mut()) {
+        for (a, r) in a.coeffs.iter_mut().zip(r.coeffs.iter_mut()) {
+            *r = rng.random_range(0..Q_I32);
+            *a -= *r;
+        }
+    }
+}
+
+/// Add uniform r in [0, Q) to one share and subtract it from the other.
+fn refresh_polys(a: &mut [Poly], b: &mut [Poly], rng: &mut impl Rng) {
+    for (a, b) in a.iter_mut().zip(b.iter_mut()) {
+        for (a, b) in a.coeffs.iter_mut().zip(b.coeffs.iter_mut()) {
+            let r = rng.random_range(0..Q_I32);
+            *a += r;
+            *b -= r;
+        }
+        poly_reduce(a);
+        poly_reduce(b);
+    }
+}
+
+/// Generator for the masks, seeded from `randombytes` so that it follows
+/// `with_random_source`.
+fn mask_rng() -> StdRng {
+    let mut seed = [0u8; 32];
+    randombytes(&mut seed, 32);
+    StdRng::from_seed(seed)
+}
+
+/// Reduce to the standard representative in [0, Q).
+fn freeze(a: &mut [Poly]) {
+    for a in a {
+        poly_reduce(a);
+        poly_caddq(a);
+    }
+}
+
+/// Recombine shares into centered representatives.
+fn unmask_polys(r: &mut [Poly], a: &[Poly], b: &[Poly]) {
+    for ((r, a), b) in r.iter_mut().zip(a).zip(b) {
+        *r = *a;
+        poly_add(r, b);
+    }
+    freeze(r);
+    for c in r.iter_mut().flat_map(|r| r.coeffs.iter_mut()) {
+        *c -= (((Q_I32 - 1) / 2 - *c) >> 31) & Q_I32;
+    }
+}
+
+/// A secret key split into two shares for `crypto_sign_signature_masked`.
+/// The shares are refreshed by every signature.
+#[derive(Clone)]
+pub struct MaskedSecretKey<const K: usize, const L: usize> {
+    rho: [u8; SEEDBYTES],
+    tr: [u8; TRBYTES],
+    /// Boolean shares of `key`
+    key: [[u8; SEEDBYTES]; 2],
+    /// Arithmetic shares in NTT domain
+    s1: [Polyvecl<L>; 2],
+    s2: [Polyveck<K>; 2],
+    t0: [Polyveck<K>; 2],
+}
+
+/// Secret key elided
+impl<const K: usize, const L: usize> std::fmt::Debug for MaskedSecretKey<K, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MaskedSecretKey(<elided>)")
+    }
+}
+
+impl<const K: usize, const L: usize> MaskedSecretKey<K, L> {
+    /// Unpack and split a packed secret key of the right length.
+    pub(crate) fn new<const POLYETA_PACKEDBYTES: usize, const ETA: usize>(sk: &[u8]) -> Self {
+        let mut rng = mask_rng();
+        let mut msk = MaskedSecretKey {
+            rho: [0u8; SEEDBYTES],
+            tr: [0u8; TRBYTES],
+            key: [[0u8; SEEDBYTES]; 2],
+            s1: [Polyvecl::default(); 2],
+            s2: [Polyveck::default(); 2],
+            t0: [Polyveck::default(); 2],
+        };
+        unpack_sk::<K, L, POLYETA_PACKEDBYTES, ETA>(
+            &mut msk.rho,
+            &mut msk.tr,
+            &mut msk.key[0],
+            &mut msk.t0[0],
+            &mut msk.s1[0],
+            &mut msk.s2[0],
+            sk,
+        );
+        let [a, b] = &mut msk.key;
+        rng.fill_bytes(b);
+        for (a, b) in a.iter_mut().zip(b.iter()) {
+            *a ^= b;
+        }
+        let [a, b] = &mut msk.s1;
+        mask_polys(&mut a.vec, &mut b.vec, &mut rng);
+        let [a, b] = &mut msk.s2;
+        mask_polys(&mut a.vec, &mut b.vec, &mut rng);
+        let [a, b] = &mut msk.t0;
+        mask_polys(&mut a.vec, &mut b.vec, &mut rng);
+
+        for s1 in msk.s1.iter_mut() {
+            polyvecl_ntt(s1);
+            polyvecl_reduce(s1);
+        }
+        for s in msk.s2.iter_mut().chain(msk.t0.iter_mut()) {
+            polyveck_ntt(s);
+            polyveck_reduce(s);
+        }
+        msk
+    }
+
+    /// Draw fresh shares of the same key.
+    fn refresh(&mut self, rng: &mut impl Rng) {
+        let mut r = [0u8; SEEDBYTES];
+        rng.fill_bytes(&mut r);
+        let [a, b] = &mut self.key;
+        for ((a, b), r) in a.iter_mut().zip(b.iter_mut()).zip(r) {
+            *a ^= r;
+            *b ^= r;
+        }
+        let [a, b] = &mut self.s1;
+        refresh_polys(&mut a.vec, &mut b.vec, rng);
+        let [a, b] = &mut self.s2;
+        refresh_polys(&mut a.vec, &mut b.vec, rng);
+        let [a, b] = &mut self.t0;
+        refresh_polys(&mut a.vec, &mut b.vec, rng);
+    }
+}
+
+/// Same as `crypto_sign_signature` for a key kept in shares, with the
+/// secret-dependent part of signing masked.
+pub fn crypto_sign_signature_masked<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend, const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const TAU: usize, const POLYZ_PACKEDBYTES: usize>
+(sig: &mut [u8], m: &[u8], sk: &mut MaskedSecretKey<K, L>, ctx: Option<&[u8]>, using_randomized_signing: bool) -> Result<(), SignError> {
+    const { assert!(S::STANDARD, "masked signing needs the SHAKE stream backend") };
+    let mut rng = mask_rng();
+    let mut rej = RejectionLoop::<L>::new();
+    let mut mu = [0u8; CRHBYTES];
+    let mut rnd = [0u8; RNDBYTES];
+    let mut rhoprime = [[0u8; CRHBYTES]; 2];
+    let mut mat: [Polyvecl<L>; K] = [Polyvecl::default(); K];
+    let mut y = [Polyvecl::<L>::default(); 2];
+    let mut z = [Polyvecl::<L>::default(); 2];
+    let (mut w, mut ct0) = ([Polyveck::<K>::default(); 2], [Polyveck::<K>::default(); 2]);
+    let (mut w1, mut h) = (Polyveck::<K>::default(), Polyveck::<K>::default());
+    let mut zout = Polyvecl::<L>::default();
+    let mut cp = Poly::default();
+    let mut state = KeccakState::default();
+
+    let ctx = match ctx {
+        Some(x) => x,
+        None => &[0u8; 0],
+    };
+
+    if ctx.len() > 255 {
+        return Err(SignError::Input);
+    }
+
+    sk.refresh(&mut rng);
+
+    // Compute mu = CRH(tr, 0, ctxlen, ctx, msg)
+    let pre = [0u8, ctx.len() as u8];
+    shake256_absorb(&mut state, &sk.tr, TRBYTES);
+    shake256_absorb(&mut state, &pre, 2);
+    shake256_absorb(&mut state, ctx, ctx.len());
+    shake256_absorb(&mut state, m, m.len());
+    shake256_finalize(&mut state);
+    shake256_squeeze(&mut mu, CRHBYTES, &mut state);
+
+    // rhoprime = CRH(key, rnd, mu) on the shares of key, with rnd all zero
+    // in deterministic mode
+    if using_randomized_signing {
+        randombytes(&mut rnd, RNDBYTES);
+    }
+    let input = [[&sk.key[0][..], &rnd, &mu].concat(), [&sk.key[1][..], &[0u8; RNDBYTES + CRHBYTES]].concat()];
+    let [r0, r1] = &mut rhoprime;
+    shake256_masked([r0, r1], [&input[0], &input[1]], &mut rng);
+
+    // Expand matrix
+    polyvec_matrix_expand::<K, L, POLY_UNIFORM_NBLOCKS, S>(&mut mat, &sk.rho);
+
+    loop {
+        // Sample shares of intermediate vector y
+        let nonce = rej.next()?;
+        polyvecl_uniform_gamma1_masked::<L, POLY_UNIFORM_GAMMA1_NBLOCKS, GAMMA1>(&mut y, &rhoprime, nonce, &mut rng);
+
+        // Matrix-vector multiplication on each share
+        for (w, y) in w.iter_mut().zip(&y) {
+            let mut yhat = *y;
+            polyvecl_ntt(&mut yhat);
+            polyvecl_reduce(&mut yhat);
+            polyvec_matrix_pointwise_montgomery(w, &mat, &yhat);
+            polyveck_reduce(w);
+            polyveck_invntt_tomont(w);
+            freeze(&mut w.vec);
+        }
+
+        // Decompose w and call the random oracle
+        let [a, b] = &mut w;
+        for i in 0..K {
+            poly_decompose_masked::<GAMMA2>(&mut w1.vec[i], &mut a.vec[i], &b.vec[i], &mut rng);
+        }
+        polyveck_pack_w1::<K, POLYW1_PACKEDBYTES, GAMMA2>(sig, &w1);
+
+        state.init();
+        shake256_absorb(&mut state, &mu, CRHBYTES);
+        shake256_absorb(&mut state, sig, K * POLYW1_PACKEDBYTES);
+        shake256_finalize(&mut state);
+        shake256_squeeze(sig, CTILDEBYTES, &mut state);
+        poly_challenge::<CTILDEBYTES, TAU>(&mut cp, sig);
+        poly_ntt(&mut cp);
+
+        // Compute z, reject if it reveals secret
+        for ((z, s1), y) in z.iter_mut().zip(&sk.s1).zip(&y) {
+            polyvecl_pointwise_poly_montgomery(z, &cp, s1);
+            polyvecl_invntt_tomont(z);
+            polyvecl_add(z, y);
+            freeze(&mut z.vec);
+        }
+        if chknorm_masked(&z[0].vec, &z[1].vec, (GAMMA1 - BETA) as i32, &mut rng) {
+            rej.reject(Rejection::ZNorm);
+            continue;
+        }
+
+        /* Check that subtracting cs2 does not change high bits of w and low bits
+         * do not reveal secret information */
+        for (w, s2) in w.iter_mut().zip(&sk.s2) {
+            polyveck_pointwise_poly_montgomery(&mut h, &cp, s2);
+            polyveck_invntt_tomont(&mut h);
+            polyveck_sub(w, &h);
+            freeze(&mut w.vec);
+        }
+        if chknorm_masked(&w[0].vec, &w[1].vec, (GAMMA2 - BETA) as i32, &mut rng) {
+            rej.reject(Rejection::R0Norm);
+            continue;
+        }
+
+        // Compute hints for w1
+        for (ct0, t0) in ct0.iter_mut().zip(&sk.t0) {
+            polyveck_pointwise_poly_montgomery(ct0, &cp, t0);
+            polyveck_invntt_tomont(ct0);
+            freeze(&mut ct0.vec);
+        }
+        if chknorm_masked(&ct0[0].vec, &ct0[1].vec, GAMMA2 as i32, &mut rng) {
+            rej.reject(Rejection::Ct0Norm);
+            continue;
+        }
+
+        let mut n = 0;
+        for (w, ct0) in w.iter_mut().zip(&ct0) {
+            polyveck_add(w, ct0);
+            freeze(&mut w.vec);
+        }
+        for i in 0..K {
+            n += poly_make_hint_masked::<GAMMA2>(&mut h.vec[i], &w[0].vec[i], &w[1].vec[i], &w1.vec[i], &mut rng);
+        }
+        if n > OMEGA {
+            rej.reject(Rejection::HintCount);
+            continue;
+        }
+
+        // Write signature
+        unmask_polys(&mut zout.vec, &z[0].vec, &z[1].vec);
+        pack_sig::<K, L, CTILDEBYTES, POLYZ_PACKEDBYTES, OMEGA, GAMMA1>(sig, None, &zout, &h);
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rounding::{decompose, make_hint};
+
+    fn share(x: i32, rng: &mut impl Rng) -> (i32, i32) {
+        let r = rng.random_range(0..Q_I32);
+        ((x - r).rem_euclid(Q_I32), r)
+    }
+
+    #[test]
+    fn gadgets() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..1000 {
+            let (x, y) = (rng.next_u32(), rng.next_u32());
+            let xs = refresh([x, 0], &mut rng);
+            let ys = refresh([y, 0], &mut rng);
+            assert_eq!(unmask(sec_and(xs, ys, &mut rng)), x & y);
+            assert_eq!(unmask(sec_add(xs, ys, &mut rng)), x.wrapping_add(y));
+
+            let a = rng.random_range(0..Q_I32);
+            let (a0, a1) = share(a, &mut rng);
+            let b = a2b(a0, a1, &mut rng);
+            assert_eq!(unmask(b), a as u32);
+            let c = rng.random_range(0..Q as u32);
+            assert_eq!(unmask(lt(b, c, &mut rng)), (a < c as i32) as u32);
+        }
+    }
+
+    fn share_bytes(x: &[u8], rng: &mut impl Rng) -> [Vec<u8>; 2] {
+        let r: Vec<u8> = (0..x.len()).map(|_| rng.random()).collect();
+        [x.iter().zip(&r).map(|(x, r)| x ^ r).collect(), r]
+    }
+
+    #[test]
+    fn keccak_matches_unmasked() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut plain = [0u64; 25];
+        plain.iter_mut().for_each(|x| *x = rng.next_u64());
+        let mask: [u64; 25] = core::array::from_fn(|_| rng.next_u64());
+        let mut s = [core::array::from_fn(|i| plain[i] ^ mask[i]), mask];
+        keccakf1600_statepermute(&mut plain);
+        keccakf1600_masked(&mut s, &mut rng);
+        assert_eq!(core::array::from_fn::<u64, 25, _>(|i| s[0][i] ^ s[1][i]), plain);
+
+        let input: Vec<u8> = (0..100).collect();
+        let mut expected = [0u8; 300];
+        shake256(&mut expected, 300, &input, input.len());
+        let [i0, i1] = share_bytes(&input, &mut rng);
+        let (mut o0, mut o1) = ([0u8; 300], [0u8; 300]);
+        shake256_masked([&mut o0, &mut o1], [&i0, &i1], &mut rng);
+        assert_eq!(core::array::from_fn::<u8, 300, _>(|i| o0[i] ^ o1[i]), expected);
+    }
+
+    #[test]
+    fn y_matches_unmasked() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for x in [0, 1, Q as u32 - 1, 1 << 20] {
+            let (a, b) = b2a(refresh([x, 0], &mut rng), &mut rng);
+            assert_eq!((a + b) % Q_I32, x as i32);
+        }
+
+        let mut seed = [0u8; CRHBYTES];
+        rng.fill_bytes(&mut seed);
+        let [s0, s1] = share_bytes(&seed, &mut rng);
+        let rhoprime = [s0.try_into().unwrap(), s1.try_into().unwrap()];
+        let (mut y, mut shares, mut sum) = (Polyvecl::<4>::default(), [Polyvecl::<4>::default(); 2], Polyvecl::<4>::default());
+        polyvecl_uniform_gamma1::<crate::symmetric::Shake, 4, 5, { 1 << 17 }>(&mut y, &seed, 3);
+        polyvecl_uniform_gamma1_masked::<4, 5, { 1 << 17 }>(&mut shares, &rhoprime, 3, &mut rng);
+        unmask_polys(&mut sum.vec, &shares[0].vec, &shares[1].vec);
+        assert!(y.vec.iter().zip(&sum.vec).all(|(a, b)| a.coeffs == b.coeffs));
+    }
+
+    fn edge_values<const GAMMA2: usize>() -> Vec<i32> {
+        let g = GAMMA2 as i32;
+        let mut v = vec![0, 1, g - 1, g, g + 1, 3 * g, 3 * g + 1, Q_I32 - 1 - g, Q_I32 - g, Q_I32 - g - 1, Q_I32 - g + 1, Q_I32 - 1];
+        let mut rng = StdRng::seed_from_u64(2);
+        v.extend((0..N - v.len()).map(|_| rng.random_range(0..Q_I32)));
+        v
+    }
+
+    fn decompose_matches<const GAMMA2: usize>() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let (mut a, mut b, mut w1) = (Poly::default(), Poly::default(), Poly::default());
+        let values = edge_values::<GAMMA2>();
+        for (i, &x) in values.iter().enumerate() {
+            (a.coeffs[i], b.coeffs[i]) = share(x, &mut rng);
+        }
+        poly_decompose_masked::<GAMMA2>(&mut w1, &mut a, &b, &mut rng);
+        for (i, &x) in values.iter().enumerate() {
+            let mut a0 = 0;
+            assert_eq!(w1.coeffs[i], decompose::<GAMMA2>(&mut a0, x), "{}", x);
+            assert_eq!((a.coeffs[i] + b.coeffs[i] - a0).rem_euclid(Q_I32), 0, "{}", x);
+        }
+    }
+
+    fn make_hint_matches<const GAMMA2: usize>() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let (mut a, mut b, mut a1, mut h) = (Poly::default(), Poly::default(), Poly::default(), Poly::default());
+        let values = edge_values::<GAMMA2>();
+        for (i, &x) in values.iter().enumerate() {
+            (a.coeffs[i], b.coeffs[i]) = share(x, &mut rng);
+            a1.coeffs[i] = (i % 3) as i32;
+        }
+        let n = poly_make_hint_masked::<GAMMA2>(&mut h, &a, &b, &a1, &mut rng);
+        let mut expected = 0;
+        for (i, &x) in values.iter().enumerate() {
+            let a0 = if x > (Q_I32 - 1) / 2 { x - Q_I32 } else { x };
+            let hint = make_hint::<GAMMA2>(a0, a1.coeffs[i]) as i32;
+            assert_eq!(h.coeffs[i], hint, "{}", x);
+            expected += hint as usize;
+        }
+        assert_eq!(n, expected);
+    }
+
+    #[test]
+    fn rounding_matches_scalar() {
+        decompose_matches::<{ (Q - 1) / 32 }>();
+        decompose_matches::<{ (Q - 1) / 88 }>();
+        make_hint_matches::<{ (Q - 1) / 32 }>();
+        make_hint_matches::<{ (Q - 1) / 88 }>();
+    }
+
+    #[test]
+    fn chknorm_matches_scalar() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let bound = 1000;
+        for x in [0, 999, 1000, -999, -1000, 4000, -4000] {
+            let (mut a, mut b) = (Poly::default(), Poly::default());
+            for i in 0..N {
+                (a.coeffs[i], b.coeffs[i]) = share(rng.random_range(-999..1000), &mut rng);
+            }
+            (a.coeffs[17], b.coeffs[17]) = share(x, &mut rng);
+            let mut p = Poly::default();
+            p.coeffs[17] = x;
+            assert_eq!(chknorm_masked(&[a], &[b], bound, &mut rng), poly_chknorm(&p, bound) > 0, "{}", x);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+//! NEON versions of the NTT and the pointwise multiplication for aarch64.
+//!
+//! Like avx2.rs, every function returns exactly what its scalar counterpart
+//! returns. Callers must check `available` first.
+
+use std::arch::aarch64::*;
+
+use crate::{
+  ntt::ZETAS,
+  params::{N, Q_I32},
+  poly::Poly,
+  reduce::QINV,
+};
+
+pub fn available() -> bool {
+  std::arch::is_aarch64_feature_detected!("neon")
+}
+
+#[target_feature(enable = "neon")]
+fn load(a: &[i32]) -> int32x4_t {
+  assert!(a.len() >= 4);
+  // SAFETY: at least 4 coefficients
+  unsafe { vld1q_s32(a.as_ptr()) }
+}
+
+#[target_feature(enable = "neon")]
+fn store(a: &mut [i32], x: int32x4_t) {
+  assert!(a.len() >= 4);
+  // SAFETY: at least 4 coefficients
+  unsafe { vst1q_s32(a.as_mut_ptr(), x) }
+}
+
+/// `montgomery_reduce(a * b)` on each lane.
+#[target_feature(enable = "neon")]
+fn montmul(a: int32x4_t, b: int32x4_t) -> int32x4_t {
+  let q = vdupq_n_s32(Q_I32);
+  let p_lo = vmull_s32(vget_low_s32(a), vget_low_s32(b));
+  let p_hi = vmull_high_s32(a, b);
+  let t = vmulq_s32(vmovn_high_s64(vmovn_s64(p_lo), p_hi), vdupq_n_s32(QINV));
+  let r_lo = vmlsl_s32(p_lo, vget_low_s32(t), vget_low_s32(q));
+  let r_hi = vmlsl_high_s32(p_hi, t, q);
+  vshrn_high_n_s64::<32>(vshrn_n_s64::<32>(r_lo), r_hi)
+}
+
+#[target_feature(enable = "neon")]
+fn zetas(idx: [usize; 4], sign: i32) -> int32x4_t {
+  let z = idx.map(|k| sign * ZETAS[k]);
+  load(&z)
+}
+
+#[target_feature(enable = "neon")]
+fn ntt_layer(a: &mut [i32; N], len: usize) {
+  for (g, start) in (0..N).step_by(2 * len).enumerate() {
+    let zeta = vdupq_n_s32(ZETAS[N / (2 * len) + g]);
+    for j in (start..start + len).step_by(4) {
+      let t = montmul(zeta, load(&a[j + len..]));
+      let x = load(&a[j..]);
+      store(&mut a[j + len..], vsubq_s32(x, t));
+      store(&mut a[j..], vaddq_s32(x, t));
+    }
+  }
+}
+
+#[target_feature(enable = "neon")]
+fn invntt_layer(a: &mut [i32; N], len: usize) {
+  for (g, start) in (0..N).step_by(2 * len).enumerate() {
+    let zeta = vdupq_n_s32(-ZETAS[N / len - 1 - g]);
+    for j in (start..start + len).step_by(4) {
+      let x = load(&a[j..]);
+      let y = load(&a[j + len..]);
+      store(&mut a[j..], vaddq_s32(x, y));
+      store(&mut a[j + len..], montmul(zeta, vsubq_s32(x, y)));
+    }
+  }
+}
+
+// The layers with len 2 and 1 work on 8 coefficients a || b at a time, see
+// avx2.rs. For len 2 the blocks land in lanes [0, 0, 1, 1], for len 1 in
+// [0, 1, 2, 3].
+
+#[target_feature(enable = "neon")]
+fn split2(a: int32x4_t, b: int32x4_t) -> (int32x4_t, int32x4_t) {
+  let (a, b) = (vreinterpretq_s64_s32(a), vreinterpretq_s64_s32(b));
+  (vreinterpretq_s32_s64(vtrn1q_s64(a, b)), vreinterpretq_s32_s64(vtrn2q_s64(a, b)))
+}
+
+#[target_feature(enable = "neon")]
+fn split1(a: int32x4_t, b: int32x4_t) -> (int32x4_t, int32x4_t) {
+  (vuzp1q_s32(a, b), vuzp2q_s32(a, b))
+}
+
+#[target_feature(enable = "neon")]
+fn join1(lo: int32x4_t, hi: int32x4_t) -> (int32x4_t, int32x4_t) {
+  (vzip1q_s32(lo, hi), vzip2q_s32(lo, hi))
+}
+
+const BLOCKS2: [usize; 4] = [0, 0, 1, 1];
+const BLOCKS1: [usize; 4] = [0, 1, 2, 3];
+
+/// Forward NTT, same output as `ntt::ntt`.
+#[target_feature(enable = "neon")]
+pub fn ntt(a: &mut [i32; N]) {
+  let mut len = 128;
+  while len >= 4 {
+    ntt_layer(a, len);
+    len >>= 1;
+  }
+  for m in 0..N / 8 {
+    let c = &mut a[8 * m..8 * m + 8];
+    let (mut x, mut y) = (load(c), load(&c[4..]));
+    for (len, blocks) in [(2, BLOCKS2), (1, BLOCKS1)] {
+      let first = N / (2 * len) + 8 / (2 * len) * m;
+      let zeta = zetas(blocks.map(|g| first + g), 1);
+      let (lo, hi) = if len == 2 { split2(x, y) } else { split1(x, y) };
+      let t = montmul(zeta, hi);
+      let (lo, hi) = (vaddq_s32(lo, t), vsubq_s32(lo, t));
+      (x, y) = if len == 2 { split2(lo, hi) } else { join1(lo, hi) };
+    }
+    store(c, x);
+    store(&mut c[4..], y);
+  }
+}
+
+/// Inverse NTT and multiplication by 2^32, same output as
+/// `ntt::invntt_tomont`.
+#[target_feature(enable = "neon")]
+pub fn invntt_tomont(a: &mut [i32; N]) {
+  for m in 0..N / 8 {
+    let c = &mut a[8 * m..8 * m + 8];
+    let (mut x, mut y) = (load(c), load(&c[4..]));
+    for (len, blocks) in [(1, BLOCKS1), (2, BLOCKS2)] {
+      let first = N / len - 1 - 8 / (2 * len) * m;
+      let zeta = zetas(blocks.map(|g| first - g), -1);
+      let (lo, hi) = if len == 2 { split2(x, y) } else { split1(x, y) };
+      let (lo, hi) = (vaddq_s32(lo, hi), montmul(zeta, vsubq_s32(lo, hi)));
+      (x, y) = if len == 2 { split2(lo, hi) } else { join1(lo, hi) };
+    }
+    store(c, x);
+    store(&mut c[4..], y);
+  }
+  let mut len = 4;
+  while len < N {
+    invntt_layer(a, len);
+    len <<= 1;
+  }
+  let f = vdupq_n_s32(41978); // mont^2/256
+  for c in a.chunks_exact_mut(4) {
+    store(c, montmul(f, load(c)));
+  }
+}
+
+#[target_feature(enable = "neon")]
+pub fn poly_pointwise_montgomery(c: &mut Poly, a: &Poly, b: &Poly) {
+  for i in (0..N).step_by(4) {
+    store(&mut c.coeffs[i..], montmul(load(&a.coeffs[i..]), load(&b.coeffs[i..])));
+  }
+}
+
+#[target_feature(enable = "neon")]
+pub fn pointwise_acc_montgomery(w: &mut Poly, u: &[Poly], v: &[Poly]) {
+  for i in (0..N).step_by(4) {
+    let mut acc = montmul(load(&u[0].coeffs[i..]), load(&v[0].coeffs[i..]));
+    for (u, v) in u.iter().zip(v).skip(1) {
+      acc = vaddq_s32(acc, montmul(load(&u.coeffs[i..]), load(&v.coeffs[i..])));
+    }
+    store(&mut w.coeffs[i..], acc);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use rand::prelude::*;
+
+  use super::*;
+  use crate::{ntt, reduce};
+
+  fn random_poly(rng: &mut impl Rng) -> Poly {
+    let mut p = Poly::default();
+    for c in p.coeffs.iter_mut() {
+      *c = rng.random_range(-Q_I32 + 1..Q_I32);
+    }
+    p
+  }
+
+  #[test]
+  fn matches_scalar() {
+    if !available() {
+      return;
+    }
+    let mut rng = StdRng::seed_from_u64(42);
+    for _ in 0..50 {
+      let a = random_poly(&mut rng);
+      let b = random_poly(&mut rng);
+
+      let (mut x, mut y) = (a, a);
+      ntt::ntt(&mut x.coeffs);
+      unsafe { ntt(&mut y.coeffs) };
+      assert_eq!(x.coeffs, y.coeffs);
+
+      let (mut x, mut y) = (a, a);
+      ntt::invntt_tomont(&mut x.coeffs);
+      unsafe { invntt_tomont(&mut y.coeffs) };
+      assert_eq!(x.coeffs, y.coeffs);
+
+      let mut c = Poly::default();
+      unsafe { poly_pointwise_montgomery(&mut c, &a, &b) };
+      for i in 0..N {
+        assert_eq!(c.coeffs[i], reduce::montgomery_reduce(a.coeffs[i] as i64 * b.coeffs[i] as i64));
+      }
+
+      let u = [a, b, b];
+      let v = [b, a, b];
+      unsafe { pointwise_acc_montgomery(&mut c, &u, &v) };
+      for i in 0..N {
+        let expected: i32 = (0..3)
+          .map(|l| reduce::montgomery_reduce(u[l].coeffs[i] as i64 * v[l].coeffs[i] as i64))
+          .sum();
+        assert_eq!(c.coeffs[i], expected);
+      }
+    }
+  }
+}
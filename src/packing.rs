@@ -35,8 +35,9 @@ pub fn pack_sk<const K: usize, const L: usize, const POLYETA_PACKEDBYTES: usize,
     sk[idx..idx + SEEDBYTES].copy_from_slice(&key[0..SEEDBYTES]);
     idx += SEEDBYTES;
 
-    sk[idx..idx + TRBYTES].copy_from_slice(&tr[0..TRBYTES]);
-    idx += TRBYTES;
+    // tr is TRBYTES long, or SEEDBYTES for round-3 Dilithium
+    sk[idx..idx + tr.len()].copy_from_slice(tr);
+    idx += tr.len();
 
     for i in 0..L {
         polyeta_pack::<ETA>(&mut sk[idx + i * POLYETA_PACKEDBYTES..], &s1.vec[i]);
@@ -71,8 +72,9 @@ pub fn unpack_sk<const K: usize, const L: usize, const POLYETA_PACKEDBYTES: usiz
     key[..SEEDBYTES].copy_from_slice(&sk[idx..idx + SEEDBYTES]);
     idx += SEEDBYTES;
 
-    tr[..TRBYTES].copy_from_slice(&sk[idx..idx + TRBYTES]);
-    idx += TRBYTES;
+    let trbytes = tr.len();
+    tr.copy_from_slice(&sk[idx..idx + trbytes]);
+    idx += trbytes;
 
     for i in 0..L {
         polyeta_unpack::<ETA>(&mut s1.vec[i], &sk[idx + i * POLYETA_PACKEDBYTES..]);
@@ -161,3 +163,34 @@ pub fn unpack_sig<const K: usize, const L: usize, const CTILDEBYTES: usize, cons
 
     Ok(())
 }
+
+/// Decode the hint polynomial of row i from the hint section of a signature.
+/// Performs the same strong unforgeability checks as `unpack_sig`, the check
+/// for trailing zero indices is done together with the last row.
+pub fn unpack_hint_row<const K: usize, const OMEGA: usize>(
+    h: &mut Poly,
+    i: usize,
+    hints: &[u8],
+) -> Result<(), SignError> {
+    let k = if i == 0 { 0 } else { hints[OMEGA + i - 1] as usize };
+    let end = hints[OMEGA + i] as usize;
+    if end < k || end > OMEGA {
+        return Err(SignError::Input);
+    }
+
+    h.coeffs.fill(0);
+    for j in k..end {
+        // Coefficients are ordered for strong unforgeability
+        if j > k && hints[j] <= hints[j - 1] {
+            return Err(SignError::Input);
+        }
+        h.coeffs[hints[j] as usize] = 1;
+    }
+
+    // Extra indices are zero for strong unforgeability
+    if i == K - 1 && hints[end..OMEGA].iter().any(|&x| x > 0) {
+        return Err(SignError::Input);
+    }
+
+    Ok(())
+}
@@ -12,4 +12,80 @@ pub const POLYT0_PACKEDBYTES: usize = 416;
 
 // Concise types to avoid cast cluttering
 pub const Q_I32: i32 = Q as i32;
-pub const N_U32: u32 = N as u32;
\ No newline at end of file
+pub const N_U32: u32 = N as u32;
+/// The parameters of a `Dilithium` or `DilithiumLegacy` instantiation, for
+/// the consistency checks both run at compile time.
+pub(crate) struct Params {
+    pub k: usize,
+    pub l: usize,
+    pub eta: usize,
+    pub tau: usize,
+    pub beta: usize,
+    pub gamma1: usize,
+    pub gamma2: usize,
+    pub omega: usize,
+    pub trbytes: usize,
+    pub ctildebytes: usize,
+    pub publickeybytes: usize,
+    pub secretkeybytes: usize,
+    pub signbytes: usize,
+    pub polyeta_packedbytes: usize,
+    pub polyz_packedbytes: usize,
+    pub polyw1_packedbytes: usize,
+    pub polyvech_packedbytes: usize,
+    pub poly_uniform_nblocks: usize,
+    pub poly_uniform_eta_nblocks: usize,
+    pub poly_uniform_gamma1_nblocks: usize,
+    pub stream256_blockbytes: usize,
+}
+
+impl Params {
+    /// Panic if the parameters are inconsistent; meant to be called in a
+    /// `const` block.
+    pub(crate) const fn check(&self) {
+        assert!(self.k >= 1 && self.l >= 1, "K and L must be at least 1");
+        assert!(self.gamma2 == (Q - 1) / 32 || self.gamma2 == (Q - 1) / 88, "GAMMA2 must be (Q-1)/32 or (Q-1)/88");
+        assert!(self.gamma1 == 1 << 17 || self.gamma1 == 1 << 19, "GAMMA1 must be 2^17 or 2^19");
+        assert!(self.eta == 2 || self.eta == 4, "ETA must be 2 or 4");
+        assert!(self.beta == self.tau * self.eta, "BETA must be TAU*ETA");
+        assert!(self.beta < self.gamma2, "BETA must be smaller than GAMMA2");
+        assert!(self.tau <= N, "TAU must not exceed N");
+        // packing stores hint positions and counts in single bytes
+        assert!(self.omega <= 255, "OMEGA must not exceed 255");
+        assert!(
+            self.ctildebytes == 32 || self.ctildebytes == 48 || self.ctildebytes == 64,
+            "CTILDEBYTES must be 32, 48 or 64"
+        );
+        assert!(self.polyeta_packedbytes == if self.eta == 2 { 96 } else { 128 }, "POLYETA_PACKEDBYTES does not match ETA");
+        assert!(
+            self.polyz_packedbytes == if self.gamma1 == 1 << 17 { 576 } else { 640 },
+            "POLYZ_PACKEDBYTES does not match GAMMA1"
+        );
+        assert!(
+            self.polyw1_packedbytes == if self.gamma2 == (Q - 1) / 88 { 192 } else { 128 },
+            "POLYW1_PACKEDBYTES does not match GAMMA2"
+        );
+        assert!(self.polyvech_packedbytes == self.omega + self.k, "POLYVECH_PACKEDBYTES must be OMEGA + K");
+        assert!(self.publickeybytes == SEEDBYTES + self.k * POLYT1_PACKEDBYTES, "PUBLICKEYBYTES does not match K");
+        assert!(
+            self.secretkeybytes
+                == 2 * SEEDBYTES
+                    + self.trbytes
+                    + (self.k + self.l) * self.polyeta_packedbytes
+                    + self.k * POLYT0_PACKEDBYTES,
+            "SECRETKEYBYTES does not match K, L and ETA"
+        );
+        assert!(
+            self.signbytes == self.ctildebytes + self.l * self.polyz_packedbytes + self.polyvech_packedbytes,
+            "SIGNBYTES does not match L, GAMMA1 and OMEGA"
+        );
+        assert!(
+            self.poly_uniform_gamma1_nblocks * self.stream256_blockbytes >= self.polyz_packedbytes,
+            "POLY_UNIFORM_GAMMA1_NBLOCKS is too small for GAMMA1"
+        );
+        assert!(
+            self.poly_uniform_nblocks > 0 && self.poly_uniform_eta_nblocks > 0,
+            "stream block counts must be positive"
+        );
+    }
+}
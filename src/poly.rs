@@ -1,5 +1,9 @@
+#[cfg(target_arch = "x86_64")]
+use crate::avx2;
+#[cfg(target_arch = "aarch64")]
+use crate::neon;
 use crate::{
-  fips202::*, ntt::*, params::*, reduce::*, rounding::*, symmetric::*
+  ct::{neg_mask, nonzero_mask, select}, fips202::*, ntt::*, params::*, reduce::*, rounding::*, symmetric::*
 };
 
 const D_SHL: i32 = 1i32 << (D - 1);
@@ -18,6 +22,11 @@ impl Default for Poly {
 /// Inplace reduction of all coefficients of polynomial to
 /// representative in [0,2*Q].
 pub fn poly_reduce(a: &mut Poly) {
+  #[cfg(target_arch = "x86_64")]
+  if avx2::available() {
+    // SAFETY: AVX2 support was checked
+    return unsafe { avx2::poly_reduce(a) };
+  }
   for i in 0..N {
     a.coeffs[i] = reduce32(a.coeffs[i]);
   }
@@ -26,6 +35,11 @@ pub fn poly_reduce(a: &mut Poly) {
 /// For all coefficients of in/out polynomial add Q if
 /// coefficient is negative.
 pub fn poly_caddq(a: &mut Poly) {
+  #[cfg(target_arch = "x86_64")]
+  if avx2::available() {
+    // SAFETY: AVX2 support was checked
+    return unsafe { avx2::poly_caddq(a) };
+  }
   for i in 0..N {
     a.coeffs[i] = caddq(a.coeffs[i]);
   }
@@ -58,6 +72,16 @@ pub fn poly_shiftl(a: &mut Poly) {
 /// Inplace forward NTT. Output coefficients can be up to
 /// 16*Q larger than input coefficients.
 pub fn poly_ntt(a: &mut Poly) {
+  #[cfg(target_arch = "x86_64")]
+  if avx2::available() {
+    // SAFETY: AVX2 support was checked
+    return unsafe { avx2::ntt(&mut a.coeffs) };
+  }
+  #[cfg(target_arch = "aarch64")]
+  if neon::available() {
+    // SAFETY: NEON support was checked
+    return unsafe { neon::ntt(&mut a.coeffs) };
+  }
   ntt(&mut a.coeffs);
 }
 
@@ -65,6 +89,16 @@ pub fn poly_ntt(a: &mut Poly) {
 /// Input coefficients need to be less than 2*Q.
 /// Output coefficients are less than 2*Q.
 pub fn poly_invntt_tomont(a: &mut Poly) {
+  #[cfg(target_arch = "x86_64")]
+  if avx2::available() {
+    // SAFETY: AVX2 support was checked
+    return unsafe { avx2::invntt_tomont(&mut a.coeffs) };
+  }
+  #[cfg(target_arch = "aarch64")]
+  if neon::available() {
+    // SAFETY: NEON support was checked
+    return unsafe { neon::invntt_tomont(&mut a.coeffs) };
+  }
   invntt_tomont(&mut a.coeffs);
 }
 
@@ -73,6 +107,16 @@ pub fn poly_invntt_tomont(a: &mut Poly) {
 /// by 2^{-32}. Output coefficients are less than 2*Q if input
 /// coefficient are less than 22*Q.
 pub fn poly_pointwise_montgomery(c: &mut Poly, a: &Poly, b: &Poly) {
+  #[cfg(target_arch = "x86_64")]
+  if avx2::available() {
+    // SAFETY: AVX2 support was checked
+    return unsafe { avx2::poly_pointwise_montgomery(c, a, b) };
+  }
+  #[cfg(target_arch = "aarch64")]
+  if neon::available() {
+    // SAFETY: NEON support was checked
+    return unsafe { neon::poly_pointwise_montgomery(c, a, b) };
+  }
   for i in 0..N {
     c.coeffs[i] = montgomery_reduce((a.coeffs[i] as i64) * b.coeffs[i] as i64);
   }
@@ -94,6 +138,11 @@ pub fn poly_power2round(a1: &mut Poly, a0: &mut Poly) {
 /// set c1 = 0 and -ALPHA/2 <= c0 = c mod Q - Q < 0.
 /// Assumes coefficients to be standard representatives.
 pub fn poly_decompose<const GAMMA2: usize>(a1: &mut Poly, a0: &mut Poly) {
+  #[cfg(target_arch = "x86_64")]
+  if avx2::available() {
+    // SAFETY: AVX2 support was checked
+    return unsafe { avx2::poly_decompose::<GAMMA2>(a1, a0) };
+  }
   for i in 0..N {
     a1.coeffs[i] = decompose::<GAMMA2>(&mut a0.coeffs[i], a1.coeffs[i]);
   }
@@ -103,6 +152,11 @@ pub fn poly_decompose<const GAMMA2: usize>(a1: &mut Poly, a0: &mut Poly) {
 /// whether the low bits of the corresponding coefficient of
 /// the input polynomial overflow into the high bits.
 pub fn poly_make_hint<const GAMMA2: usize>(h: &mut Poly, a0: &Poly, a1: &Poly) -> i32 {
+  #[cfg(target_arch = "x86_64")]
+  if avx2::available() {
+    // SAFETY: AVX2 support was checked
+    return unsafe { avx2::poly_make_hint::<GAMMA2>(h, a0, a1) };
+  }
   let mut s = 0i32;
   for i in 0..N {
     h.coeffs[i] = make_hint::<GAMMA2>(a0.coeffs[i], a1.coeffs[i]) as i32;
@@ -117,6 +171,11 @@ pub fn poly_make_hint<const GAMMA2: usize>(h: &mut Poly, a0: &Poly, a1: &Poly) -
 /// - const poly *a: pointer to input polynomial
 /// - const poly *h: pointer to input hint polynomial
 pub fn poly_use_hint<const GAMMA2: usize>(b: &mut Poly, h: &Poly) {
+  #[cfg(target_arch = "x86_64")]
+  if avx2::available() {
+    // SAFETY: AVX2 support was checked
+    return unsafe { avx2::poly_use_hint::<GAMMA2>(b, h) };
+  }
   for i in 0..N {
     b.coeffs[i] = use_hint::<GAMMA2>(b.coeffs[i], h.coeffs[i] as u8);
   }
@@ -126,24 +185,22 @@ pub fn poly_use_hint<const GAMMA2: usize>(b: &mut Poly, h: &Poly) {
 /// Assumes input coefficients to be standard representatives.
 /// Returns 0 if norm is strictly smaller than B and 1 otherwise.
 pub fn poly_chknorm(a: &Poly, b: i32) -> u8 {
-  // It is ok to leak which coefficient violates the bound since
-  // the probability for each coefficient is independent of secret
-  // data but we must not leak the sign of the centralized representative.
+  // The bound is public, the coefficients are not: scan all of them and
+  // never branch on their sign or on which one violates the bound.
   let mut t;
+  let mut r = 0;
 
   if b > (Q_I32 - 1) / 8 {
     return 1;
   }
   for i in 0..N {
     // Absolute value of centralized representative
-    t = a.coeffs[i] >> 31;
+    t = neg_mask(a.coeffs[i]);
     t = a.coeffs[i] - (t & 2 * a.coeffs[i]);
 
-    if t >= b {
-      return 1;
-    }
+    r |= !neg_mask(t - b);
   }
-  return 0;
+  (r & 1) as u8
 }
 
 /// Sample uniformly random coefficients in [0, Q-1] by
@@ -176,14 +233,14 @@ pub fn rej_uniform(a: &mut [i32], len: u32, buf: &[u8], buflen: usize) -> u32 {
 /// Sample polynomial with uniformly random coefficients
 /// in [0, Q-1] by performing rejection sampling using the
 /// output stream of SHAKE256(seed|nonce) or AES256CTR(seed,nonce).
-pub fn poly_uniform<const POLY_UNIFORM_NBLOCKS: usize, const STREAM128_BLOCKBYTES: usize>(a: &mut Poly, seed: &[u8], nonce: u16) {
-  let mut buflen = POLY_UNIFORM_NBLOCKS * STREAM128_BLOCKBYTES;
-  let buf_size = POLY_UNIFORM_NBLOCKS * STREAM128_BLOCKBYTES + 2;
+pub fn poly_uniform<S: StreamBackend, const POLY_UNIFORM_NBLOCKS: usize>(a: &mut Poly, seed: &[u8], nonce: u16) {
+  let mut buflen = POLY_UNIFORM_NBLOCKS * S::STREAM128_BLOCKBYTES;
+  let buf_size = POLY_UNIFORM_NBLOCKS * S::STREAM128_BLOCKBYTES + 2;
   let mut buf = vec![0u8; buf_size];
-  let mut state = Stream128State::default();
+  let mut state = S::Stream128State::default();
 
-  stream128_init(&mut state, seed, nonce);
-  stream128_squeezeblocks(&mut buf, POLY_UNIFORM_NBLOCKS as u64, &mut state);
+  S::stream128_init(&mut state, seed, nonce);
+  S::stream128_squeezeblocks(&mut buf, POLY_UNIFORM_NBLOCKS as u64, &mut state);
 
   let mut ctr = rej_uniform(&mut a.coeffs, N_U32, &mut buf, buflen);
   let mut off;
@@ -192,8 +249,8 @@ pub fn poly_uniform<const POLY_UNIFORM_NBLOCKS: usize, const STREAM128_BLOCKBYTE
     for i in 0..off {
       buf[i] = buf[buflen - off + i];
     }
-    buflen = STREAM128_BLOCKBYTES + off;
-    stream128_squeezeblocks(&mut buf[off..], 1, &mut state);
+    buflen = S::STREAM128_BLOCKBYTES + off;
+    S::stream128_squeezeblocks(&mut buf[off..], 1, &mut state);
     ctr += rej_uniform(
       &mut a.coeffs[(ctr as usize)..],
       N_U32 - ctr,
@@ -203,36 +260,56 @@ pub fn poly_uniform<const POLY_UNIFORM_NBLOCKS: usize, const STREAM128_BLOCKBYTE
   }
 }
 
+/// Four `poly_uniform` calls with the same seed, sharing one 4-way stream.
+pub fn poly_uniform_4x<S: StreamBackend, const POLY_UNIFORM_NBLOCKS: usize>(a: [&mut Poly; 4], seed: &[u8], nonces: [u16; 4]) {
+  let mut buflen = POLY_UNIFORM_NBLOCKS * S::STREAM128_BLOCKBYTES;
+  let mut buf = [(); 4].map(|_| vec![0u8; buflen + 2]);
+  let mut state = S::Stream128x4State::default();
+
+  S::stream128x4_init(&mut state, seed, nonces);
+  let [b0, b1, b2, b3] = &mut buf;
+  S::stream128x4_squeezeblocks([b0, b1, b2, b3], POLY_UNIFORM_NBLOCKS as u64, &mut state);
+
+  let mut ctr = [0u32; 4];
+  for k in 0..4 {
+    ctr[k] = rej_uniform(&mut a[k].coeffs, N_U32, &buf[k], buflen);
+  }
+  while ctr.iter().any(|&c| c < N_U32) {
+    let off = buflen % 3;
+    for b in buf.iter_mut() {
+      b.copy_within(buflen - off..buflen, 0);
+    }
+    buflen = S::STREAM128_BLOCKBYTES + off;
+    let [b0, b1, b2, b3] = &mut buf;
+    S::stream128x4_squeezeblocks([&mut b0[off..], &mut b1[off..], &mut b2[off..], &mut b3[off..]], 1, &mut state);
+    for k in 0..4 {
+      if ctr[k] < N_U32 {
+        ctr[k] += rej_uniform(&mut a[k].coeffs[ctr[k] as usize..], N_U32 - ctr[k], &buf[k], buflen);
+      }
+    }
+  }
+}
+
 /// Sample uniformly random coefficients in [-ETA, ETA] by
 /// performing rejection sampling using array of random bytes.
 pub fn rej_eta<const ETA: usize>(a: &mut [i32], len: usize, buf: &[u8], buflen: usize) -> u32 {
-  let (mut ctr, mut pos) = (0usize, 0usize);
-  let (mut t0, mut t1);
-  while ctr < len && pos < buflen {
-    t0 = (buf[pos] & 0x0F) as u32;
-    t1 = (buf[pos] >> 4) as u32;
-    pos += 1;
-
-    if ETA == 2 {
-      if t0 < 15 {
-        t0 = t0 - (205 * t0 >> 10) * 5;
-        a[ctr] = 2 - t0 as i32;
-        ctr += 1;
-      }
-      if t1 < 15 && ctr < len {
-        t1 = t1 - (205 * t1 >> 10) * 5;
-        a[ctr] = 2 - t1 as i32;
-        ctr += 1;
-      }
-    } else if ETA == 4 {
-      if t0 < 9 {
-        a[ctr] = 4 - t0 as i32;
-        ctr += 1;
-      }
-      if t1 < 9 && ctr < len {
-        a[ctr] = 4 - t1 as i32;
-        ctr += 1;
+  // The seed is secret, so every byte of the buffer is consumed and each
+  // candidate is offered to every output position with a select, so that
+  // neither the branches nor the memory accesses depend on which candidates
+  // are accepted. Only the number of sampled coefficients is revealed.
+  let mut ctr = 0i32;
+  for &byte in &buf[..buflen] {
+    for t in [(byte & 0x0F) as i32, (byte >> 4) as i32] {
+      let (bound, v) = if ETA == 2 {
+        (15, 2 - (t - ((205 * t) >> 10) * 5))
+      } else {
+        (9, 4 - t)
+      };
+      let accept = neg_mask(t - bound) & neg_mask(ctr - len as i32);
+      for (j, c) in a[..len].iter_mut().enumerate() {
+        *c = select(accept & !nonzero_mask(j as i32 ^ ctr), v, *c);
       }
+      ctr += accept & 1;
     }
   }
   ctr as u32
@@ -247,12 +324,12 @@ pub fn rej_eta<const ETA: usize>(a: &mut [i32], len: usize, buf: &[u8], buflen:
 //   (227 + STREAM256_BLOCKBYTES - 1) / STREAM256_BLOCKBYTES
 // };
 
-pub fn poly_uniform_eta<const POLY_UNIFORM_ETA_NBLOCKS: usize, const ETA: usize>(a: &mut Poly, seed: &[u8], nonce: u16) {
-  let buflen = POLY_UNIFORM_ETA_NBLOCKS * STREAM256_BLOCKBYTES;
-  let mut buf = vec![0u8; POLY_UNIFORM_ETA_NBLOCKS * STREAM256_BLOCKBYTES];
-  let mut state = Stream256State::default();
-  stream256_init(&mut state, seed, nonce);
-  stream256_squeezeblocks(
+pub fn poly_uniform_eta<S: StreamBackend, const POLY_UNIFORM_ETA_NBLOCKS: usize, const ETA: usize>(a: &mut Poly, seed: &[u8], nonce: u16) {
+  let buflen = POLY_UNIFORM_ETA_NBLOCKS * S::STREAM256_BLOCKBYTES;
+  let mut buf = vec![0u8; POLY_UNIFORM_ETA_NBLOCKS * S::STREAM256_BLOCKBYTES];
+  let mut state = S::Stream256State::default();
+  S::stream256_init(&mut state, seed, nonce);
+  S::stream256_squeezeblocks(
     &mut buf,
     POLY_UNIFORM_ETA_NBLOCKS as u64,
     &mut state,
@@ -261,16 +338,42 @@ pub fn poly_uniform_eta<const POLY_UNIFORM_ETA_NBLOCKS: usize, const ETA: usize>
   let mut ctr = rej_eta::<ETA>(&mut a.coeffs, N, &buf, buflen);
 
   while ctr < N_U32 {
-    stream256_squeezeblocks(&mut buf, 1, &mut state);
+    S::stream256_squeezeblocks(&mut buf, 1, &mut state);
     ctr += rej_eta::<ETA>(
       &mut a.coeffs[ctr as usize..],
       N - ctr as usize,
       &buf,
-      STREAM256_BLOCKBYTES,
+      S::STREAM256_BLOCKBYTES,
     );
   }
 }
 
+/// Four `poly_uniform_eta` calls with the same seed, sharing one 4-way
+/// stream.
+pub fn poly_uniform_eta_4x<S: StreamBackend, const POLY_UNIFORM_ETA_NBLOCKS: usize, const ETA: usize>(a: [&mut Poly; 4], seed: &[u8], nonces: [u16; 4]) {
+  let buflen = POLY_UNIFORM_ETA_NBLOCKS * S::STREAM256_BLOCKBYTES;
+  let mut buf = [(); 4].map(|_| vec![0u8; buflen]);
+  let mut state = S::Stream256x4State::default();
+
+  S::stream256x4_init(&mut state, seed, nonces);
+  let [b0, b1, b2, b3] = &mut buf;
+  S::stream256x4_squeezeblocks([b0, b1, b2, b3], POLY_UNIFORM_ETA_NBLOCKS as u64, &mut state);
+
+  let mut ctr = [0u32; 4];
+  for k in 0..4 {
+    ctr[k] = rej_eta::<ETA>(&mut a[k].coeffs, N, &buf[k], buflen);
+  }
+  while ctr.iter().any(|&c| c < N_U32) {
+    let [b0, b1, b2, b3] = &mut buf;
+    S::stream256x4_squeezeblocks([b0, b1, b2, b3], 1, &mut state);
+    for k in 0..4 {
+      if ctr[k] < N_U32 {
+        ctr[k] += rej_eta::<ETA>(&mut a[k].coeffs[ctr[k] as usize..], N - ctr[k] as usize, &buf[k], S::STREAM256_BLOCKBYTES);
+      }
+    }
+  }
+}
+
 // const POLY_UNIFORM_GAMMA1_NBLOCKS: usize =
 //   (POLYZ_PACKEDBYTES + STREAM256_BLOCKBYTES - 1) / STREAM256_BLOCKBYTES;
 
@@ -278,12 +381,12 @@ pub fn poly_uniform_eta<const POLY_UNIFORM_ETA_NBLOCKS: usize, const ETA: usize>
 /// in [-(GAMMA1 - 1), GAMMA1 - 1] by performing rejection
 /// sampling on output stream of SHAKE256(seed|nonce)
 /// or AES256CTR(seed,nonce).
-pub fn poly_uniform_gamma1<const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const GAMMA1: usize>(a: &mut Poly, seed: &[u8], nonce: u16) {
-  let mut buf = vec![0u8; POLY_UNIFORM_GAMMA1_NBLOCKS * STREAM256_BLOCKBYTES];
-  let mut state = Stream256State::default();
+pub fn poly_uniform_gamma1<S: StreamBackend, const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const GAMMA1: usize>(a: &mut Poly, seed: &[u8], nonce: u16) {
+  let mut buf = vec![0u8; POLY_UNIFORM_GAMMA1_NBLOCKS * S::STREAM256_BLOCKBYTES];
+  let mut state = S::Stream256State::default();
 
-  stream256_init(&mut state, seed, nonce);
-  stream256_squeezeblocks(
+  S::stream256_init(&mut state, seed, nonce);
+  S::stream256_squeezeblocks(
     &mut buf,
     POLY_UNIFORM_GAMMA1_NBLOCKS as u64,
     &mut state,
@@ -291,6 +394,20 @@ pub fn poly_uniform_gamma1<const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const GAMMA
   polyz_unpack::<GAMMA1>(a, &mut buf);
 }
 
+/// Four `poly_uniform_gamma1` calls with the same seed, sharing one 4-way
+/// stream.
+pub fn poly_uniform_gamma1_4x<S: StreamBackend, const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const GAMMA1: usize>(a: [&mut Poly; 4], seed: &[u8], nonces: [u16; 4]) {
+  let mut buf = [(); 4].map(|_| vec![0u8; POLY_UNIFORM_GAMMA1_NBLOCKS * S::STREAM256_BLOCKBYTES]);
+  let mut state = S::Stream256x4State::default();
+
+  S::stream256x4_init(&mut state, seed, nonces);
+  let [b0, b1, b2, b3] = &mut buf;
+  S::stream256x4_squeezeblocks([b0, b1, b2, b3], POLY_UNIFORM_GAMMA1_NBLOCKS as u64, &mut state);
+  for (a, b) in a.into_iter().zip(&buf) {
+    polyz_unpack::<GAMMA1>(a, b);
+  }
+}
+
 /// Implementation of H. Samples polynomial with TAU nonzero
 /// coefficients in {-1,1} using the output stream of
 /// SHAKE256(seed).
@@ -623,3 +740,70 @@ pub fn polyw1_pack<const GAMMA2: usize>(r: &mut [u8], a: &Poly) {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn check_4x<S: StreamBackend>() {
+    let seed: Vec<u8> = (0..CRHBYTES as u8).collect();
+    let nonces = [0u16, 1, 0x0102, 0x0203];
+    let mut a = [Poly::default(); 4];
+    let mut b = Poly::default();
+
+    let [a0, a1, a2, a3] = &mut a;
+    poly_uniform_4x::<S, 5>([a0, a1, a2, a3], &seed, nonces);
+    for (a, nonce) in a.iter().zip(nonces) {
+      poly_uniform::<S, 5>(&mut b, &seed, nonce);
+      assert_eq!(a.coeffs, b.coeffs);
+    }
+
+    let [a0, a1, a2, a3] = &mut a;
+    poly_uniform_eta_4x::<S, 1, 2>([a0, a1, a2, a3], &seed, nonces);
+    for (a, nonce) in a.iter().zip(nonces) {
+      poly_uniform_eta::<S, 1, 2>(&mut b, &seed, nonce);
+      assert_eq!(a.coeffs, b.coeffs);
+    }
+
+    let [a0, a1, a2, a3] = &mut a;
+    poly_uniform_gamma1_4x::<S, 9, { 1 << 17 }>([a0, a1, a2, a3], &seed, nonces);
+    for (a, nonce) in a.iter().zip(nonces) {
+      poly_uniform_gamma1::<S, 9, { 1 << 17 }>(&mut b, &seed, nonce);
+      assert_eq!(a.coeffs, b.coeffs);
+    }
+  }
+
+  /// rej_eta with the branches of the reference code.
+  fn rej_eta_branching<const ETA: usize>(a: &mut [i32], len: usize, buf: &[u8]) -> u32 {
+    let mut ctr = 0;
+    for t in buf.iter().flat_map(|&b| [(b & 0x0F) as i32, (b >> 4) as i32]) {
+      if ctr < len && ETA == 2 && t < 15 {
+        a[ctr] = 2 - (t - (205 * t >> 10) * 5);
+        ctr += 1;
+      } else if ctr < len && ETA == 4 && t < 9 {
+        a[ctr] = 4 - t;
+        ctr += 1;
+      }
+    }
+    ctr as u32
+  }
+
+  #[test]
+  fn rej_eta_matches_branching() {
+    let buf: Vec<u8> = (0..300u32).map(|i| (i * 97 + 13) as u8).collect();
+    for len in [0, 1, 40, N] {
+      let (mut a, mut b) = ([7i32; N], [7i32; N]);
+      assert_eq!(rej_eta::<2>(&mut a, len, &buf, buf.len()), rej_eta_branching::<2>(&mut b, len, &buf));
+      assert_eq!(a, b);
+      let (mut a, mut b) = ([7i32; N], [7i32; N]);
+      assert_eq!(rej_eta::<4>(&mut a, len, &buf, buf.len()), rej_eta_branching::<4>(&mut b, len, &buf));
+      assert_eq!(a, b);
+    }
+  }
+
+  #[test]
+  fn samplers_4x_match_single() {
+    check_4x::<Shake>();
+    check_4x::<Aes>();
+  }
+}
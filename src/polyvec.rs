@@ -1,4 +1,9 @@
+#[cfg(target_arch = "x86_64")]
+use crate::avx2;
+#[cfg(target_arch = "aarch64")]
+use crate::neon;
 use crate::poly::*;
+use crate::symmetric::StreamBackend;
 
 #[derive(Copy, Clone)]
 pub struct Polyveck<const K: usize> {
@@ -30,10 +35,35 @@ impl<const L: usize> Default for Polyvecl<L> {
 /// random coefficients a_{i,j} by performing rejection
 /// sampling on the output stream of SHAKE128(rho|j|i)
 /// or AES256CTR(rho,j|i).
-pub fn polyvec_matrix_expand<const K: usize, const L: usize, const POLY_UNIFORM_NBLOCKS: usize, const STREAM128_BLOCKBYTES: usize>(mat: &mut [Polyvecl<L>], rho: &[u8]) {
-    for i in 0..K {
-        for j in 0..L {
-            poly_uniform::<POLY_UNIFORM_NBLOCKS, STREAM128_BLOCKBYTES>(&mut mat[i].vec[j], rho, ((i << 8) + j) as u16);
+pub fn polyvec_matrix_expand<const K: usize, const L: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend>(mat: &mut [Polyvecl<L>], rho: &[u8]) {
+    let polys = mat[..K].iter_mut().enumerate().flat_map(|(i, row)| {
+        row.vec.iter_mut().enumerate().map(move |(j, a)| (a, ((i << 8) + j) as u16))
+    });
+    in_groups_of_four(
+        polys,
+        |a, nonces| poly_uniform_4x::<S, POLY_UNIFORM_NBLOCKS>(a, rho, nonces),
+        |a, nonce| poly_uniform::<S, POLY_UNIFORM_NBLOCKS>(a, rho, nonce),
+    );
+}
+
+/// Run `four` on the polynomials in groups of four and `one` on the
+/// remaining ones.
+fn in_groups_of_four<'a>(
+    polys: impl IntoIterator<Item = (&'a mut Poly, u16)>,
+    mut four: impl FnMut([&mut Poly; 4], [u16; 4]),
+    mut one: impl FnMut(&mut Poly, u16),
+) {
+    let mut polys = polys.into_iter();
+    loop {
+        let group: Vec<_> = polys.by_ref().take(4).collect();
+        match <[_; 4]>::try_from(group) {
+            Ok([(a0, n0), (a1, n1), (a2, n2), (a3, n3)]) => four([a0, a1, a2, a3], [n0, n1, n2, n3]),
+            Err(rest) => {
+                for (a, nonce) in rest {
+                    one(a, nonce);
+                }
+                return;
+            }
         }
     }
 }
@@ -50,17 +80,20 @@ pub fn polyvec_matrix_pointwise_montgomery<const K: usize, const L: usize>(
 
 //*********** Vectors of polynomials of length L ****************************
 
-pub fn polyvecl_uniform_eta<const L: usize, const POLY_UNIFORM_ETA_NBLOCKS: usize, const ETA: usize>(v: &mut Polyvecl<L>, seed: &[u8], mut nonce: u16) {
-    for i in 0..L {
-        poly_uniform_eta::<POLY_UNIFORM_ETA_NBLOCKS, ETA>(&mut v.vec[i], seed, nonce);
-        nonce += 1;
-    }
+pub fn polyvecl_uniform_eta<S: StreamBackend, const L: usize, const POLY_UNIFORM_ETA_NBLOCKS: usize, const ETA: usize>(v: &mut Polyvecl<L>, seed: &[u8], nonce: u16) {
+    in_groups_of_four(
+        v.vec.iter_mut().zip(nonce..),
+        |a, nonces| poly_uniform_eta_4x::<S, POLY_UNIFORM_ETA_NBLOCKS, ETA>(a, seed, nonces),
+        |a, nonce| poly_uniform_eta::<S, POLY_UNIFORM_ETA_NBLOCKS, ETA>(a, seed, nonce),
+    );
 }
 
-pub fn polyvecl_uniform_gamma1<const L: usize, const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const GAMMA1: usize>(v: &mut Polyvecl<L>, seed: &[u8], nonce: u16) {
-    for i in 0..L {
-        poly_uniform_gamma1::<POLY_UNIFORM_GAMMA1_NBLOCKS, GAMMA1>(&mut v.vec[i], seed, (L as u16) * nonce + i as u16);
-    }
+pub fn polyvecl_uniform_gamma1<S: StreamBackend, const L: usize, const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const GAMMA1: usize>(v: &mut Polyvecl<L>, seed: &[u8], nonce: u16) {
+    in_groups_of_four(
+        v.vec.iter_mut().zip((L as u16) * nonce..),
+        |a, nonces| poly_uniform_gamma1_4x::<S, POLY_UNIFORM_GAMMA1_NBLOCKS, GAMMA1>(a, seed, nonces),
+        |a, nonce| poly_uniform_gamma1::<S, POLY_UNIFORM_GAMMA1_NBLOCKS, GAMMA1>(a, seed, nonce),
+    );
 }
 pub fn polyvecl_reduce<const L: usize>(v: &mut Polyvecl<L>) {
     for i in 0..L {
@@ -110,6 +143,16 @@ pub fn polyvecl_pointwise_acc_montgomery<const L: usize>(
     u: &Polyvecl<L>,
     v: &Polyvecl<L>,
 ) {
+    #[cfg(target_arch = "x86_64")]
+    if avx2::available() {
+        // SAFETY: AVX2 support was checked
+        return unsafe { avx2::pointwise_acc_montgomery(w, &u.vec, &v.vec) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    if neon::available() {
+        // SAFETY: NEON support was checked
+        return unsafe { neon::pointwise_acc_montgomery(w, &u.vec, &v.vec) };
+    }
     let mut t = Poly::default();
     poly_pointwise_montgomery(w, &u.vec[0], &v.vec[0]);
     for i in 1..L {
@@ -123,21 +166,18 @@ pub fn polyvecl_pointwise_acc_montgomery<const L: usize>(
 /// Returns 0 if norm of all polynomials is strictly smaller than B and 1
 /// otherwise.
 pub fn polyvecl_chknorm<const L: usize>(v: &Polyvecl<L>, bound: i32) -> u8 {
-    for i in 0..L {
-        if poly_chknorm(&v.vec[i], bound) > 0 {
-            return 1;
-        }
-    }
-    return 0;
+    // Check every polynomial so the time does not reveal which one failed
+    v.vec.iter().fold(0, |r, p| r | poly_chknorm(p, bound))
 }
 
 //*********** Vectors of polynomials of length K ****************************
 
-pub fn polyveck_uniform_eta<const K: usize, const POLY_UNIFORM_ETA_NBLOCKS: usize, const ETA: usize>(v: &mut Polyveck<K>, seed: &[u8], mut nonce: u16) {
-    for i in 0..K {
-        poly_uniform_eta::<POLY_UNIFORM_ETA_NBLOCKS, ETA>(&mut v.vec[i], seed, nonce);
-        nonce += 1
-    }
+pub fn polyveck_uniform_eta<S: StreamBackend, const K: usize, const POLY_UNIFORM_ETA_NBLOCKS: usize, const ETA: usize>(v: &mut Polyveck<K>, seed: &[u8], nonce: u16) {
+    in_groups_of_four(
+        v.vec.iter_mut().zip(nonce..),
+        |a, nonces| poly_uniform_eta_4x::<S, POLY_UNIFORM_ETA_NBLOCKS, ETA>(a, seed, nonces),
+        |a, nonce| poly_uniform_eta::<S, POLY_UNIFORM_ETA_NBLOCKS, ETA>(a, seed, nonce),
+    );
 }
 
 /// Reduce coefficients of polynomials in vector of length K
@@ -214,12 +254,7 @@ pub fn polyveck_pointwise_poly_montgomery<const K: usize>(
 /// Returns 0 if norm of all polynomials are strictly smaller than B and 1
 /// otherwise.
 pub fn polyveck_chknorm<const K: usize>(v: &Polyveck<K>, bound: i32) -> u8 {
-    for i in 0..K {
-        if poly_chknorm(&v.vec[i], bound) > 0 {
-            return 1;
-        }
-    }
-    return 0;
+    v.vec.iter().fold(0, |r, p| r | poly_chknorm(p, bound))
 }
 
 /// For all coefficients a of polynomials in vector of length K,
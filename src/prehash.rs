@@ -0,0 +1,125 @@
+//! Hash functions approved for HashML-DSA (FIPS 204, section 5.4).
+//!
+//! The pre-hashed variant signs M' = 1 || ctxlen || ctx || OID || PH(M); the
+//! digest PH(M) is computed by the caller.
+
+use crate::asn1::oid;
+
+/// Hash function used for HashML-DSA.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PreHashAlgorithm {
+    Sha2_224,
+    Sha2_256,
+    Sha2_384,
+    Sha2_512,
+    Sha2_512_224,
+    Sha2_512_256,
+    Sha3_224,
+    Sha3_256,
+    Sha3_384,
+    Sha3_512,
+    /// SHAKE128 with 256 bits of output
+    Shake128,
+    /// SHAKE256 with 512 bits of output
+    Shake256,
+}
+
+const ALL: [PreHashAlgorithm; 12] = [
+    PreHashAlgorithm::Sha2_224,
+    PreHashAlgorithm::Sha2_256,
+    PreHashAlgorithm::Sha2_384,
+    PreHashAlgorithm::Sha2_512,
+    PreHashAlgorithm::Sha2_512_224,
+    PreHashAlgorithm::Sha2_512_256,
+    PreHashAlgorithm::Sha3_224,
+    PreHashAlgorithm::Sha3_256,
+    PreHashAlgorithm::Sha3_384,
+    PreHashAlgorithm::Sha3_512,
+    PreHashAlgorithm::Shake128,
+    PreHashAlgorithm::Shake256,
+];
+
+impl PreHashAlgorithm {
+    /// Name as used by NIST ACVP, e.g. `SHA2-256` or `SHAKE-128`.
+    pub fn name(self) -> &'static str {
+        match self {
+            PreHashAlgorithm::Sha2_224 => "SHA2-224",
+            PreHashAlgorithm::Sha2_256 => "SHA2-256",
+            PreHashAlgorithm::Sha2_384 => "SHA2-384",
+            PreHashAlgorithm::Sha2_512 => "SHA2-512",
+            PreHashAlgorithm::Sha2_512_224 => "SHA2-512/224",
+            PreHashAlgorithm::Sha2_512_256 => "SHA2-512/256",
+            PreHashAlgorithm::Sha3_224 => "SHA3-224",
+            PreHashAlgorithm::Sha3_256 => "SHA3-256",
+            PreHashAlgorithm::Sha3_384 => "SHA3-384",
+            PreHashAlgorithm::Sha3_512 => "SHA3-512",
+            PreHashAlgorithm::Shake128 => "SHAKE-128",
+            PreHashAlgorithm::Shake256 => "SHAKE-256",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        ALL.into_iter().find(|h| h.name() == name)
+    }
+
+    /// Length of the digest in bytes.
+    pub fn digest_len(self) -> usize {
+        match self {
+            PreHashAlgorithm::Sha2_224 | PreHashAlgorithm::Sha2_512_224 | PreHashAlgorithm::Sha3_224 => 28,
+            PreHashAlgorithm::Sha2_256 | PreHashAlgorithm::Sha2_512_256 | PreHashAlgorithm::Sha3_256 => 32,
+            PreHashAlgorithm::Shake128 => 32,
+            PreHashAlgorithm::Sha2_384 | PreHashAlgorithm::Sha3_384 => 48,
+            PreHashAlgorithm::Sha2_512 | PreHashAlgorithm::Sha3_512 => 64,
+            PreHashAlgorithm::Shake256 => 64,
+        }
+    }
+
+    /// DER encoded object identifier, without tag and length. All of them
+    /// live under 2.16.840.1.101.3.4.2.
+    pub fn oid(self) -> [u8; 9] {
+        let arc = match self {
+            PreHashAlgorithm::Sha2_256 => 1,
+            PreHashAlgorithm::Sha2_384 => 2,
+            PreHashAlgorithm::Sha2_512 => 3,
+            PreHashAlgorithm::Sha2_224 => 4,
+            PreHashAlgorithm::Sha2_512_224 => 5,
+            PreHashAlgorithm::Sha2_512_256 => 6,
+            PreHashAlgorithm::Sha3_224 => 7,
+            PreHashAlgorithm::Sha3_256 => 8,
+            PreHashAlgorithm::Sha3_384 => 9,
+            PreHashAlgorithm::Sha3_512 => 10,
+            PreHashAlgorithm::Shake128 => 11,
+            PreHashAlgorithm::Shake256 => 12,
+        };
+        [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, arc]
+    }
+
+    pub fn from_oid(value: &[u8]) -> Option<Self> {
+        ALL.into_iter().find(|h| h.oid() == value)
+    }
+}
+
+/// Build the domain separated prefix 1 || ctxlen || ctx || OID that
+/// precedes the digest in M'.
+pub(crate) fn prefix(hash: PreHashAlgorithm, ctx: &[u8]) -> Vec<u8> {
+    [&[1u8, ctx.len() as u8][..], ctx, &oid(&hash.oid())].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_and_oids_round_trip() {
+        for h in ALL {
+            assert_eq!(PreHashAlgorithm::from_name(h.name()), Some(h));
+            assert_eq!(PreHashAlgorithm::from_oid(&h.oid()), Some(h));
+        }
+        // id-sha256 from RFC 5758
+        assert_eq!(
+            oid(&PreHashAlgorithm::Sha2_256.oid()),
+            [0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01]
+        );
+        assert_eq!(prefix(PreHashAlgorithm::Shake256, b"ab")[..4], [1, 2, b'a', b'b']);
+    }
+}
@@ -1,5 +1,136 @@
+use std::cell::Cell;
+
 use rand::prelude::*;
 
+use crate::aes256ctr::{aes256_encrypt_block, aes256_key_expand};
+use crate::scoped::with_scoped;
+
+/// A source of randomness that can replace the system generator, see
+/// `with_random_source`.
+pub trait RandomSource {
+  fn fill_bytes(&mut self, out: &mut [u8]);
+}
+
+thread_local! {
+  static SOURCE: Cell<Option<Box<dyn RandomSource>>> = const { Cell::new(None) };
+}
+
 pub fn randombytes(x: &mut [u8], len: usize) {
-  thread_rng().fill_bytes(&mut x[..len])
+  // The source is moved out while it runs and put back afterwards
+  match SOURCE.take() {
+    Some(mut source) => {
+      source.fill_bytes(&mut x[..len]);
+      SOURCE.set(Some(source));
+    }
+    None => rand::rng().fill_bytes(&mut x[..len]),
+  }
+}
+
+/// Run `f` with all randomness drawn on the current thread, for key
+/// generation and hedged signing, taken from `source`. The previous source
+/// is restored afterwards, also when `f` panics.
+pub fn with_random_source<T>(source: impl RandomSource + 'static, f: impl FnOnce() -> T) -> T {
+  with_scoped(&SOURCE, Some(Box::new(source)), f)
+}
+
+/// The AES-256 CTR_DRBG of the NIST PQC reference code (`rng.c`), without
+/// derivation function or reseeding. It is fully determined by its 48 byte
+/// seed and used to reproduce the `PQCsignKAT_*.rsp` known-answer files.
+/// Not for production use.
+#[derive(Clone)]
+pub struct KatDrbg {
+  key: [u8; 32],
+  v: [u8; 16],
+  reseed_counter: u64,
+}
+
+impl KatDrbg {
+  /// `randombytes_init(entropy_input, NULL, 256)`.
+  pub fn new(entropy_input: &[u8; 48]) -> Self {
+    let mut drbg = KatDrbg { key: [0u8; 32], v: [0u8; 16], reseed_counter: 1 };
+    drbg.update(Some(entropy_input));
+    drbg
+  }
+
+  fn increment_v(&mut self) {
+    for b in self.v.iter_mut().rev() {
+      *b = b.wrapping_add(1);
+      if *b != 0 {
+        break;
+      }
+    }
+  }
+
+  fn update(&mut self, provided_data: Option<&[u8; 48]>) {
+    let mut sk_exp = [0u64; 120];
+    aes256_key_expand(&mut sk_exp, &self.key);
+    let mut temp = [0u8; 48];
+    for chunk in temp.chunks_exact_mut(16) {
+      self.increment_v();
+      let mut block = [0u8; 16];
+      aes256_encrypt_block(&mut block, &self.v, &sk_exp);
+      chunk.copy_from_slice(&block);
+    }
+    if let Some(data) = provided_data {
+      for (t, d) in temp.iter_mut().zip(data) {
+        *t ^= d;
+      }
+    }
+    self.key.copy_from_slice(&temp[..32]);
+    self.v.copy_from_slice(&temp[32..]);
+  }
+}
+
+impl RandomSource for KatDrbg {
+  /// `randombytes(x, xlen)`. The state is updated after every call, so the
+  /// output depends on how requests are split, as in the reference code.
+  fn fill_bytes(&mut self, out: &mut [u8]) {
+    let mut sk_exp = [0u64; 120];
+    aes256_key_expand(&mut sk_exp, &self.key);
+    for chunk in out.chunks_mut(16) {
+      self.increment_v();
+      let mut block = [0u8; 16];
+      aes256_encrypt_block(&mut block, &self.v, &sk_exp);
+      chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+    self.update(None);
+    self.reseed_counter += 1;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn unhex(s: &str) -> Vec<u8> {
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+  }
+
+  #[test]
+  fn kat_drbg_matches_reference() {
+    // The first two draws of every NIST PQC KAT request file: the seed of
+    // count 0, then its message, of which the request file has 33 bytes
+    let mut entropy_input = [0u8; 48];
+    for (i, b) in entropy_input.iter_mut().enumerate() {
+      *b = i as u8;
+    }
+    let mut drbg = KatDrbg::new(&entropy_input);
+    let mut seed = [0u8; 48];
+    drbg.fill_bytes(&mut seed);
+    assert_eq!(seed.to_vec(), unhex("061550234D158C5EC95595FE04EF7A25767F2E24CC2BC479D09D86DC9ABCFDE7056A8C266F9EF97ED08541DBD2E1FFA1"));
+    drbg.fill_bytes(&mut seed);
+    assert_eq!(seed.to_vec(), unhex("D81C4D8D734FCBFBEADE3D3F8A039FAA2A2C9957E835AD55B22E75BF57BB556AC81ADDE6AEEB4A5A875C3BFCADFA958F"));
+  }
+
+  #[test]
+  fn random_source_is_scoped() {
+    let seed = [7u8; 48];
+    let mut a = [0u8; 40];
+    let mut b = [0u8; 40];
+    with_random_source(KatDrbg::new(&seed), || randombytes(&mut a, 40));
+    KatDrbg::new(&seed).fill_bytes(&mut b);
+    assert_eq!(a, b);
+    randombytes(&mut a, 40);
+    assert_ne!(a, b);
+  }
 }
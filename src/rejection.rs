@@ -0,0 +1,142 @@
+//! Bounds and statistics for the rejection sampling loop of signing.
+//!
+//! Each iteration of the loop samples y with a fresh 16-bit nonce, so the
+//! nonce space alone bounds a signature to 65536 / L iterations. A lower
+//! limit can be set with `with_max_sign_iterations`; either way signing
+//! returns `SignError::Exhausted` instead of looping on or reusing a nonce.
+
+use std::cell::Cell;
+
+use crate::api::SignError;
+use crate::scoped::with_scoped;
+
+/// Counters of the signing loop, filled in by `with_sign_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SignStats {
+    /// Iterations started, including the accepted one.
+    pub iterations: u32,
+    /// Rejected because ||z|| >= GAMMA1 - BETA.
+    pub z_norm: u32,
+    /// Rejected because ||r0|| >= GAMMA2 - BETA.
+    pub r0_norm: u32,
+    /// Rejected because ||c*t0|| >= GAMMA2.
+    pub ct0_norm: u32,
+    /// Rejected because there were more than OMEGA hints.
+    pub hint_count: u32,
+}
+
+/// The check that rejected an iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Rejection {
+    ZNorm,
+    R0Norm,
+    Ct0Norm,
+    HintCount,
+}
+
+thread_local! {
+    static MAX_ITERATIONS: Cell<Option<u32>> = const { Cell::new(None) };
+    static STATS: Cell<Option<SignStats>> = const { Cell::new(None) };
+}
+
+/// Run `f` with every signature on the current thread limited to `max`
+/// iterations of the rejection loop. The previous limit is restored
+/// afterwards, also when `f` panics.
+pub fn with_max_sign_iterations<T>(max: u32, f: impl FnOnce() -> T) -> T {
+    with_scoped(&MAX_ITERATIONS, Some(max), f)
+}
+
+/// Run `f` and return its result together with the loop statistics of all
+/// signatures it made on the current thread, including failed ones.
+pub fn with_sign_stats<T>(f: impl FnOnce() -> T) -> (T, SignStats) {
+    with_scoped(&STATS, Some(SignStats::default()), || {
+        let result = f();
+        (result, STATS.get().unwrap_or_default())
+    })
+}
+
+fn record(f: impl FnOnce(&mut SignStats)) {
+    STATS.with(|s| {
+        if let Some(mut stats) = s.get() {
+            f(&mut stats);
+            s.set(Some(stats));
+        }
+    });
+}
+
+/// Nonces and iteration count of one run of the loop for a vector y of
+/// length L.
+pub(crate) struct RejectionLoop<const L: usize> {
+    nonce: u16,
+    iterations: u32,
+    max: u32,
+}
+
+impl<const L: usize> RejectionLoop<L> {
+    pub fn new() -> Self {
+        RejectionLoop { nonce: 0, iterations: 0, max: MAX_ITERATIONS.with(|m| m.get()).unwrap_or(u32::MAX) }
+    }
+
+    /// Start the next iteration and return its nonce. y then uses the
+    /// nonces L * nonce .. L * nonce + L - 1, which must all fit in a u16.
+    pub fn next(&mut self) -> Result<u16, SignError> {
+        if self.iterations >= self.max {
+            return Err(SignError::Exhausted);
+        }
+        let nonce = self.nonce;
+        (nonce as usize + 1)
+            .checked_mul(L)
+            .filter(|&end| end <= 1 << 16)
+            .ok_or(SignError::Exhausted)?;
+        self.nonce = nonce.wrapping_add(1);
+        self.iterations += 1;
+        record(|s| s.iterations += 1);
+        Ok(nonce)
+    }
+
+    pub fn reject(&self, why: Rejection) {
+        record(|s| match why {
+            Rejection::ZNorm => s.z_norm += 1,
+            Rejection::R0Norm => s.r0_norm += 1,
+            Rejection::Ct0Norm => s.ct0_norm += 1,
+            Rejection::HintCount => s.hint_count += 1,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nonce_space_bounds_the_loop() {
+        let mut l = RejectionLoop::<5>::new();
+        for i in 0..65536 / 5 {
+            assert_eq!(l.next(), Ok(i as u16));
+        }
+        assert_eq!(l.next(), Err(SignError::Exhausted));
+
+        let mut l = RejectionLoop::<4>::new();
+        for _ in 0..16384 {
+            l.next().unwrap();
+        }
+        assert_eq!(l.next(), Err(SignError::Exhausted));
+    }
+
+    #[test]
+    fn limit_and_stats_are_scoped() {
+        let ((), stats) = with_sign_stats(|| {
+            with_max_sign_iterations(2, || {
+                let mut l = RejectionLoop::<4>::new();
+                l.next().unwrap();
+                l.reject(Rejection::ZNorm);
+                l.next().unwrap();
+                l.reject(Rejection::HintCount);
+                assert_eq!(l.next(), Err(SignError::Exhausted));
+            });
+        });
+        assert_eq!(stats, SignStats { iterations: 2, z_norm: 1, hint_count: 1, ..Default::default() });
+        assert_eq!(MAX_ITERATIONS.with(|m| m.get()), None);
+        assert_eq!(STATS.with(|s| s.get()), None);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::ct::{neg_mask, nonzero_mask, select};
 use crate::params::*;
 
 /// For finite field element a, compute a0, a1 such that
@@ -33,45 +34,73 @@ pub fn decompose<const GAMMA2: usize>(a0: &mut i32, a: i32) -> i32 {
 }
 
 /// Compute hint bit indicating whether the low bits of the
-/// input element overflow into the high bits.
+/// input element overflow into the high bits. Runs in constant time.
 ///
 /// Returns 1 if overflow.
 pub fn make_hint<const GAMMA2: usize>(a0: i32, a1: i32) -> u8 {
-  if a0 > (GAMMA2 as i32) || a0 < -(GAMMA2 as i32) || (a0 == -(GAMMA2 as i32) && a1 != 0) {
-    return 1;
-  }
-  return 0;
+  let g = GAMMA2 as i32;
+  // a0 > GAMMA2, a0 < -GAMMA2, or a0 == -GAMMA2 with a1 != 0
+  let h = neg_mask(g - a0) | neg_mask(a0 + g) | (!nonzero_mask(a0 + g) & nonzero_mask(a1));
+  (h & 1) as u8
 }
 
-/// Correct high bits according to hint.
+/// Correct high bits according to hint. Runs in constant time.
 ///
 /// Returns corrected high bits.
 pub fn use_hint<const GAMMA2: usize>(a: i32, hint: u8) -> i32 {
   let mut a0 = 0i32;
   let a1 = decompose::<GAMMA2>(&mut a0, a);
-  if hint == 0 {
-    return a1;
-  }
+  // +1 if a0 > 0, -1 otherwise, and 0 without hint
+  let delta = select(neg_mask(-a0), 1, -1) & nonzero_mask(hint as i32);
 
   if GAMMA2 == (Q - 1) / 32 {
-    if a0 > 0 {
-      return (a1 + 1) & 15;
-    } else {
-      return (a1 - 1) & 15;
-    }
+    (a1 + delta) & 15
   } else {
-    if a0 > 0 {
-      if a1 == 43 {
-        return 0;
-      } else {
-        return a1 + 1;
-      };
-    } else {
-      if a1 == 0 {
-        return 43;
-      } else {
-        return a1 - 1;
+    let r = a1 + delta;
+    // Wrap -1 to 43 and 44 to 0
+    r + (neg_mask(r) & 44) - (!neg_mask(r - 44) & 44)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn make_hint_ref<const GAMMA2: usize>(a0: i32, a1: i32) -> u8 {
+    (a0 > GAMMA2 as i32 || a0 < -(GAMMA2 as i32) || (a0 == -(GAMMA2 as i32) && a1 != 0)) as u8
+  }
+
+  fn use_hint_ref<const GAMMA2: usize>(a: i32, hint: u8) -> i32 {
+    let mut a0 = 0i32;
+    let a1 = decompose::<GAMMA2>(&mut a0, a);
+    let m = (Q as i32 - 1) / (2 * GAMMA2 as i32);
+    match (hint, a0 > 0) {
+      (0, _) => a1,
+      (_, true) => (a1 + 1) % m,
+      (_, false) => (a1 - 1 + m) % m,
+    }
+  }
+
+  fn check<const GAMMA2: usize>() {
+    let g = GAMMA2 as i32;
+    for a0 in -g - 2..=-g + 2 {
+      for a1 in [0, 1] {
+        assert_eq!(make_hint::<GAMMA2>(a0, a1), make_hint_ref::<GAMMA2>(a0, a1));
+      }
+    }
+    for a0 in g - 2..=g + 2 {
+      assert_eq!(make_hint::<GAMMA2>(a0, 1), make_hint_ref::<GAMMA2>(a0, 1));
+    }
+    for a in (0..Q as i32).step_by(997).chain([0, 1, g, g + 1, Q as i32 - g - 1, Q as i32 - 1]) {
+      for hint in [0, 1] {
+        assert_eq!(use_hint::<GAMMA2>(a, hint), use_hint_ref::<GAMMA2>(a, hint), "a={a} hint={hint}");
       }
     }
   }
+
+  #[test]
+  fn branchless_hints_match_reference() {
+    check::<{ (Q - 1) / 32 }>();
+    check::<{ (Q - 1) / 88 }>();
+  }
 }
@@ -0,0 +1,42 @@
+//! Thread-local settings that apply for the duration of a closure, used by
+//! `with_random_source`, `with_max_sign_iterations`, `with_sign_stats` and
+//! `with_fault_checks`.
+
+use std::cell::Cell;
+use std::thread::LocalKey;
+
+/// Run `f` with `key` set to `value` on the current thread. The previous
+/// value is restored afterwards, also when `f` panics.
+pub(crate) fn with_scoped<V: 'static, T>(key: &'static LocalKey<Cell<V>>, value: V, f: impl FnOnce() -> T) -> T {
+    struct Restore<V: 'static>(&'static LocalKey<Cell<V>>, Option<V>);
+
+    impl<V: 'static> Drop for Restore<V> {
+        fn drop(&mut self) {
+            if let Some(previous) = self.1.take() {
+                self.0.with(|c| c.set(previous));
+            }
+        }
+    }
+
+    let _restore = Restore(key, Some(key.with(|c| c.replace(value))));
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    thread_local! {
+        static VALUE: Cell<u32> = const { Cell::new(0) };
+    }
+
+    #[test]
+    fn restores_after_panic() {
+        let inner = with_scoped(&VALUE, 1, || with_scoped(&VALUE, 2, || VALUE.get()));
+        assert_eq!(inner, 2);
+        assert_eq!(VALUE.get(), 0);
+        let result = std::panic::catch_unwind(|| with_scoped(&VALUE, 3, || panic!()));
+        assert!(result.is_err());
+        assert_eq!(VALUE.get(), 0);
+    }
+}
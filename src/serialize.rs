@@ -0,0 +1,143 @@
+//! Serde support for public keys, signatures and (with the `serde-secret`
+//! feature) secret keys.
+//!
+//! Human-readable formats such as JSON get a lowercase hex string, binary
+//! formats such as bincode get the raw bytes. Deserializing goes through
+//! `from_bytes`, which checks the length, and for secret keys also that the
+//! coefficients of s1 and s2 are in range.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize, Serializer};
+
+#[cfg(feature = "serde-secret")]
+use crate::api::SecretKey;
+use crate::api::{PublicKey, Signature};
+
+fn serialize_bytes<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&hex::encode(bytes))
+    } else {
+        serializer.serialize_bytes(bytes)
+    }
+}
+
+struct BytesVisitor<const LEN: usize>(PhantomData<[u8; LEN]>);
+
+impl<'de, const LEN: usize> Visitor<'de> for BytesVisitor<LEN> {
+    type Value = [u8; LEN];
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} bytes or a hex string of {} characters", LEN, 2 * LEN)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        let mut out = [0u8; LEN];
+        if v.len() != 2 * LEN {
+            return Err(E::invalid_length(v.len() / 2, &self));
+        }
+        hex::decode_to_slice(v, &mut out).map_err(E::custom)?;
+        Ok(out)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        <[u8; LEN]>::try_from(v).map_err(|_| E::invalid_length(v.len(), &self))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut out = [0u8; LEN];
+        for (i, x) in out.iter_mut().enumerate() {
+            *x = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        if seq.next_element::<u8>()?.is_some() {
+            return Err(de::Error::invalid_length(LEN + 1, &self));
+        }
+        Ok(out)
+    }
+}
+
+fn deserialize_bytes<'de, D: Deserializer<'de>, const LEN: usize>(deserializer: D) -> Result<[u8; LEN], D::Error> {
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_str(BytesVisitor::<LEN>(PhantomData))
+    } else {
+        deserializer.deserialize_bytes(BytesVisitor::<LEN>(PhantomData))
+    }
+}
+
+macro_rules! impl_serde {
+    ($name:ident) => {
+        impl<const LEN: usize> Serialize for $name<LEN> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serialize_bytes(&self.0, serializer)
+            }
+        }
+
+        impl<'de, const LEN: usize> Deserialize<'de> for $name<LEN> {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let mut bytes = deserialize_bytes::<D, LEN>(deserializer)?;
+                let value = Self::from_bytes(&bytes);
+                crate::ct::wipe(&mut bytes);
+                value.map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+impl_serde!(PublicKey);
+impl_serde!(Signature);
+#[cfg(feature = "serde-secret")]
+impl_serde!(SecretKey);
+
+#[cfg(test)]
+mod tests {
+    use crate::api::{PublicKey, Signature};
+    use crate::Dilithium2;
+
+    #[test]
+    fn roundtrip_json_and_bincode() {
+        let (_, pk) = Dilithium2::key_gen(Some(vec![1u8; 32])).unwrap();
+        let pk = PublicKey::<1312>::from_bytes(&pk).unwrap();
+
+        let json = serde_json::to_string(&pk).unwrap();
+        assert_eq!(json, format!("\"{}\"", hex::encode(pk.0)));
+        assert_eq!(serde_json::from_str::<PublicKey<1312>>(&json).unwrap(), pk);
+
+        let bin = bincode::serialize(&pk).unwrap();
+        assert_eq!(bin.len(), 8 + 1312);
+        assert_eq!(bincode::deserialize::<PublicKey<1312>>(&bin).unwrap(), pk);
+    }
+
+    #[test]
+    fn rejects_wrong_parameter_set() {
+        let (_, pk) = Dilithium2::key_gen(Some(vec![1u8; 32])).unwrap();
+        let pk = PublicKey::<1312>::from_bytes(&pk).unwrap();
+
+        // A Dilithium2 public key is not a Dilithium3 public key
+        let json = serde_json::to_string(&pk).unwrap();
+        assert!(serde_json::from_str::<PublicKey<1952>>(&json).is_err());
+        let bin = bincode::serialize(&pk).unwrap();
+        assert!(bincode::deserialize::<PublicKey<1952>>(&bin).is_err());
+
+        assert!(serde_json::from_str::<Signature<2420>>("\"00\"").is_err());
+        assert!(serde_json::from_str::<Signature<2420>>("\"zz\"").is_err());
+    }
+
+    #[cfg(feature = "serde-secret")]
+    #[test]
+    fn secret_key_roundtrip() {
+        use crate::api::SecretKey;
+
+        let (sk, _) = Dilithium2::key_gen(None).unwrap();
+        let sk = SecretKey::<2560>::from_bytes(&sk).unwrap();
+        let json = serde_json::to_string(&sk).unwrap();
+        assert_eq!(serde_json::from_str::<SecretKey<2560>>(&json).unwrap(), sk);
+
+        // Right length, but s1 out of range
+        let mut bad = sk.to_vec();
+        bad[128] |= 0x07;
+        let json = format!("\"{}\"", hex::encode(bad));
+        assert!(serde_json::from_str::<SecretKey<2560>>(&json).is_err());
+    }
+}
@@ -2,8 +2,11 @@ use crate::{
     fips202::*, packing::*, params::*, poly::*, polyvec::*, randombytes::*,
 };
 use crate::api::SignError;
+use crate::fault::{self, Signed};
+use crate::rejection::{Rejection, RejectionLoop};
+use crate::symmetric::StreamBackend;
 
-pub fn crypto_sign_keypair<const K: usize, const L: usize, const PUBLICKEYBYTES: usize, const POLY_UNIFORM_NBLOCKS: usize, const STREAM128_BLOCKBYTES: usize, const POLY_UNIFORM_ETA_NBLOCKS: usize, const ETA: usize, const POLYETA_PACKEDBYTES: usize>(
+pub fn crypto_sign_keypair<const K: usize, const L: usize, const PUBLICKEYBYTES: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend, const POLY_UNIFORM_ETA_NBLOCKS: usize, const ETA: usize, const POLYETA_PACKEDBYTES: usize>(
     pk: &mut [u8],
     sk: &mut [u8],
     seed: Option<&[u8]>,
@@ -20,7 +23,26 @@ pub fn crypto_sign_keypair<const K: usize, const L: usize, const PUBLICKEYBYTES:
     new_init_seed[SEEDBYTES + 0] = K as u8;
     new_init_seed[SEEDBYTES + 1] = L as u8;
 
-    let mut tr = [0u8; TRBYTES];
+    // Get randomness for rho, rhoprime and key
+    shake256(
+        &mut seedbuf,
+        2 * SEEDBYTES + CRHBYTES,
+        &new_init_seed,
+        SEEDBYTES + 2,
+    );
+    crypto_sign_keypair_expanded::<K, L, PUBLICKEYBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_ETA_NBLOCKS, ETA, POLYETA_PACKEDBYTES>(pk, sk, &seedbuf, &mut [0u8; TRBYTES]);
+
+    0
+}
+
+/// Key generation from the expanded seed rho || rhoprime || key. The length
+/// of `tr` selects the size of the public key hash stored in `sk`.
+pub(crate) fn crypto_sign_keypair_expanded<const K: usize, const L: usize, const PUBLICKEYBYTES: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend, const POLY_UNIFORM_ETA_NBLOCKS: usize, const ETA: usize, const POLYETA_PACKEDBYTES: usize>(
+    pk: &mut [u8],
+    sk: &mut [u8],
+    seedbuf: &[u8],
+    tr: &mut [u8],
+) {
     let (mut rho, mut rhoprime, mut key) =
         ([0u8; SEEDBYTES], [0u8; CRHBYTES], [0u8; SEEDBYTES]);
     let mut mat: [Polyvecl<L>; K] = [Polyvecl::<L>::default(); K];
@@ -31,22 +53,15 @@ pub fn crypto_sign_keypair<const K: usize, const L: usize, const PUBLICKEYBYTES:
         Polyveck::<K>::default(),
     );
 
-    // Get randomness for rho, rhoprime and key
-    shake256(
-        &mut seedbuf,
-        2 * SEEDBYTES + CRHBYTES,
-        &new_init_seed,
-        SEEDBYTES + 2,
-    );
     rho.copy_from_slice(&seedbuf[..SEEDBYTES]);
     rhoprime.copy_from_slice(&seedbuf[SEEDBYTES..SEEDBYTES + CRHBYTES]);
     key.copy_from_slice(&seedbuf[SEEDBYTES + CRHBYTES..]);
 
     // Expand matrix
-    polyvec_matrix_expand::<K, L, POLY_UNIFORM_NBLOCKS, STREAM128_BLOCKBYTES>(&mut mat, &rho);
+    polyvec_matrix_expand::<K, L, POLY_UNIFORM_NBLOCKS, S>(&mut mat, &rho);
     // Sample short vectors s1 and s2
-    polyvecl_uniform_eta::<L, POLY_UNIFORM_ETA_NBLOCKS, ETA>(&mut s1, &rhoprime, 0);
-    polyveck_uniform_eta::<K, POLY_UNIFORM_ETA_NBLOCKS, ETA>(&mut s2, &rhoprime, L as u16);
+    polyvecl_uniform_eta::<S, L, POLY_UNIFORM_ETA_NBLOCKS, ETA>(&mut s1, &rhoprime, 0);
+    polyveck_uniform_eta::<S, K, POLY_UNIFORM_ETA_NBLOCKS, ETA>(&mut s2, &rhoprime, L as u16);
 
     // Matrix-vector multiplication
     let mut s1hat = s1;
@@ -64,18 +79,75 @@ pub fn crypto_sign_keypair<const K: usize, const L: usize, const PUBLICKEYBYTES:
     pack_pk(pk, &rho, &t1);
 
     // Compute H(rho, t1) and write secret key
-    shake256(&mut tr, TRBYTES, pk, PUBLICKEYBYTES);
-    pack_sk::<K, L, POLYETA_PACKEDBYTES, ETA>(sk, &rho, &tr, &key, &t0, &s1, &s2);
+    shake256(tr, tr.len(), pk, PUBLICKEYBYTES);
+    pack_sk::<K, L, POLYETA_PACKEDBYTES, ETA>(sk, &rho, tr, &key, &t0, &s1, &s2);
+}
 
-    0
+/// Sign with the external interface of FIPS 204 (ML-DSA.Sign): the message
+/// is prefixed with the domain separator 0 and the context string. The
+/// randomness `rnd` is random for hedged signing and all zero otherwise.
+pub fn crypto_sign_signature<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const POLYETA_PACKEDBYTES: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend, const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const TAU: usize, const POLYZ_PACKEDBYTES: usize, const ETA: usize>
+(sig: &mut [u8], m: &[u8], sk: &[u8], ctx: Option<&[u8]>, using_randomized_signing: bool) -> Result<(), SignError> {
+    let ctx = match ctx {
+        Some(x) => x,
+        None => &[0u8; 0],
+    };
+
+    if ctx.len() > 255 {
+        return Err(SignError::Input);
+    }
+
+    let mut pre = [0u8; 257];
+    pre[1] = ctx.len() as u8;
+    pre[2..2 + ctx.len()].copy_from_slice(ctx);
+
+    let mut rnd = [0u8; RNDBYTES];
+    if using_randomized_signing {
+        randombytes(&mut rnd, RNDBYTES);
+    }
+
+    crypto_sign_signature_internal::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYETA_PACKEDBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_GAMMA1_NBLOCKS, TAU, POLYZ_PACKEDBYTES, ETA>(sig, m, &pre[..2 + ctx.len()], &rnd, sk)
+}
+
+/// ML-DSA.Sign_internal: sign M' = pre || m with the randomness `rnd`.
+pub fn crypto_sign_signature_internal<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const POLYETA_PACKEDBYTES: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend, const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const TAU: usize, const POLYZ_PACKEDBYTES: usize, const ETA: usize>
+(sig: &mut [u8], m: &[u8], pre: &[u8], rnd: &[u8], sk: &[u8]) -> Result<(), SignError> {
+    let mut mu = [0u8; CRHBYTES];
+    let mut state = KeccakState::default(); //shake256_init()
+
+    // Compute mu = CRH(tr, pre, msg)
+    shake256_absorb(&mut state, &sk[2 * SEEDBYTES..2 * SEEDBYTES + TRBYTES], TRBYTES);
+    shake256_absorb(&mut state, pre, pre.len());
+    shake256_absorb(&mut state, m, m.len());
+    shake256_finalize(&mut state);
+    shake256_squeeze(&mut mu, CRHBYTES, &mut state);
+
+    crypto_sign_signature_mu::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYETA_PACKEDBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_GAMMA1_NBLOCKS, TAU, POLYZ_PACKEDBYTES, ETA>(sig, &mu, rnd, sk)
 }
 
-pub fn crypto_sign_signature<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const POLYETA_PACKEDBYTES: usize, const POLY_UNIFORM_NBLOCKS: usize, const STREAM128_BLOCKBYTES: usize, const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const TAU: usize, const POLYZ_PACKEDBYTES: usize, const ETA: usize>
-(sig: &mut [u8], m: &[u8], sk: &[u8], ctx: Option<&[u8]>, using_randomized_signing: bool) {
-    // `key` and `mu` are concatenated
+/// Sign an externally computed message representative mu.
+pub fn crypto_sign_signature_mu<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const POLYETA_PACKEDBYTES: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend, const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const TAU: usize, const POLYZ_PACKEDBYTES: usize, const ETA: usize>
+(sig: &mut [u8], mu: &[u8], rnd: &[u8], sk: &[u8]) -> Result<(), SignError> {
+    // `key`, `rnd` and `mu` are concatenated
     let mut keymu = [0u8; SEEDBYTES + RNDBYTES + CRHBYTES];
+    let mut rhoprime = [0u8; CRHBYTES];
+
+    keymu[..SEEDBYTES].copy_from_slice(&sk[SEEDBYTES..2 * SEEDBYTES]);
+    keymu[SEEDBYTES..SEEDBYTES + RNDBYTES].copy_from_slice(&rnd[..RNDBYTES]);
+    keymu[SEEDBYTES + RNDBYTES..].copy_from_slice(&mu[..CRHBYTES]);
+
+    // rhoprime = CRH(key, rnd, mu)
+    shake256(&mut rhoprime, CRHBYTES, &keymu, SEEDBYTES + RNDBYTES + CRHBYTES);
 
-    let mut nonce = 0u16;
+    crypto_sign_signature_rhoprime::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYETA_PACKEDBYTES, POLY_UNIFORM_NBLOCKS, S, POLY_UNIFORM_GAMMA1_NBLOCKS, TAU, POLYZ_PACKEDBYTES, ETA>(sig, mu, &rhoprime, sk, TRBYTES)
+}
+
+/// The rejection sampling loop, given mu and the private seed rhoprime.
+/// `trbytes` is the length of tr in the packed secret key. Fails once the
+/// iteration limit or the nonce space is used up.
+pub(crate) fn crypto_sign_signature_rhoprime<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const POLYETA_PACKEDBYTES: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend, const POLY_UNIFORM_GAMMA1_NBLOCKS: usize, const TAU: usize, const POLYZ_PACKEDBYTES: usize, const ETA: usize>
+(sig: &mut [u8], mu: &[u8], rhoprime: &[u8], sk: &[u8], trbytes: usize) -> Result<(), SignError> {
+    let mut rej = RejectionLoop::<L>::new();
     let mut mat: [Polyvecl<L>; K] = [Polyvecl::default(); K];
     let (mut s1, mut y) = (Polyvecl::default(), Polyvecl::default());
     let (mut s2, mut t0) = (Polyveck::<K>::default(), Polyveck::default());
@@ -83,56 +155,32 @@ pub fn crypto_sign_signature<const K: usize, const L: usize, const POLYW1_PACKED
     let mut h = Polyveck::default();
     let mut cp = Poly::default();
     let mut state = KeccakState::default(); //shake256_init()
-    let mut rho = [0u8; SEEDBYTES];
+    let (mut rho, mut key) = ([0u8; SEEDBYTES], [0u8; SEEDBYTES]);
     let mut tr = [0u8; TRBYTES];
-    let mut rhoprime = [0u8; CRHBYTES];
-
-    let ctx = match ctx {
-        Some(x) => x,
-        None => &[0u8; 0],
-    };
-
-    if ctx.len() > 255 {
-        panic!("ctx is too long");
-    }
 
     unpack_sk::<K, L, POLYETA_PACKEDBYTES, ETA>(
         &mut rho,
-        &mut tr,
-        &mut keymu[..SEEDBYTES],
+        &mut tr[..trbytes],
+        &mut key,
         &mut t0,
         &mut s1,
         &mut s2,
         &sk,
     );
 
-    // Compute CRH(tr, 0, ctxlen, ctx, msg)
-    // keymu[0] = 0;
-    // keymu[1] = ctxLen;
-    let mu = [0u8, ctx.len() as u8];
-    shake256_absorb(&mut state, &tr, TRBYTES);
-    shake256_absorb(&mut state, &mu, 2);
-    shake256_absorb(&mut state, &ctx, ctx.len());
-    shake256_absorb(&mut state, m, m.len());
-    shake256_finalize(&mut state);
-    shake256_squeeze(&mut keymu[SEEDBYTES + RNDBYTES..], CRHBYTES, &mut state);
-
-    if using_randomized_signing {
-        randombytes(&mut rhoprime, CRHBYTES);
-    } else {
-        shake256(&mut rhoprime, CRHBYTES, &keymu, SEEDBYTES + RNDBYTES + CRHBYTES);
-    }
+    // Keep the secret vectors in normal representation for the fault checks
+    let plain = fault::enabled().then_some((s1, s2, t0));
 
     // Expand matrix and transform vectors
-    polyvec_matrix_expand::<K, L, POLY_UNIFORM_NBLOCKS, STREAM128_BLOCKBYTES>(&mut mat, &rho);
+    polyvec_matrix_expand::<K, L, POLY_UNIFORM_NBLOCKS, S>(&mut mat, &rho);
     polyvecl_ntt(&mut s1);
     polyveck_ntt(&mut s2);
     polyveck_ntt(&mut t0);
 
     loop {
         // Sample intermediate vector y
-        polyvecl_uniform_gamma1::<L, POLY_UNIFORM_GAMMA1_NBLOCKS, GAMMA1>(&mut y, &rhoprime, nonce);
-        nonce += 1;
+        let nonce = rej.next()?;
+        polyvecl_uniform_gamma1::<S, L, POLY_UNIFORM_GAMMA1_NBLOCKS, GAMMA1>(&mut y, rhoprime, nonce);
 
         // Matrix-vector multiplication
         let mut z = y;
@@ -147,7 +195,7 @@ pub fn crypto_sign_signature<const K: usize, const L: usize, const POLYW1_PACKED
         polyveck_pack_w1::<K, POLYW1_PACKEDBYTES, GAMMA2>(sig, &w1);
 
         state.init();
-        shake256_absorb(&mut state, &keymu[SEEDBYTES + RNDBYTES..], CRHBYTES);
+        shake256_absorb(&mut state, mu, CRHBYTES);
         shake256_absorb(&mut state, &sig, K * POLYW1_PACKEDBYTES);
         shake256_finalize(&mut state);
         shake256_squeeze(sig, CTILDEBYTES, &mut state);
@@ -160,6 +208,7 @@ pub fn crypto_sign_signature<const K: usize, const L: usize, const POLYW1_PACKED
         polyvecl_add(&mut z, &y);
         polyvecl_reduce(&mut z);
         if polyvecl_chknorm::<L>(&z, (GAMMA1 - BETA) as i32) > 0 {
+            rej.reject(Rejection::ZNorm);
             continue;
         }
 
@@ -170,6 +219,7 @@ pub fn crypto_sign_signature<const K: usize, const L: usize, const POLYW1_PACKED
         polyveck_sub(&mut w0, &h);
         polyveck_reduce(&mut w0);
         if polyveck_chknorm::<K>(&w0, (GAMMA2 - BETA) as i32) > 0 {
+            rej.reject(Rejection::R0Norm);
             continue;
         }
 
@@ -178,30 +228,102 @@ pub fn crypto_sign_signature<const K: usize, const L: usize, const POLYW1_PACKED
         polyveck_invntt_tomont(&mut h);
         polyveck_reduce(&mut h);
         if polyveck_chknorm::<K>(&h, GAMMA2 as i32) > 0 {
+            rej.reject(Rejection::Ct0Norm);
             continue;
         }
 
         polyveck_add(&mut w0, &h);
         let n = polyveck_make_hint::<K, GAMMA2>(&mut h, &w0, &w1);
         if n > OMEGA as i32 {
+            rej.reject(Rejection::HintCount);
             continue;
         }
 
         // Write signature
         pack_sig::<K, L, CTILDEBYTES, POLYZ_PACKEDBYTES, OMEGA, GAMMA1>(sig, None, &z, &h);
-        return;
+
+        // Check the packed signature before it is released
+        if let Some((s1, s2, t0)) = &plain {
+            let signed = Signed { sig, mu, rho: &rho, tr: &tr[..trbytes], mat: &mat, s1, s2, t0, y: &y, z: &z, w1: &w1 };
+            if let Err(e) = fault::check_signature::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYZ_PACKEDBYTES, TAU, POLY_UNIFORM_NBLOCKS, S>(&signed) {
+                sig.fill(0);
+                return Err(e);
+            }
+        }
+        return Ok(());
     }
 }
 
-pub fn crypto_sign_verify<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const SIGNBYTES: usize, const PUBLICKEYBYTES: usize, const POLYZ_PACKEDBYTES: usize, const TAU: usize, const POLY_UNIFORM_NBLOCKS: usize, const STREAM128_BLOCKBYTES: usize>(
+/// Verify with the external interface of FIPS 204 (ML-DSA.Verify).
+pub fn crypto_sign_verify<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const SIGNBYTES: usize, const PUBLICKEYBYTES: usize, const POLYZ_PACKEDBYTES: usize, const TAU: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend>(
     sig: &[u8],
     m: &[u8],
     pk: &[u8],
     ctx: Option<&[u8]>,
+) -> Result<(), SignError> {
+    let ctx = match ctx {
+        Some(x) => x,
+        None => &[0u8; 0],
+    };
+
+    if ctx.len() > 255 {
+        return Err(SignError::Input);
+    }
+
+    let mut pre = [0u8; 257];
+    pre[1] = ctx.len() as u8;
+    pre[2..2 + ctx.len()].copy_from_slice(ctx);
+
+    crypto_sign_verify_internal::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, SIGNBYTES, PUBLICKEYBYTES, POLYZ_PACKEDBYTES, TAU, POLY_UNIFORM_NBLOCKS, S>(sig, m, &pre[..2 + ctx.len()], pk)
+}
+
+/// ML-DSA.Verify_internal: verify a signature on M' = pre || m.
+pub fn crypto_sign_verify_internal<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const SIGNBYTES: usize, const PUBLICKEYBYTES: usize, const POLYZ_PACKEDBYTES: usize, const TAU: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend>(
+    sig: &[u8],
+    m: &[u8],
+    pre: &[u8],
+    pk: &[u8],
+) -> Result<(), SignError> {
+    let mut mu = [0u8; CRHBYTES];
+    let mut state = KeccakState::default(); // shake256_init()
+
+    if pk.len() != PUBLICKEYBYTES {
+        return Err(SignError::Input);
+    }
+
+    // Compute CRH(CRH(rho, t1), pre, msg)
+    shake256(&mut mu, TRBYTES, pk, PUBLICKEYBYTES);
+    shake256_absorb(&mut state, &mu, TRBYTES);
+    shake256_absorb(&mut state, pre, pre.len());
+    shake256_absorb(&mut state, m, m.len());
+    shake256_finalize(&mut state);
+    shake256_squeeze(&mut mu, CRHBYTES, &mut state);
+
+    crypto_sign_verify_mu::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, SIGNBYTES, PUBLICKEYBYTES, POLYZ_PACKEDBYTES, TAU, POLY_UNIFORM_NBLOCKS, S>(sig, &mu, pk)
+}
+
+/// Verify a signature on an externally computed message representative mu.
+pub fn crypto_sign_verify_mu<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const SIGNBYTES: usize, const PUBLICKEYBYTES: usize, const POLYZ_PACKEDBYTES: usize, const TAU: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend>(
+    sig: &[u8],
+    mu: &[u8],
+    pk: &[u8],
+) -> Result<(), SignError> {
+    if sig.len() != SIGNBYTES || pk.len() != PUBLICKEYBYTES || mu.len() != CRHBYTES {
+        return Err(SignError::Input);
+    }
+
+    verify_mu::<K, L, POLYW1_PACKEDBYTES, CTILDEBYTES, GAMMA1, GAMMA2, BETA, OMEGA, POLYZ_PACKEDBYTES, TAU, POLY_UNIFORM_NBLOCKS, S>(sig, mu, pk)
+}
+
+/// `crypto_sign_verify_mu` for a signature and public key of the right
+/// length, which the signer checks its output with.
+pub(crate) fn verify_mu<const K: usize, const L: usize, const POLYW1_PACKEDBYTES: usize, const CTILDEBYTES: usize, const GAMMA1: usize, const GAMMA2: usize, const BETA: usize, const OMEGA: usize, const POLYZ_PACKEDBYTES: usize, const TAU: usize, const POLY_UNIFORM_NBLOCKS: usize, S: StreamBackend>(
+    sig: &[u8],
+    mu: &[u8],
+    pk: &[u8],
 ) -> Result<(), SignError> {
     let mut buf = vec![0u8; K * POLYW1_PACKEDBYTES];
     let mut rho = [0u8; SEEDBYTES];
-    let mut mu = [0u8; CRHBYTES];
     let mut c = [0u8; CTILDEBYTES];
     let mut c2 = [0u8; CTILDEBYTES];
     let mut cp = Poly::default();
@@ -213,19 +335,6 @@ pub fn crypto_sign_verify<const K: usize, const L: usize, const POLYW1_PACKEDBYT
     );
     let mut state = KeccakState::default(); // shake256_init()
 
-    let ctx = match ctx {
-        Some(x) => x,
-        None => &[0u8; 0],
-    };
-
-    if ctx.len() > 255 {
-        return Err(SignError::Input);
-    }
-
-    if sig.len() != SIGNBYTES {
-        return Err(SignError::Input);
-    }
-
     unpack_pk::<K>(&mut rho, &mut t1, pk);
     if let Err(e) = unpack_sig::<K, L, CTILDEBYTES, POLYZ_PACKEDBYTES, OMEGA, GAMMA1>(&mut c, &mut z, &mut h, sig) {
         return Err(e);
@@ -234,19 +343,9 @@ pub fn crypto_sign_verify<const K: usize, const L: usize, const POLYW1_PACKEDBYT
         return Err(SignError::Input);
     }
 
-    // Compute CRH(CRH(rho, t1), msg)
-    shake256(&mut mu, TRBYTES, pk, PUBLICKEYBYTES);
-    shake256_absorb(&mut state, &mu, TRBYTES);
-    let mm = [0u8, ctx.len() as u8];
-    shake256_absorb(&mut state, &mm, 2);
-    shake256_absorb(&mut state, ctx, ctx.len());
-    shake256_absorb(&mut state, m, m.len());
-    shake256_finalize(&mut state);
-    shake256_squeeze(&mut mu, CRHBYTES, &mut state);
-
     // Matrix-vector multiplication; compute Az - c2^dt1
     poly_challenge::<CTILDEBYTES, TAU>(&mut cp, &c);
-    polyvec_matrix_expand::<K, L, POLY_UNIFORM_NBLOCKS, STREAM128_BLOCKBYTES>(&mut mat, &rho);
+    polyvec_matrix_expand::<K, L, POLY_UNIFORM_NBLOCKS, S>(&mut mat, &rho);
 
     polyvecl_ntt(&mut z);
     polyvec_matrix_pointwise_montgomery(&mut w1, &mat, &z);
@@ -0,0 +1,159 @@
+//! X.509 SubjectPublicKeyInfo encoding of public keys.
+//!
+//! ```text
+//! SubjectPublicKeyInfo ::= SEQUENCE {
+//!   algorithm         AlgorithmIdentifier,  -- id-ml-dsa-*, parameters absent
+//!   subjectPublicKey  BIT STRING }          -- pk as produced by pack_pk
+//! ```
+
+use anyhow::{anyhow, bail};
+
+use crate::algorithm::Dilithium;
+use crate::asn1::*;
+use crate::symmetric::StreamBackend;
+
+#[cfg(feature = "pem")]
+const PEM_LABEL: &str = "PUBLIC KEY";
+
+/// Encode a SubjectPublicKeyInfo for an arbitrary algorithm without parameters.
+pub fn encode_spki(algorithm: &[u8], key: &[u8]) -> Vec<u8> {
+    sequence(&[&algorithm_identifier(algorithm), &bit_string(key)])
+}
+
+/// Decode a SubjectPublicKeyInfo whose algorithm has no parameters,
+/// returning the algorithm identifier and the key bytes.
+pub fn decode_spki(der: &[u8]) -> anyhow::Result<(&[u8], &[u8])> {
+    let mut outer = DerReader::new(der);
+    let mut spki = outer.read_sequence()?;
+    outer.finish()?;
+    let algorithm = spki.read_algorithm_identifier()?;
+    let key = spki.read_bit_string()?;
+    spki.finish()?;
+    Ok((algorithm, key))
+}
+
+impl<
+    const K: usize,
+    const L: usize,
+    const ETA: usize,
+    const TAU: usize,
+    const BETA: usize,
+    const GAMMA1: usize,
+    const GAMMA2: usize,
+    const OMEGA: usize,
+    const PUBLICKEYBYTES: usize,
+    const SECRETKEYBYTES: usize,
+    const POLY_UNIFORM_NBLOCKS: usize,
+    S: StreamBackend,
+    const POLY_UNIFORM_ETA_NBLOCKS: usize,
+    const POLYETA_PACKEDBYTES: usize,
+    const SIGNBYTES: usize,
+    const POLYW1_PACKEDBYTES: usize,
+    const CTILDEBYTES: usize,
+    const POLYZ_PACKEDBYTES: usize,
+    const POLYVECH_PACKEDBYTES: usize,
+    const POLY_UNIFORM_GAMMA1_NBLOCKS: usize,
+>
+Dilithium<
+    K,
+    L,
+    ETA,
+    TAU,
+    BETA,
+    GAMMA1,
+    GAMMA2,
+    OMEGA,
+    PUBLICKEYBYTES,
+    SECRETKEYBYTES,
+    POLY_UNIFORM_NBLOCKS,
+    S,
+    POLY_UNIFORM_ETA_NBLOCKS,
+    POLYETA_PACKEDBYTES,
+    SIGNBYTES,
+    POLYW1_PACKEDBYTES,
+    CTILDEBYTES,
+    POLYZ_PACKEDBYTES,
+    POLYVECH_PACKEDBYTES,
+    POLY_UNIFORM_GAMMA1_NBLOCKS,
+>
+{
+    /// Encode a packed public key as DER SubjectPublicKeyInfo.
+    pub fn to_public_key_der(pk: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let oid = Self::parameter_set()
+            .ok_or_else(|| anyhow!("parameters do not match an ML-DSA parameter set"))?
+            .oid();
+        if pk.len() != PUBLICKEYBYTES {
+            bail!("public key must be {} bytes", PUBLICKEYBYTES);
+        }
+        Ok(encode_spki(oid, pk))
+    }
+
+    #[cfg(feature = "pem")]
+    pub fn to_public_key_pem(pk: &[u8]) -> anyhow::Result<String> {
+        Ok(pem_encode(PEM_LABEL, &Self::to_public_key_der(pk)?))
+    }
+
+    /// Decode a DER SubjectPublicKeyInfo of this parameter set into the
+    /// packed public key.
+    pub fn from_public_key_der(der: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let oid = Self::parameter_set()
+            .ok_or_else(|| anyhow!("parameters do not match an ML-DSA parameter set"))?
+            .oid();
+        let (algorithm, key) = decode_spki(der)?;
+        if algorithm != oid {
+            bail!("algorithm {} does not match the parameter set", oid_to_string(algorithm));
+        }
+        if key.len() != PUBLICKEYBYTES {
+            bail!("public key must be {} bytes, got {}", PUBLICKEYBYTES, key.len());
+        }
+        Ok(key.to_vec())
+    }
+
+    #[cfg(feature = "pem")]
+    pub fn from_public_key_pem(pem: &str) -> anyhow::Result<Vec<u8>> {
+        Self::from_public_key_der(&pem_decode(PEM_LABEL, pem)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dilithium2, Dilithium3, Dilithium5};
+
+    #[test]
+    fn matches_hand_built_der() {
+        let (_, pk) = Dilithium2::key_gen(Some(vec![3u8; 32])).unwrap();
+        let der = Dilithium2::to_public_key_der(&pk).unwrap();
+        // 1312 byte key, BIT STRING of 1313 bytes
+        let mut expected = vec![
+            0x30, 0x82, 0x05, 0x32, 0x30, 0x0b, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03,
+            0x04, 0x03, 0x11, 0x03, 0x82, 0x05, 0x21, 0x00,
+        ];
+        expected.extend_from_slice(&pk);
+        assert_eq!(der, expected);
+    }
+
+    #[test]
+    fn roundtrip_and_validation() {
+        let (_, pk) = Dilithium5::key_gen(None).unwrap();
+        #[cfg(feature = "pem")]
+        {
+            let pem = Dilithium5::to_public_key_pem(&pk).unwrap();
+            assert!(pem.starts_with("-----BEGIN PUBLIC KEY-----\n"));
+            assert_eq!(Dilithium5::from_public_key_pem(&pem).unwrap(), pk);
+        }
+
+        // Wrong parameter set
+        let der = Dilithium5::to_public_key_der(&pk).unwrap();
+        assert!(Dilithium3::from_public_key_der(&der).is_err());
+
+        // Right OID, wrong key length
+        let short = encode_spki(ID_ML_DSA_87, &pk[..1952]);
+        assert!(Dilithium5::from_public_key_der(&short).is_err());
+        assert!(Dilithium5::to_public_key_der(&pk[..1952]).is_err());
+
+        // Parameters must be absent
+        let with_null = sequence(&[&sequence(&[&oid(ID_ML_DSA_87), &[0x05, 0x00]]), &bit_string(&pk)]);
+        assert!(Dilithium5::from_public_key_der(&with_null).is_err());
+    }
+}
@@ -1,73 +1,156 @@
+use crate::aes256ctr::*;
 use crate::fips202::*;
+use crate::fips202x4::*;
 use crate::params::{CRHBYTES, SEEDBYTES};
 
-#[cfg(feature = "aes")]
-use crate::aes256ctr::*;
-
-#[cfg(not(feature = "aes"))]
-pub type Stream128State = KeccakState;
-#[cfg(feature = "aes")]
-pub type Stream128State = Aes256ctrCtx;
-#[cfg(not(feature = "aes"))]
-pub type Stream256State = KeccakState;
-#[cfg(feature = "aes")]
-pub type Stream256State = Aes256ctrCtx;
-
-// #[cfg(feature = "aes")]
-// pub const STREAM128_BLOCKBYTES: usize = AES256CTR_BLOCKBYTES;
-// #[cfg(not(feature = "aes"))]
-// pub const STREAM128_BLOCKBYTES: usize = SHAKE128_RATE;
-
-#[cfg(feature = "aes")]
-pub const STREAM256_BLOCKBYTES: usize = AES256CTR_BLOCKBYTES;
-#[cfg(not(feature = "aes"))]
-pub const STREAM256_BLOCKBYTES: usize = SHAKE256_RATE;
-
-pub fn _crh(out: &mut [u8], input: &[u8], inbytes: usize) {
-  shake256(out, CRHBYTES, input, inbytes)
-}
-
-pub fn stream128_init(state: &mut Stream128State, seed: &[u8], nonce: u16) {
-  #[cfg(not(feature = "aes"))]
-  dilithium_shake128_stream_init(state, seed, nonce);
-
-  #[cfg(feature = "aes")]
-  dilithium_aes256ctr_init(state, seed, nonce)
+/// The symmetric primitive used to expand seeds into the matrix A and the
+/// vectors s1, s2 and y. `Shake` is the FIPS 204 instantiation; `Aes` is
+/// the AES-256-CTR variant from the round-3 Dilithium submission.
+///
+/// The stream128 functions are keyed with the 32 byte seed rho, the
+/// stream256 ones with the 64 byte seed rhoprime. The x4 variants run
+/// four streams with the same seed and different nonces side by side.
+pub trait StreamBackend {
+  type Stream128State: Default;
+  type Stream256State: Default;
+  type Stream128x4State: Default;
+  type Stream256x4State: Default;
+
+  const STREAM128_BLOCKBYTES: usize;
+  const STREAM256_BLOCKBYTES: usize;
+  /// Whether this is the instantiation standardized in FIPS 204.
+  const STANDARD: bool;
+
+  fn stream128_init(state: &mut Self::Stream128State, seed: &[u8], nonce: u16);
+  fn stream128_squeezeblocks(out: &mut [u8], outblocks: u64, state: &mut Self::Stream128State);
+  fn stream256_init(state: &mut Self::Stream256State, seed: &[u8], nonce: u16);
+  fn stream256_squeezeblocks(out: &mut [u8], outblocks: u64, state: &mut Self::Stream256State);
+
+  fn stream128x4_init(state: &mut Self::Stream128x4State, seed: &[u8], nonces: [u16; 4]);
+  fn stream128x4_squeezeblocks(out: [&mut [u8]; 4], outblocks: u64, state: &mut Self::Stream128x4State);
+  fn stream256x4_init(state: &mut Self::Stream256x4State, seed: &[u8], nonces: [u16; 4]);
+  fn stream256x4_squeezeblocks(out: [&mut [u8]; 4], outblocks: u64, state: &mut Self::Stream256x4State);
 }
 
-pub fn stream128_squeezeblocks(
-  out: &mut [u8],
-  outblocks: u64,
-  state: &mut Stream128State,
-) {
-  #[cfg(not(feature = "aes"))]
-  shake128_squeezeblocks(out, outblocks as usize, state);
-
-  #[cfg(feature = "aes")]
-  aes256ctr_squeezeblocks(out, outblocks, state);
+/// SHAKE128 and SHAKE256 streams, as in FIPS 204.
+pub struct Shake;
+
+/// AES-256-CTR streams, the "-AES" variant of round-3 Dilithium. Not
+/// standardized, and only usable without a FIPS 204 parameter set.
+pub struct Aes;
+
+impl StreamBackend for Shake {
+  type Stream128State = KeccakState;
+  type Stream256State = KeccakState;
+  type Stream128x4State = KeccakStateX4;
+  type Stream256x4State = KeccakStateX4;
+
+  const STREAM128_BLOCKBYTES: usize = SHAKE128_RATE;
+  const STREAM256_BLOCKBYTES: usize = SHAKE256_RATE;
+  const STANDARD: bool = true;
+
+  fn stream128_init(state: &mut KeccakState, seed: &[u8], nonce: u16) {
+    dilithium_shake128_stream_init(state, seed, nonce);
+  }
+
+  fn stream128_squeezeblocks(out: &mut [u8], outblocks: u64, state: &mut KeccakState) {
+    shake128_squeezeblocks(out, outblocks as usize, state);
+  }
+
+  fn stream256_init(state: &mut KeccakState, seed: &[u8], nonce: u16) {
+    dilithium_shake256_stream_init(state, seed, nonce);
+  }
+
+  fn stream256_squeezeblocks(out: &mut [u8], outblocks: u64, state: &mut KeccakState) {
+    shake256_squeezeblocks(out, outblocks as usize, state);
+  }
+
+  fn stream128x4_init(state: &mut KeccakStateX4, seed: &[u8], nonces: [u16; 4]) {
+    let input = nonces.map(|nonce| {
+      let mut t = [0u8; SEEDBYTES + 2];
+      t[..SEEDBYTES].copy_from_slice(&seed[..SEEDBYTES]);
+      t[SEEDBYTES..].copy_from_slice(&nonce.to_le_bytes());
+      t
+    });
+    let [t0, t1, t2, t3] = &input;
+    shake128x4_absorb_once(state, [t0, t1, t2, t3], SEEDBYTES + 2);
+  }
+
+  fn stream128x4_squeezeblocks(out: [&mut [u8]; 4], outblocks: u64, state: &mut KeccakStateX4) {
+    shake128x4_squeezeblocks(out, outblocks as usize, state);
+  }
+
+  fn stream256x4_init(state: &mut KeccakStateX4, seed: &[u8], nonces: [u16; 4]) {
+    let input = nonces.map(|nonce| {
+      let mut t = [0u8; CRHBYTES + 2];
+      t[..CRHBYTES].copy_from_slice(&seed[..CRHBYTES]);
+      t[CRHBYTES..].copy_from_slice(&nonce.to_le_bytes());
+      t
+    });
+    let [t0, t1, t2, t3] = &input;
+    shake256x4_absorb_once(state, [t0, t1, t2, t3], CRHBYTES + 2);
+  }
+
+  fn stream256x4_squeezeblocks(out: [&mut [u8]; 4], outblocks: u64, state: &mut KeccakStateX4) {
+    shake256x4_squeezeblocks(out, outblocks as usize, state);
+  }
 }
 
-pub fn stream256_init(state: &mut Stream256State, seed: &[u8], nonce: u16) {
-  #[cfg(not(feature = "aes"))]
-  dilithium_shake256_stream_init(state, seed, nonce);
-
-  #[cfg(feature = "aes")]
-  dilithium_aes256ctr_init(state, seed, nonce)
+impl StreamBackend for Aes {
+  type Stream128State = Aes256ctrCtx;
+  type Stream256State = Aes256ctrCtx;
+  type Stream128x4State = [Aes256ctrCtx; 4];
+  type Stream256x4State = [Aes256ctrCtx; 4];
+
+  const STREAM128_BLOCKBYTES: usize = AES256CTR_BLOCKBYTES;
+  const STREAM256_BLOCKBYTES: usize = AES256CTR_BLOCKBYTES;
+  const STANDARD: bool = false;
+
+  fn stream128_init(state: &mut Aes256ctrCtx, seed: &[u8], nonce: u16) {
+    dilithium_aes256ctr_init(state, seed, nonce);
+  }
+
+  fn stream128_squeezeblocks(out: &mut [u8], outblocks: u64, state: &mut Aes256ctrCtx) {
+    aes256ctr_squeezeblocks(out, outblocks, state);
+  }
+
+  fn stream256_init(state: &mut Aes256ctrCtx, seed: &[u8], nonce: u16) {
+    dilithium_aes256ctr_init(state, seed, nonce);
+  }
+
+  fn stream256_squeezeblocks(out: &mut [u8], outblocks: u64, state: &mut Aes256ctrCtx) {
+    aes256ctr_squeezeblocks(out, outblocks, state);
+  }
+
+  // AES-256-CTR is already parallel within one stream
+
+  fn stream128x4_init(state: &mut [Aes256ctrCtx; 4], seed: &[u8], nonces: [u16; 4]) {
+    for (s, nonce) in state.iter_mut().zip(nonces) {
+      dilithium_aes256ctr_init(s, seed, nonce);
+    }
+  }
+
+  fn stream128x4_squeezeblocks(out: [&mut [u8]; 4], outblocks: u64, state: &mut [Aes256ctrCtx; 4]) {
+    for (out, s) in out.into_iter().zip(state.iter_mut()) {
+      aes256ctr_squeezeblocks(out, outblocks, s);
+    }
+  }
+
+  fn stream256x4_init(state: &mut [Aes256ctrCtx; 4], seed: &[u8], nonces: [u16; 4]) {
+    Self::stream128x4_init(state, seed, nonces);
+  }
+
+  fn stream256x4_squeezeblocks(out: [&mut [u8]; 4], outblocks: u64, state: &mut [Aes256ctrCtx; 4]) {
+    Self::stream128x4_squeezeblocks(out, outblocks, state);
+  }
 }
 
-pub fn stream256_squeezeblocks(
-  out: &mut [u8],
-  outblocks: u64,
-  state: &mut Stream256State,
-) {
-  #[cfg(not(feature = "aes"))]
-  shake256_squeezeblocks(out, outblocks as usize, state);
-
-  #[cfg(feature = "aes")]
-  aes256ctr_squeezeblocks(out, outblocks, state);
+pub fn _crh(out: &mut [u8], input: &[u8], inbytes: usize) {
+  shake256(out, CRHBYTES, input, inbytes)
 }
 
-#[cfg(feature = "aes")]
+/// The key is the first 32 bytes of the seed; the nonce fills the low
+/// bytes of the 96 bit IV.
 pub fn dilithium_aes256ctr_init(
   state: &mut Aes256ctrCtx,
   key: &[u8],
@@ -76,10 +159,9 @@ pub fn dilithium_aes256ctr_init(
   let mut expnonce = [0u8; 12];
   expnonce[0] = nonce as u8;
   expnonce[1] = (nonce >> 8) as u8;
-  aes256ctr_init(state, key, expnonce);
+  aes256ctr_init(state, &key[..32], expnonce);
 }
 
-#[cfg(not(feature = "aes"))]
 pub fn dilithium_shake128_stream_init(
   state: &mut KeccakState,
   seed: &[u8],
@@ -92,7 +174,6 @@ pub fn dilithium_shake128_stream_init(
   shake128_finalize(state);
 }
 
-#[cfg(not(feature = "aes"))]
 pub fn dilithium_shake256_stream_init(
   state: &mut KeccakState,
   seed: &[u8],
@@ -104,3 +185,27 @@ pub fn dilithium_shake256_stream_init(
   shake256_absorb(state, &t, 2);
   shake256_finalize(state);
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn aes_stream_is_aes256_ctr() {
+    // Key 00 01 .. 1f and IV 02 01 00 .. 00, as computed by
+    // openssl enc -aes-256-ctr
+    let key: Vec<u8> = (0..32).collect();
+    let mut state = Aes256ctrCtx::default();
+    let mut out = [0u8; 128];
+    Aes::stream128_init(&mut state, &key, 0x0102);
+    Aes::stream128_squeezeblocks(&mut out, 2, &mut state);
+    assert_eq!(
+      out[..16],
+      [0xd2, 0xb5, 0x02, 0x1f, 0x9f, 0x0c, 0xc0, 0x98, 0xbc, 0x87, 0xd9, 0x86, 0x62, 0x96, 0x7c, 0x17]
+    );
+    assert_eq!(
+      out[112..],
+      [0x99, 0xc8, 0x60, 0xf5, 0xe7, 0x6f, 0x8f, 0x45, 0x8b, 0xb9, 0x3e, 0x11, 0x9b, 0x30, 0x02, 0x1a]
+    );
+  }
+}
@@ -0,0 +1,879 @@
+//! X.509 v3 certificates signed with ML-DSA.
+//!
+//! Certificates are built from a `CertificateBuilder` and signed by the
+//! issuer's parameter set with `Dilithium::sign_certificate`, which uses the
+//! id-ml-dsa-* signature algorithm and an empty context string. Parsing keeps
+//! the exact DER of the TBSCertificate around so that signatures made by other
+//! implementations can be checked.
+
+use std::fmt;
+
+use anyhow::{anyhow, bail};
+
+use crate::algorithm::{Dilithium, ParameterSet};
+use crate::asn1::*;
+use crate::fips202::shake256;
+use crate::randombytes::randombytes;
+use crate::spki::decode_spki;
+use crate::symmetric::StreamBackend;
+
+#[cfg(feature = "pem")]
+const PEM_LABEL: &str = "CERTIFICATE";
+
+// Attribute types, DER encoded without tag and length
+/// id-at-commonName, 2.5.4.3
+pub const ID_AT_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+/// id-at-countryName, 2.5.4.6
+pub const ID_AT_COUNTRY_NAME: &[u8] = &[0x55, 0x04, 0x06];
+/// id-at-organizationName, 2.5.4.10
+pub const ID_AT_ORGANIZATION_NAME: &[u8] = &[0x55, 0x04, 0x0a];
+/// id-at-organizationalUnitName, 2.5.4.11
+pub const ID_AT_ORGANIZATIONAL_UNIT_NAME: &[u8] = &[0x55, 0x04, 0x0b];
+
+// Certificate extensions
+/// id-ce-subjectKeyIdentifier, 2.5.29.14
+pub const ID_CE_SUBJECT_KEY_IDENTIFIER: &[u8] = &[0x55, 0x1d, 0x0e];
+/// id-ce-keyUsage, 2.5.29.15
+pub const ID_CE_KEY_USAGE: &[u8] = &[0x55, 0x1d, 0x0f];
+/// id-ce-subjectAltName, 2.5.29.17
+pub const ID_CE_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+/// id-ce-basicConstraints, 2.5.29.19
+pub const ID_CE_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1d, 0x13];
+/// id-ce-authorityKeyIdentifier, 2.5.29.35
+pub const ID_CE_AUTHORITY_KEY_IDENTIFIER: &[u8] = &[0x55, 0x1d, 0x23];
+
+/// KeyUsage bits, combine with `|`.
+pub mod key_usage {
+    pub const DIGITAL_SIGNATURE: u16 = 1 << 0;
+    pub const NON_REPUDIATION: u16 = 1 << 1;
+    pub const KEY_ENCIPHERMENT: u16 = 1 << 2;
+    pub const DATA_ENCIPHERMENT: u16 = 1 << 3;
+    pub const KEY_AGREEMENT: u16 = 1 << 4;
+    pub const KEY_CERT_SIGN: u16 = 1 << 5;
+    pub const CRL_SIGN: u16 = 1 << 6;
+    pub const ENCIPHER_ONLY: u16 = 1 << 7;
+    pub const DECIPHER_ONLY: u16 = 1 << 8;
+}
+
+/// A single attribute of a distinguished name. `tag` is the ASN.1 string
+/// type the value is encoded with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameAttribute {
+    pub oid: Vec<u8>,
+    pub tag: u8,
+    pub value: String,
+}
+
+/// Distinguished name, encoded as one attribute per RelativeDistinguishedName.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Name {
+    pub attributes: Vec<NameAttribute>,
+}
+
+impl Name {
+    pub fn new() -> Self {
+        Name::default()
+    }
+
+    /// Append an attribute of the given type encoded as UTF8String.
+    pub fn with(mut self, oid: &[u8], value: &str) -> Self {
+        self.attributes.push(NameAttribute { oid: oid.to_vec(), tag: UTF8_STRING, value: value.to_string() });
+        self
+    }
+
+    pub fn common_name(self, value: &str) -> Self {
+        self.with(ID_AT_COMMON_NAME, value)
+    }
+
+    pub fn organization(self, value: &str) -> Self {
+        self.with(ID_AT_ORGANIZATION_NAME, value)
+    }
+
+    pub fn organizational_unit(self, value: &str) -> Self {
+        self.with(ID_AT_ORGANIZATIONAL_UNIT_NAME, value)
+    }
+
+    /// Country names are PrintableStrings of two letters.
+    pub fn country(mut self, value: &str) -> Self {
+        self.attributes.push(NameAttribute { oid: ID_AT_COUNTRY_NAME.to_vec(), tag: PRINTABLE_STRING, value: value.to_string() });
+        self
+    }
+
+    pub fn to_der(&self) -> Vec<u8> {
+        let rdns: Vec<Vec<u8>> = self
+            .attributes
+            .iter()
+            .map(|a| set_of(&[&sequence(&[&oid(&a.oid), &tlv(a.tag, a.value.as_bytes())])]))
+            .collect();
+        sequence(&rdns.iter().map(|x| x.as_slice()).collect::<Vec<_>>())
+    }
+
+    pub(crate) fn decode(r: &mut DerReader) -> anyhow::Result<Self> {
+        let mut rdns = r.read_sequence()?;
+        let mut attributes = Vec::new();
+        while !rdns.is_empty() {
+            let mut rdn = rdns.read_nested(SET)?;
+            while !rdn.is_empty() {
+                let mut atv = rdn.read_sequence()?;
+                let oid = atv.read_oid()?.to_vec();
+                let (tag, value, _) = atv.read_raw()?;
+                atv.finish()?;
+                let value = String::from_utf8(value.to_vec()).map_err(|_| anyhow!("name attribute is not a string"))?;
+                attributes.push(NameAttribute { oid, tag, value });
+            }
+        }
+        Ok(Name { attributes })
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = self
+            .attributes
+            .iter()
+            .map(|a| {
+                let key = match a.oid.as_slice() {
+                    ID_AT_COMMON_NAME => "CN".to_string(),
+                    ID_AT_COUNTRY_NAME => "C".to_string(),
+                    ID_AT_ORGANIZATION_NAME => "O".to_string(),
+                    ID_AT_ORGANIZATIONAL_UNIT_NAME => "OU".to_string(),
+                    oid => oid_to_string(oid),
+                };
+                format!("{}={}", key, a.value)
+            })
+            .collect();
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Validity period in seconds since the Unix epoch, both ends inclusive.
+/// Times before 1970 are negative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Validity {
+    pub not_before: i64,
+    pub not_after: i64,
+}
+
+impl Validity {
+    /// 9999-12-31 23:59:59, which RFC 5280 uses for certificates without a
+    /// well-defined expiration date.
+    pub const NO_EXPIRATION: i64 = 253402300799;
+
+    /// Validity starting now and lasting the given number of days, at most
+    /// until `NO_EXPIRATION`.
+    pub fn for_days(days: u64) -> Self {
+        let now = unix_time();
+        let end = days.checked_mul(86400).and_then(|secs| i64::try_from(secs).ok()).and_then(|secs| now.checked_add(secs));
+        Validity { not_before: now, not_after: end.map_or(Self::NO_EXPIRATION, |t| t.min(Self::NO_EXPIRATION)) }
+    }
+
+    pub fn contains(&self, time: i64) -> bool {
+        self.not_before <= time && time <= self.not_after
+    }
+}
+
+pub(crate) fn unix_time() -> i64 {
+    match std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    }
+}
+
+/// Days since 1970-01-01 to civil date, from Howard Hinnant's date algorithms.
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (yoe + era * 400 + (m <= 2) as i64, m, d)
+}
+
+fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (m as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+/// Encode a time as UTCTime for the years 1950 to 2049 and as
+/// GeneralizedTime otherwise, as RFC 5280 requires.
+pub(crate) fn encode_time(t: i64) -> Vec<u8> {
+    let (y, mo, d) = civil_from_days(t.div_euclid(86400));
+    let s = t.rem_euclid(86400);
+    let hms = format!("{:02}{:02}{:02}Z", s / 3600, s / 60 % 60, s % 60);
+    if (1950..2050).contains(&y) {
+        tlv(UTC_TIME, format!("{:02}{:02}{:02}{}", y % 100, mo, d, hms).as_bytes())
+    } else {
+        tlv(GENERALIZED_TIME, format!("{:04}{:02}{:02}{}", y, mo, d, hms).as_bytes())
+    }
+}
+
+pub(crate) fn decode_time(r: &mut DerReader) -> anyhow::Result<i64> {
+    let (tag, value, _) = r.read_raw()?;
+    let s = std::str::from_utf8(value)?;
+    let digits = match (tag, s.len()) {
+        (UTC_TIME, 13) | (GENERALIZED_TIME, 15) if s.ends_with('Z') => &s[..s.len() - 1],
+        _ => bail!("unsupported time encoding"),
+    };
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        bail!("invalid time");
+    }
+    let num = |i: usize, n: usize| digits[i..i + n].parse::<u32>().unwrap();
+    let (y, rest) = if tag == UTC_TIME {
+        let yy = num(0, 2) as i64;
+        (if yy >= 50 { 1900 + yy } else { 2000 + yy }, 2)
+    } else {
+        (num(0, 4) as i64, 4)
+    };
+    let (mo, d, h, mi, sec) = (num(rest, 2), num(rest + 2, 2), num(rest + 4, 2), num(rest + 6, 2), num(rest + 8, 2));
+    if !(1..=12).contains(&mo) || !(1..=31).contains(&d) || h > 23 || mi > 59 || sec > 59 {
+        bail!("invalid time");
+    }
+    Ok(days_from_civil(y, mo, d) * 86400 + (h * 3600 + mi * 60 + sec) as i64)
+}
+
+/// Encode named bits as a DER BIT STRING, dropping trailing zero bits.
+pub(crate) fn named_bits(bits: u16) -> Vec<u8> {
+    let mut bytes = vec![(bits as u8).reverse_bits(), ((bits >> 8) as u8).reverse_bits()];
+    while bytes.last() == Some(&0) {
+        bytes.pop();
+    }
+    let unused = bytes.last().map_or(0, |b| b.trailing_zeros() as u8);
+    let mut v = vec![unused];
+    v.extend_from_slice(&bytes);
+    tlv(BIT_STRING, &v)
+}
+
+fn decode_named_bits(value: &[u8]) -> anyhow::Result<u16> {
+    let mut r = DerReader::new(value);
+    let v = r.read(BIT_STRING)?;
+    r.finish()?;
+    match v {
+        [unused, bits @ ..] if *unused < 8 && bits.len() <= 2 => {
+            Ok(bits.iter().enumerate().fold(0u16, |acc, (i, b)| acc | (b.reverse_bits() as u16) << (8 * i)))
+        }
+        _ => bail!("invalid named bit string"),
+    }
+}
+
+/// Key identifier of a public key: the leftmost 160 bits of SHAKE256 over the
+/// subjectPublicKey BIT STRING value. This is not one of the RFC 7093
+/// methods, which use SHA-2; RFC 5280 allows any method that yields unique
+/// identifiers.
+pub fn key_identifier(public_key: &[u8]) -> Vec<u8> {
+    let mut id = vec![0u8; 20];
+    shake256(&mut id, 20, public_key, public_key.len());
+    id
+}
+
+/// Value of the basicConstraints extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BasicConstraints {
+    pub ca: bool,
+    pub path_len: Option<u64>,
+}
+
+/// A certificate extension, `value` is the DER inside the extnValue OCTET STRING.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension {
+    pub oid: Vec<u8>,
+    pub critical: bool,
+    pub value: Vec<u8>,
+}
+
+impl Extension {
+    pub fn basic_constraints(ca: bool, path_len: Option<u64>) -> Self {
+        let mut fields = Vec::new();
+        if ca {
+            fields.push(boolean(true));
+        }
+        if let Some(n) = path_len {
+            fields.push(small_integer(n));
+        }
+        let value = sequence(&fields.iter().map(|x| x.as_slice()).collect::<Vec<_>>());
+        Extension { oid: ID_CE_BASIC_CONSTRAINTS.to_vec(), critical: true, value }
+    }
+
+    /// KeyUsage from bits in `key_usage`.
+    pub fn key_usage(bits: u16) -> Self {
+        Extension { oid: ID_CE_KEY_USAGE.to_vec(), critical: true, value: named_bits(bits) }
+    }
+
+    pub fn subject_key_identifier(key_id: &[u8]) -> Self {
+        Extension { oid: ID_CE_SUBJECT_KEY_IDENTIFIER.to_vec(), critical: false, value: octet_string(key_id) }
+    }
+
+    pub fn authority_key_identifier(key_id: &[u8]) -> Self {
+        Extension { oid: ID_CE_AUTHORITY_KEY_IDENTIFIER.to_vec(), critical: false, value: sequence(&[&tlv(context(0), key_id)]) }
+    }
+
+    /// subjectAltName holding DNS names.
+    pub fn subject_alt_name_dns(names: &[&str]) -> Self {
+        let names: Vec<Vec<u8>> = names.iter().map(|n| tlv(context(2), n.as_bytes())).collect();
+        let value = sequence(&names.iter().map(|x| x.as_slice()).collect::<Vec<_>>());
+        Extension { oid: ID_CE_SUBJECT_ALT_NAME.to_vec(), critical: false, value }
+    }
+
+    pub(crate) fn to_der(&self) -> Vec<u8> {
+        if self.critical {
+            sequence(&[&oid(&self.oid), &boolean(true), &octet_string(&self.value)])
+        } else {
+            sequence(&[&oid(&self.oid), &octet_string(&self.value)])
+        }
+    }
+
+    pub(crate) fn decode(r: &mut DerReader) -> anyhow::Result<Self> {
+        let mut ext = r.read_sequence()?;
+        let oid = ext.read_oid()?.to_vec();
+        let critical = if ext.peek_tag() == Some(BOOLEAN) { ext.read_boolean()? } else { false };
+        let value = ext.read_octet_string()?.to_vec();
+        ext.finish()?;
+        Ok(Extension { oid, critical, value })
+    }
+
+    /// Encode a list of extensions as `SEQUENCE OF Extension`.
+    pub(crate) fn encode_list(extensions: &[Extension]) -> Vec<u8> {
+        let exts: Vec<Vec<u8>> = extensions.iter().map(|e| e.to_der()).collect();
+        sequence(&exts.iter().map(|x| x.as_slice()).collect::<Vec<_>>())
+    }
+
+    pub(crate) fn decode_list(r: &mut DerReader) -> anyhow::Result<Vec<Extension>> {
+        let mut list = r.read_sequence()?;
+        let mut extensions: Vec<Extension> = Vec::new();
+        while !list.is_empty() {
+            let ext = Extension::decode(&mut list)?;
+            if extensions.iter().any(|e| e.oid == ext.oid) {
+                bail!("duplicate extension {}", oid_to_string(&ext.oid));
+            }
+            extensions.push(ext);
+        }
+        Ok(extensions)
+    }
+}
+
+/// Look up and decode the basicConstraints and keyUsage extensions.
+pub(crate) fn find_basic_constraints(extensions: &[Extension]) -> anyhow::Result<Option<BasicConstraints>> {
+    let ext = match extensions.iter().find(|e| e.oid == ID_CE_BASIC_CONSTRAINTS) {
+        Some(e) => e,
+        None => return Ok(None),
+    };
+    let mut outer = DerReader::new(&ext.value);
+    let mut bc = outer.read_sequence()?;
+    outer.finish()?;
+    let ca = if bc.peek_tag() == Some(BOOLEAN) { bc.read_boolean()? } else { false };
+    let path_len = if bc.peek_tag() == Some(INTEGER) { Some(bc.read_small_integer()?) } else { None };
+    bc.finish()?;
+    Ok(Some(BasicConstraints { ca, path_len }))
+}
+
+pub(crate) fn find_key_usage(extensions: &[Extension]) -> anyhow::Result<Option<u16>> {
+    match extensions.iter().find(|e| e.oid == ID_CE_KEY_USAGE) {
+        Some(e) => decode_named_bits(&e.value).map(Some),
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn find_key_identifier(extensions: &[Extension], which: &[u8]) -> anyhow::Result<Option<Vec<u8>>> {
+    let ext = match extensions.iter().find(|e| e.oid == which) {
+        Some(e) => e,
+        None => return Ok(None),
+    };
+    let mut r = DerReader::new(&ext.value);
+    let id = if which == ID_CE_AUTHORITY_KEY_IDENTIFIER {
+        r.read_sequence()?.read_optional(context(0))?.map(|x| x.to_vec())
+    } else {
+        Some(r.read_octet_string()?.to_vec())
+    };
+    r.finish()?;
+    Ok(id)
+}
+
+/// The signed part of a certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TbsCertificate {
+    pub serial_number: Vec<u8>,
+    pub signature_algorithm: Vec<u8>,
+    pub issuer: Name,
+    pub validity: Validity,
+    pub subject: Name,
+    pub subject_public_key_info: Vec<u8>,
+    pub extensions: Vec<Extension>,
+}
+
+impl TbsCertificate {
+    pub fn to_der(&self) -> Vec<u8> {
+        let mut fields = vec![
+            tlv(context_constructed(0), &small_integer(2)),
+            integer(&self.serial_number),
+            algorithm_identifier(&self.signature_algorithm),
+            self.issuer.to_der(),
+            sequence(&[&encode_time(self.validity.not_before), &encode_time(self.validity.not_after)]),
+            self.subject.to_der(),
+            self.subject_public_key_info.clone(),
+        ];
+        if !self.extensions.is_empty() {
+            fields.push(tlv(context_constructed(3), &Extension::encode_list(&self.extensions)));
+        }
+        sequence(&fields.iter().map(|x| x.as_slice()).collect::<Vec<_>>())
+    }
+
+    fn decode(der: &[u8]) -> anyhow::Result<Self> {
+        let mut outer = DerReader::new(der);
+        let mut tbs = outer.read_sequence()?;
+        outer.finish()?;
+
+        if let Some(version) = tbs.read_optional(context_constructed(0))? {
+            let mut v = DerReader::new(version);
+            if v.read_small_integer()? > 2 {
+                bail!("unsupported certificate version");
+            }
+            v.finish()?;
+        }
+        let serial_number = tbs.read_integer()?.to_vec();
+        let signature_algorithm = read_signature_algorithm(&mut tbs)?.to_vec();
+        let issuer = Name::decode(&mut tbs)?;
+        let mut times = tbs.read_sequence()?;
+        let validity = Validity { not_before: decode_time(&mut times)?, not_after: decode_time(&mut times)? };
+        times.finish()?;
+        let subject = Name::decode(&mut tbs)?;
+        let subject_public_key_info = read_spki(&mut tbs)?;
+        // Unique identifiers are ignored
+        tbs.read_optional(context(1))?;
+        tbs.read_optional(context(2))?;
+        let extensions = match tbs.read_optional(context_constructed(3))? {
+            Some(exts) => Extension::decode_list(&mut DerReader::new(exts))?,
+            None => Vec::new(),
+        };
+        tbs.finish()?;
+
+        Ok(TbsCertificate {
+            serial_number,
+            signature_algorithm,
+            issuer,
+            validity,
+            subject,
+            subject_public_key_info: subject_public_key_info.to_vec(),
+            extensions,
+        })
+    }
+}
+
+/// Read a SubjectPublicKeyInfo of any algorithm and return its DER.
+fn read_spki<'a>(r: &mut DerReader<'a>) -> anyhow::Result<&'a [u8]> {
+    let (tag, value, der) = r.read_raw()?;
+    if tag != SEQUENCE {
+        bail!("SubjectPublicKeyInfo is not a SEQUENCE");
+    }
+    let mut spki = DerReader::new(value);
+    let mut alg = spki.read_sequence()?;
+    alg.read_oid()?;
+    // Parameters depend on the algorithm, e.g. NULL for RSA or a curve for EC
+    if !alg.is_empty() {
+        alg.read_raw()?;
+    }
+    alg.finish()?;
+    spki.read_bit_string()?;
+    spki.finish()?;
+    Ok(der)
+}
+
+/// Read a signature AlgorithmIdentifier. Classical algorithms may carry NULL
+/// parameters, ML-DSA ones must not have any.
+fn read_signature_algorithm<'a>(r: &mut DerReader<'a>) -> anyhow::Result<&'a [u8]> {
+    let (oid, null) = r.read_algorithm_identifier_or_null()?;
+    if null && ParameterSet::from_oid(oid).is_some() {
+        bail!("ML-DSA algorithm identifiers must not have parameters");
+    }
+    Ok(oid)
+}
+
+/// Verifies certificate signatures made with algorithms other than ML-DSA,
+/// such as the RSA or ECDSA links of a mixed chain.
+pub trait SignatureVerifier {
+    /// Check `signature` over `message` for the signature algorithm OID
+    /// `algorithm` with the key in the DER SubjectPublicKeyInfo `issuer_spki`.
+    fn verify(&self, algorithm: &[u8], message: &[u8], signature: &[u8], issuer_spki: &[u8]) -> anyhow::Result<()>;
+}
+
+impl<F: Fn(&[u8], &[u8], &[u8], &[u8]) -> anyhow::Result<()>> SignatureVerifier for F {
+    fn verify(&self, algorithm: &[u8], message: &[u8], signature: &[u8], issuer_spki: &[u8]) -> anyhow::Result<()> {
+        self(algorithm, message, signature, issuer_spki)
+    }
+}
+
+/// Builder for the TBSCertificate of a new certificate. The issuer defaults
+/// to the subject, i.e. a self-signed certificate.
+#[derive(Debug, Clone)]
+pub struct CertificateBuilder {
+    serial_number: Option<Vec<u8>>,
+    issuer: Option<Name>,
+    subject: Name,
+    validity: Validity,
+    subject_public_key_info: Vec<u8>,
+    extensions: Vec<Extension>,
+}
+
+impl CertificateBuilder {
+    /// `subject_public_key_info` is the DER SubjectPublicKeyInfo of the
+    /// certified key, e.g. from `to_public_key_der`.
+    pub fn new(subject: Name, subject_public_key_info: Vec<u8>, validity: Validity) -> Self {
+        CertificateBuilder {
+            serial_number: None,
+            issuer: None,
+            subject,
+            validity,
+            subject_public_key_info,
+            extensions: Vec::new(),
+        }
+    }
+
+    /// Serial number as big-endian bytes. Defaults to 16 random bytes.
+    pub fn serial_number(mut self, serial: &[u8]) -> Self {
+        self.serial_number = Some(serial.to_vec());
+        self
+    }
+
+    pub fn issuer(mut self, issuer: Name) -> Self {
+        self.issuer = Some(issuer);
+        self
+    }
+
+    /// Take the issuer name from an issuer certificate and add an
+    /// authorityKeyIdentifier if the issuer has a subjectKeyIdentifier.
+    pub fn issued_by(mut self, issuer: &Certificate) -> anyhow::Result<Self> {
+        self.issuer = Some(issuer.tbs.subject.clone());
+        if let Some(id) = find_key_identifier(&issuer.tbs.extensions, ID_CE_SUBJECT_KEY_IDENTIFIER)? {
+            self.extensions.push(Extension::authority_key_identifier(&id));
+        }
+        Ok(self)
+    }
+
+    pub fn extension(mut self, extension: Extension) -> Self {
+        self.extensions.retain(|e| e.oid != extension.oid);
+        self.extensions.push(extension);
+        self
+    }
+
+    /// Add basicConstraints, keyUsage and subjectKeyIdentifier for a CA.
+    pub fn ca(self, path_len: Option<u64>) -> anyhow::Result<Self> {
+        let (_, key) = decode_spki(&self.subject_public_key_info)?;
+        let key_id = key_identifier(key);
+        Ok(self
+            .extension(Extension::basic_constraints(true, path_len))
+            .extension(Extension::key_usage(key_usage::KEY_CERT_SIGN | key_usage::CRL_SIGN))
+            .extension(Extension::subject_key_identifier(&key_id)))
+    }
+
+    /// Add basicConstraints, keyUsage and subjectKeyIdentifier for an end entity.
+    pub fn leaf(self) -> anyhow::Result<Self> {
+        let (_, key) = decode_spki(&self.subject_public_key_info)?;
+        let key_id = key_identifier(key);
+        Ok(self
+            .extension(Extension::basic_constraints(false, None))
+            .extension(Extension::key_usage(key_usage::DIGITAL_SIGNATURE))
+            .extension(Extension::subject_key_identifier(&key_id)))
+    }
+
+    pub fn build(self, signature_algorithm: &[u8]) -> TbsCertificate {
+        let serial_number = self.serial_number.unwrap_or_else(|| {
+            let mut serial = vec![0u8; 16];
+            randombytes(&mut serial, 16);
+            serial[0] &= 0x7f;
+            serial[0] |= 0x01;
+            serial
+        });
+        TbsCertificate {
+            serial_number,
+            signature_algorithm: signature_algorithm.to_vec(),
+            issuer: self.issuer.unwrap_or_else(|| self.subject.clone()),
+            validity: self.validity,
+            subject: self.subject,
+            subject_public_key_info: self.subject_public_key_info,
+            extensions: self.extensions,
+        }
+    }
+}
+
+/// A signed certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Certificate {
+    pub tbs: TbsCertificate,
+    pub signature_algorithm: Vec<u8>,
+    pub signature: Vec<u8>,
+    tbs_der: Vec<u8>,
+    der: Vec<u8>,
+}
+
+impl Certificate {
+    fn from_parts(tbs_der: Vec<u8>, signature_algorithm: Vec<u8>, signature: Vec<u8>) -> anyhow::Result<Self> {
+        let der = sequence(&[&tbs_der, &algorithm_identifier(&signature_algorithm), &bit_string(&signature)]);
+        Certificate::from_der(&der)
+    }
+
+    pub fn from_der(der: &[u8]) -> anyhow::Result<Self> {
+        let mut outer = DerReader::new(der);
+        let mut cert = outer.read_sequence()?;
+        outer.finish()?;
+        let (_, _, tbs_der) = cert.read_raw()?;
+        let signature_algorithm = read_signature_algorithm(&mut cert)?.to_vec();
+        let signature = cert.read_bit_string()?.to_vec();
+        cert.finish()?;
+
+        let tbs = TbsCertificate::decode(tbs_der)?;
+        if tbs.signature_algorithm != signature_algorithm {
+            bail!("signature algorithm does not match the TBSCertificate");
+        }
+        Ok(Certificate { tbs, signature_algorithm, signature, tbs_der: tbs_der.to_vec(), der: der.to_vec() })
+    }
+
+    #[cfg(feature = "pem")]
+    pub fn from_pem(pem: &str) -> anyhow::Result<Self> {
+        Certificate::from_der(&pem_decode(PEM_LABEL, pem)?)
+    }
+
+    pub fn to_der(&self) -> Vec<u8> {
+        self.der.clone()
+    }
+
+    #[cfg(feature = "pem")]
+    pub fn to_pem(&self) -> String {
+        pem_encode(PEM_LABEL, &self.der)
+    }
+
+    /// The exact DER of the signed TBSCertificate.
+    pub fn tbs_der(&self) -> &[u8] {
+        &self.tbs_der
+    }
+
+    pub fn basic_constraints(&self) -> anyhow::Result<Option<BasicConstraints>> {
+        find_basic_constraints(&self.tbs.extensions)
+    }
+
+    pub fn key_usage(&self) -> anyhow::Result<Option<u16>> {
+        find_key_usage(&self.tbs.extensions)
+    }
+
+    /// Check the signature against the issuer's DER SubjectPublicKeyInfo.
+    /// The parameter set is chosen by the signature algorithm, and must
+    /// match the issuer key's algorithm.
+    pub fn verify_signature(&self, issuer_spki: &[u8]) -> anyhow::Result<()> {
+        let (algorithm, key) = decode_spki(issuer_spki)?;
+        let set = ParameterSet::from_oid(&self.signature_algorithm)
+            .ok_or_else(|| anyhow!("unsupported signature algorithm {}", oid_to_string(&self.signature_algorithm)))?;
+        if algorithm != set.oid() {
+            bail!("issuer key algorithm {} does not match the signature algorithm", oid_to_string(algorithm));
+        }
+        set.verify(self.tbs_der.clone(), self.signature.clone(), key.to_vec(), None)
+            .map_err(|e| anyhow!("invalid certificate signature: {:?}", e))
+    }
+
+    /// Check the signature against the issuer's DER SubjectPublicKeyInfo,
+    /// passing signatures that are not ML-DSA to `classical`.
+    pub fn verify_signature_with(&self, issuer_spki: &[u8], classical: &dyn SignatureVerifier) -> anyhow::Result<()> {
+        if ParameterSet::from_oid(&self.signature_algorithm).is_some() {
+            return self.verify_signature(issuer_spki);
+        }
+        classical.verify(&self.signature_algorithm, &self.tbs_der, &self.signature, issuer_spki)
+    }
+
+    /// Check the signature against the public key of an issuer certificate.
+    pub fn verify_issued_by(&self, issuer: &Certificate) -> anyhow::Result<()> {
+        self.verify_signature(&issuer.tbs.subject_public_key_info)
+    }
+}
+
+impl<
+    const K: usize,
+    const L: usize,
+    const ETA: usize,
+    const TAU: usize,
+    const BETA: usize,
+    const GAMMA1: usize,
+    const GAMMA2: usize,
+    const OMEGA: usize,
+    const PUBLICKEYBYTES: usize,
+    const SECRETKEYBYTES: usize,
+    const POLY_UNIFORM_NBLOCKS: usize,
+    S: StreamBackend,
+    const POLY_UNIFORM_ETA_NBLOCKS: usize,
+    const POLYETA_PACKEDBYTES: usize,
+    const SIGNBYTES: usize,
+    const POLYW1_PACKEDBYTES: usize,
+    const CTILDEBYTES: usize,
+    const POLYZ_PACKEDBYTES: usize,
+    const POLYVECH_PACKEDBYTES: usize,
+    const POLY_UNIFORM_GAMMA1_NBLOCKS: usize,
+>
+Dilithium<
+    K,
+    L,
+    ETA,
+    TAU,
+    BETA,
+    GAMMA1,
+    GAMMA2,
+    OMEGA,
+    PUBLICKEYBYTES,
+    SECRETKEYBYTES,
+    POLY_UNIFORM_NBLOCKS,
+    S,
+    POLY_UNIFORM_ETA_NBLOCKS,
+    POLYETA_PACKEDBYTES,
+    SIGNBYTES,
+    POLYW1_PACKEDBYTES,
+    CTILDEBYTES,
+    POLYZ_PACKEDBYTES,
+    POLYVECH_PACKEDBYTES,
+    POLY_UNIFORM_GAMMA1_NBLOCKS,
+>
+{
+    /// Sign a certificate with the issuer's secret key of this parameter set.
+    pub fn sign_certificate(builder: CertificateBuilder, issuer_sk: &[u8]) -> anyhow::Result<Certificate> {
+        let set = Self::parameter_set().ok_or_else(|| anyhow!("parameters do not match an ML-DSA parameter set"))?;
+        if issuer_sk.len() != SECRETKEYBYTES {
+            bail!("secret key must be {} bytes", SECRETKEYBYTES);
+        }
+        let tbs_der = builder.build(set.oid()).to_der();
+        let signature = Self::sign(tbs_der.clone(), issuer_sk.to_vec(), None, true)?;
+        Certificate::from_parts(tbs_der, set.oid().to_vec(), signature)
+    }
+
+    /// Verify a certificate's signature with the issuer's packed public key.
+    pub fn verify_certificate(cert: &Certificate, issuer_pk: &[u8]) -> anyhow::Result<()> {
+        let set = Self::parameter_set().ok_or_else(|| anyhow!("parameters do not match an ML-DSA parameter set"))?;
+        if cert.signature_algorithm != set.oid() {
+            bail!("certificate is signed with {}", oid_to_string(&cert.signature_algorithm));
+        }
+        Self::verify(cert.tbs_der.clone(), cert.signature.clone(), issuer_pk.to_vec(), None)
+            .map_err(|e| anyhow!("invalid certificate signature: {:?}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dilithium2, Dilithium5};
+
+    #[test]
+    fn time_encoding() {
+        // 2024-02-29 12:34:56
+        let t = 1709210096;
+        assert_eq!(encode_time(t), tlv(UTC_TIME, b"240229123456Z"));
+        assert_eq!(decode_time(&mut DerReader::new(&encode_time(t))).unwrap(), t);
+        // 2050-01-01 00:00:00
+        let t = 2524608000;
+        assert_eq!(encode_time(t), tlv(GENERALIZED_TIME, b"20500101000000Z"));
+        assert_eq!(decode_time(&mut DerReader::new(&encode_time(t))).unwrap(), t);
+
+        let t = Validity::NO_EXPIRATION;
+        assert_eq!(encode_time(t), tlv(GENERALIZED_TIME, b"99991231235959Z"));
+        assert_eq!(Validity::for_days(u64::MAX).not_after, t);
+        assert_eq!(Validity::for_days(u64::MAX / 86400).not_after, t);
+    }
+
+    #[test]
+    fn pre_1970_times() {
+        // 1969-12-31 23:59:59 and 1950-01-01 00:00:00
+        for (t, der) in [(-1, tlv(UTC_TIME, b"691231235959Z")), (-631152000, tlv(UTC_TIME, b"500101000000Z"))] {
+            assert_eq!(encode_time(t), der);
+            assert_eq!(decode_time(&mut DerReader::new(&der)).unwrap(), t);
+        }
+        // 1900-03-01 00:00:00
+        let t = -2203891200;
+        assert_eq!(encode_time(t), tlv(GENERALIZED_TIME, b"19000301000000Z"));
+        assert_eq!(decode_time(&mut DerReader::new(&encode_time(t))).unwrap(), t);
+    }
+
+    #[test]
+    fn strict_tbs_decoding() {
+        let (_, pk) = Dilithium2::key_gen(None).unwrap();
+        let spki = Dilithium2::to_public_key_der(&pk).unwrap();
+        let tbs = CertificateBuilder::new(Name::new().common_name("Test"), spki.clone(), Validity::for_days(1)).build(ID_ML_DSA_44);
+        let times = [encode_time(tbs.validity.not_before), encode_time(tbs.validity.not_after)];
+        let with = |validity: &[u8], spki: &[u8]| {
+            sequence(&[
+                &tlv(context_constructed(0), &small_integer(2)),
+                &integer(&tbs.serial_number),
+                &algorithm_identifier(ID_ML_DSA_44),
+                &tbs.issuer.to_der(),
+                validity,
+                &tbs.subject.to_der(),
+                spki,
+            ])
+        };
+        assert_eq!(TbsCertificate::decode(&with(&sequence(&[&times[0], &times[1]]), &spki)).unwrap(), tbs);
+        // A third time in the Validity
+        assert!(TbsCertificate::decode(&with(&sequence(&[&times[0], &times[1], &times[1]]), &spki)).is_err());
+        // A SubjectPublicKeyInfo that is not a SEQUENCE, or has trailing data
+        let mut not_sequence = spki.clone();
+        not_sequence[0] = SET;
+        assert!(TbsCertificate::decode(&with(&sequence(&[&times[0], &times[1]]), &not_sequence)).is_err());
+        let mut reader = DerReader::new(&spki);
+        let inner = reader.read(SEQUENCE).unwrap();
+        let trailing = sequence(&[inner, &[NULL, 0]]);
+        assert!(TbsCertificate::decode(&with(&sequence(&[&times[0], &times[1]]), &trailing)).is_err());
+    }
+
+    #[test]
+    fn key_usage_bits() {
+        // digitalSignature only: one byte, seven unused bits
+        assert_eq!(named_bits(key_usage::DIGITAL_SIGNATURE), [0x03, 0x02, 0x07, 0x80]);
+        // keyCertSign | cRLSign
+        assert_eq!(named_bits(key_usage::KEY_CERT_SIGN | key_usage::CRL_SIGN), [0x03, 0x02, 0x01, 0x06]);
+        let bits = key_usage::DIGITAL_SIGNATURE | key_usage::DECIPHER_ONLY;
+        assert_eq!(decode_named_bits(&named_bits(bits)).unwrap(), bits);
+    }
+
+    #[test]
+    fn self_signed_root_and_leaf() {
+        let (root_sk, root_pk) = Dilithium5::key_gen(None).unwrap();
+        let root_name = Name::new().country("DE").organization("Example").common_name("Example Root");
+        let builder = CertificateBuilder::new(root_name.clone(), Dilithium5::to_public_key_der(&root_pk).unwrap(), Validity::for_days(3650))
+            .ca(Some(1))
+            .unwrap();
+        let root = Dilithium5::sign_certificate(builder, &root_sk).unwrap();
+        assert_eq!(root.tbs.issuer, root_name);
+        assert_eq!(root.signature_algorithm, ID_ML_DSA_87);
+        Dilithium5::verify_certificate(&root, &root_pk).unwrap();
+        root.verify_issued_by(&root).unwrap();
+        assert_eq!(root.basic_constraints().unwrap(), Some(BasicConstraints { ca: true, path_len: Some(1) }));
+
+        let (_, leaf_pk) = Dilithium2::key_gen(None).unwrap();
+        let builder = CertificateBuilder::new(Name::new().common_name("leaf.example"), Dilithium2::to_public_key_der(&leaf_pk).unwrap(), Validity::for_days(90))
+            .issued_by(&root)
+            .unwrap()
+            .leaf()
+            .unwrap()
+            .extension(Extension::subject_alt_name_dns(&["leaf.example"]));
+        let leaf = Dilithium5::sign_certificate(builder, &root_sk).unwrap();
+        leaf.verify_issued_by(&root).unwrap();
+        assert!(leaf.verify_issued_by(&leaf).is_err());
+        assert_eq!(leaf.key_usage().unwrap(), Some(key_usage::DIGITAL_SIGNATURE));
+        assert_eq!(leaf.tbs.issuer.to_string(), "C=DE, O=Example, CN=Example Root");
+        assert_eq!(
+            find_key_identifier(&leaf.tbs.extensions, ID_CE_AUTHORITY_KEY_IDENTIFIER).unwrap(),
+            Some(key_identifier(&root_pk))
+        );
+
+        // DER and PEM round trip to the same certificate
+        let parsed = Certificate::from_der(&leaf.to_der()).unwrap();
+        #[cfg(feature = "pem")]
+        assert_eq!(Certificate::from_pem(&leaf.to_pem()).unwrap(), parsed);
+        assert_eq!(parsed, leaf);
+        assert_eq!(parsed.tbs.to_der(), parsed.tbs_der());
+
+        // Any change to the TBSCertificate breaks the signature
+        let mut der = leaf.to_der();
+        let pos = der.windows(12).position(|w| w == b"leaf.example").unwrap();
+        der[pos] = b'L';
+        let tampered = Certificate::from_der(&der).unwrap();
+        assert!(tampered.verify_issued_by(&root).is_err());
+    }
+}
@@ -0,0 +1,271 @@
+//! ML-DSA against the NIST ACVP server's sample vectors, cross-checked
+//! against vectors generated with OpenSSL 3.5.
+//!
+//! `tests/acvp/nist` holds a subset of the server's
+//! `gen-val/json-files/ML-DSA-{keyGen,sigGen,sigVer}-FIPS204` files, written
+//! by `tests/acvp/fetch.py`. `tests/acvp/openssl` holds the OpenSSL vectors in
+//! the same layout; see `tests/acvp/README.md`. The NIST tests are skipped
+//! with a note if their files are missing, unless `ACVP_REQUIRE_NIST` is set,
+//! as it is in CI.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use dilithium_lib::{ParameterSet, PreHashAlgorithm};
+use serde_json::Value;
+use sha2::Digest;
+
+/// Test groups paired with the expected results of their tests keyed by tcId.
+type Groups = Vec<(Value, HashMap<u64, Value>)>;
+
+/// Test groups of a prompt under `tests/acvp/<source>`, or `None` if the
+/// source has no files for `mode`.
+fn load(source: &str, mode: &str) -> Option<Groups> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/acvp").join(source).join(format!("ML-DSA-{}-FIPS204", mode));
+    if !dir.is_dir() {
+        return None;
+    }
+    let read = |name: &str| -> Value {
+        let text = std::fs::read_to_string(dir.join(name)).unwrap();
+        serde_json::from_str(&text).unwrap()
+    };
+    let (prompt, expected) = (read("prompt.json"), read("expectedResults.json"));
+    assert_eq!(prompt["mode"], mode);
+    assert_eq!(prompt["revision"], "FIPS204");
+
+    let mut results = HashMap::new();
+    for group in expected["testGroups"].as_array().unwrap() {
+        for test in group["tests"].as_array().unwrap() {
+            results.insert(test["tcId"].as_u64().unwrap(), test.clone());
+        }
+    }
+    let groups = prompt["testGroups"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|group| {
+            let expected = group["tests"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|test| {
+                    let id = test["tcId"].as_u64().unwrap();
+                    (id, results.remove(&id).unwrap_or_else(|| panic!("no expected result for tcId {}", id)))
+                })
+                .collect();
+            (group.clone(), expected)
+        })
+        .collect();
+    Some(groups)
+}
+
+fn nist(mode: &str) -> Option<Groups> {
+    let groups = load("nist", mode);
+    if groups.is_none() {
+        assert!(std::env::var_os("ACVP_REQUIRE_NIST").is_none(), "no NIST {} vectors, run tests/acvp/fetch.py", mode);
+        eprintln!("skipping NIST {} vectors: run tests/acvp/fetch.py to download them", mode);
+    }
+    groups
+}
+
+fn openssl(mode: &str) -> Groups {
+    load("openssl", mode).unwrap()
+}
+
+fn hex(value: &Value) -> Vec<u8> {
+    let s = value.as_str().unwrap_or_else(|| panic!("expected a hex string, got {}", value));
+    assert_eq!(s.len() % 2, 0, "odd-length hex string");
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+}
+
+fn parameter_set(group: &Value) -> ParameterSet {
+    ParameterSet::from_name(group["parameterSet"].as_str().unwrap()).unwrap()
+}
+
+fn digest(hash: PreHashAlgorithm, m: &[u8]) -> Vec<u8> {
+    use sha3::digest::{ExtendableOutput, Update, XofReader};
+
+    fn xof<H: Default + Update + ExtendableOutput>(m: &[u8], len: usize) -> Vec<u8> {
+        let mut h = H::default();
+        h.update(m);
+        let mut out = vec![0u8; len];
+        h.finalize_xof().read(&mut out);
+        out
+    }
+
+    match hash {
+        PreHashAlgorithm::Sha2_224 => sha2::Sha224::digest(m).to_vec(),
+        PreHashAlgorithm::Sha2_256 => sha2::Sha256::digest(m).to_vec(),
+        PreHashAlgorithm::Sha2_384 => sha2::Sha384::digest(m).to_vec(),
+        PreHashAlgorithm::Sha2_512 => sha2::Sha512::digest(m).to_vec(),
+        PreHashAlgorithm::Sha2_512_224 => sha2::Sha512_224::digest(m).to_vec(),
+        PreHashAlgorithm::Sha2_512_256 => sha2::Sha512_256::digest(m).to_vec(),
+        PreHashAlgorithm::Sha3_224 => sha3::Sha3_224::digest(m).to_vec(),
+        PreHashAlgorithm::Sha3_256 => sha3::Sha3_256::digest(m).to_vec(),
+        PreHashAlgorithm::Sha3_384 => sha3::Sha3_384::digest(m).to_vec(),
+        PreHashAlgorithm::Sha3_512 => sha3::Sha3_512::digest(m).to_vec(),
+        PreHashAlgorithm::Shake128 => xof::<sha3::Shake128>(m, 32),
+        PreHashAlgorithm::Shake256 => xof::<sha3::Shake256>(m, 64),
+    }
+}
+
+/// Signature interface of a sigGen or sigVer group.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Interface {
+    Pure,
+    PreHash,
+    Internal,
+    ExternalMu,
+}
+
+fn interface(group: &Value) -> Interface {
+    match (group["signatureInterface"].as_str(), group["preHash"].as_str(), group["externalMu"].as_bool()) {
+        (Some("external"), Some("pure"), _) => Interface::Pure,
+        (Some("external"), Some("preHash"), _) => Interface::PreHash,
+        (Some("internal"), _, Some(true)) => Interface::ExternalMu,
+        (Some("internal"), _, _) => Interface::Internal,
+        _ => panic!("unsupported test group {}", group["tgId"]),
+    }
+}
+
+fn hash_alg(test: &Value) -> PreHashAlgorithm {
+    PreHashAlgorithm::from_name(test["hashAlg"].as_str().unwrap()).unwrap()
+}
+
+/// The formatted message M' of the external interface.
+fn external_message(interface: Interface, test: &Value) -> Vec<u8> {
+    let (ctx, m) = (hex(&test["context"]), hex(&test["message"]));
+    match interface {
+        Interface::Pure => [&[0u8, ctx.len() as u8][..], &ctx, &m].concat(),
+        _ => {
+            let hash = hash_alg(test);
+            let oid = [&[0x06, 0x09][..], &hash.oid()].concat();
+            [&[1u8, ctx.len() as u8][..], &ctx, &oid, &digest(hash, &m)].concat()
+        }
+    }
+}
+
+/// Run keyGen groups, returning the parameter sets covered.
+fn check_key_gen(groups: Groups) -> HashSet<ParameterSet> {
+    let mut covered = HashSet::new();
+    for (group, expected) in groups {
+        let ps = parameter_set(&group);
+        for test in group["tests"].as_array().unwrap() {
+            let result = &expected[&test["tcId"].as_u64().unwrap()];
+            let (sk, pk) = ps.key_gen(Some(hex(&test["seed"]))).unwrap();
+            assert_eq!(pk, hex(&result["pk"]), "tcId {}", test["tcId"]);
+            assert_eq!(sk, hex(&result["sk"]), "tcId {}", test["tcId"]);
+            covered.insert(ps);
+        }
+    }
+    covered
+}
+
+/// Run sigGen groups, returning the number of tests per parameter set,
+/// interface and signing mode.
+fn check_sig_gen(groups: Groups) -> HashMap<(ParameterSet, Interface, bool), usize> {
+    let mut covered = HashMap::new();
+    for (group, expected) in groups {
+        let ps = parameter_set(&group);
+        let interface = interface(&group);
+        let deterministic = group["deterministic"].as_bool().unwrap();
+        for test in group["tests"].as_array().unwrap() {
+            let id = test["tcId"].as_u64().unwrap();
+            let sk = hex(&test["sk"]);
+            let rnd = if deterministic { vec![0u8; 32] } else { hex(&test["rnd"]) };
+            let sig = match interface {
+                // Deterministic external signatures go through the public API;
+                // hedged ones need the given rnd, so sign M' internally.
+                Interface::Pure if deterministic => {
+                    ps.sign(hex(&test["message"]), sk, Some(hex(&test["context"])), false).unwrap()
+                }
+                Interface::PreHash if deterministic => {
+                    let hash = hash_alg(test);
+                    let digest = digest(hash, &hex(&test["message"]));
+                    ps.sign_prehash(digest, hash, sk, Some(hex(&test["context"])), false).unwrap()
+                }
+                Interface::Pure | Interface::PreHash => {
+                    ps.sign_internal(external_message(interface, test), sk, rnd).unwrap()
+                }
+                Interface::Internal => ps.sign_internal(hex(&test["message"]), sk, rnd).unwrap(),
+                Interface::ExternalMu => ps.sign_mu(hex(&test["mu"]), sk, rnd).unwrap(),
+            };
+            assert_eq!(sig, hex(&expected[&id]["signature"]), "tcId {}", id);
+            *covered.entry((ps, interface, deterministic)).or_insert(0) += 1;
+        }
+    }
+    covered
+}
+
+/// Run sigVer groups, returning the number of passing and failing tests.
+fn check_sig_ver(groups: Groups) -> (usize, usize) {
+    let (mut passed, mut failed) = (0, 0);
+    for (group, expected) in groups {
+        let ps = parameter_set(&group);
+        let interface = interface(&group);
+        for test in group["tests"].as_array().unwrap() {
+            let id = test["tcId"].as_u64().unwrap();
+            let (pk, sig) = (hex(&test["pk"]), hex(&test["signature"]));
+            let result = match interface {
+                Interface::Pure => ps.verify(hex(&test["message"]), sig, pk, Some(hex(&test["context"]))),
+                Interface::PreHash => {
+                    let hash = hash_alg(test);
+                    let digest = digest(hash, &hex(&test["message"]));
+                    ps.verify_prehash(digest, hash, sig, pk, Some(hex(&test["context"])))
+                }
+                Interface::Internal => ps.verify_internal(hex(&test["message"]), sig, pk),
+                Interface::ExternalMu => ps.verify_mu(hex(&test["mu"]), sig, pk),
+            };
+            let expected = expected[&id]["testPassed"].as_bool().unwrap();
+            assert_eq!(result.is_ok(), expected, "tcId {}", id);
+            if expected {
+                passed += 1;
+            } else {
+                failed += 1;
+            }
+        }
+    }
+    (passed, failed)
+}
+
+#[test]
+fn nist_key_gen() {
+    if let Some(groups) = nist("keyGen") {
+        assert_eq!(check_key_gen(groups).len(), 3);
+    }
+}
+
+#[test]
+fn nist_sig_gen() {
+    if let Some(groups) = nist("sigGen") {
+        let covered = check_sig_gen(groups);
+        // Deterministic and hedged signing for every parameter set
+        let modes: HashSet<_> = covered.keys().map(|&(ps, _, deterministic)| (ps, deterministic)).collect();
+        assert_eq!(modes.len(), 3 * 2);
+    }
+}
+
+#[test]
+fn nist_sig_ver() {
+    if let Some(groups) = nist("sigVer") {
+        let (passed, failed) = check_sig_ver(groups);
+        assert!(passed > 0 && failed > 0);
+    }
+}
+
+#[test]
+fn openssl_key_gen() {
+    assert_eq!(check_key_gen(openssl("keyGen")).len(), 3);
+}
+
+#[test]
+fn openssl_sig_gen() {
+    // Every parameter set, interface and signing mode is exercised
+    assert_eq!(check_sig_gen(openssl("sigGen")).len(), 3 * 4 * 2);
+}
+
+#[test]
+fn openssl_sig_ver() {
+    let (passed, failed) = check_sig_ver(openssl("sigVer"));
+    assert!(passed > 0 && failed > 0);
+}
@@ -0,0 +1,69 @@
+#!/usr/bin/env python3
+"""Vendor a subset of the NIST ACVP server's ML-DSA sample vectors.
+
+Downloads prompt.json and expectedResults.json of ML-DSA-keyGen, sigGen and
+sigVer (revision FIPS204) from gen-val/json-files in usnistgov/ACVP-Server
+and writes the first few tests of every test group to tests/acvp/nist:
+
+    python3 tests/acvp/fetch.py [git ref, default master]
+
+Every group is kept, so the subset still covers all parameter sets,
+interfaces, deterministic and hedged signing, and sigVer cases that must
+fail. If a hedged sigGen prompt leaves out rnd, it is taken from the
+server's internalProjection.json.
+"""
+
+import json
+import os
+import sys
+import urllib.request
+
+OUT = os.path.join(os.path.dirname(os.path.abspath(__file__)), "nist")
+REF = sys.argv[1] if len(sys.argv) > 1 else "master"
+URL = "https://raw.githubusercontent.com/usnistgov/ACVP-Server/%s/gen-val/json-files/%s/%s"
+PER_GROUP = 3
+
+
+def fetch(directory, name):
+    with urllib.request.urlopen(URL % (REF, directory, name)) as f:
+        return json.load(f)
+
+
+def tests_by_id(doc):
+    return {t["tcId"]: t for g in doc["testGroups"] for t in g["tests"]}
+
+
+def subset(mode):
+    directory = "ML-DSA-%s-FIPS204" % mode
+    prompt, expected = fetch(directory, "prompt.json"), fetch(directory, "expectedResults.json")
+    projection = fetch(directory, "internalProjection.json") if mode == "sigGen" else None
+
+    keep = set()
+    for group in prompt["testGroups"]:
+        if mode == "sigVer":
+            # Keep passing and failing cases alike
+            results = tests_by_id(expected)
+            passing = [t for t in group["tests"] if results[t["tcId"]]["testPassed"]]
+            failing = [t for t in group["tests"] if not results[t["tcId"]]["testPassed"]]
+            group["tests"] = passing[:PER_GROUP] + failing[:PER_GROUP]
+        else:
+            group["tests"] = group["tests"][:PER_GROUP]
+        if projection is not None and not group.get("deterministic", True):
+            rnd = {t["tcId"]: t.get("rnd") for t in tests_by_id(projection).values()}
+            for test in group["tests"]:
+                test.setdefault("rnd", rnd[test["tcId"]])
+        keep.update(t["tcId"] for t in group["tests"])
+    for group in expected["testGroups"]:
+        group["tests"] = [t for t in group["tests"] if t["tcId"] in keep]
+
+    path = os.path.join(OUT, directory)
+    os.makedirs(path, exist_ok=True)
+    for name, doc in [("prompt.json", prompt), ("expectedResults.json", expected)]:
+        with open(os.path.join(path, name), "w") as f:
+            json.dump(doc, f, indent=2)
+            f.write("\n")
+    print("%s: %d tests" % (directory, len(keep)))
+
+
+for mode in ["keyGen", "sigGen", "sigVer"]:
+    subset(mode)
@@ -0,0 +1,220 @@
+#!/usr/bin/env python3
+"""Generate ML-DSA test vectors with OpenSSL (>= 3.5) to cross-check against.
+
+Writes prompt.json and expectedResults.json for ML-DSA-keyGen, sigGen and
+sigVer in the file layout of the NIST ACVP server (revision FIPS204). These
+are not ACVP server vectors: the ACVP session metadata (vsId, isSample) is
+left out and a "generator" field names their source instead. All inputs
+are derived from a fixed seed, so the output is reproducible:
+
+    python3 tests/acvp/openssl/generate.py
+"""
+
+import hashlib
+import json
+import os
+import random
+import re
+import subprocess
+import tempfile
+
+OUT = os.path.dirname(os.path.abspath(__file__))
+GENERATOR = "OpenSSL 3.5 via tests/acvp/openssl/generate.py, not the ACVP server"
+SETS = ["ML-DSA-44", "ML-DSA-65", "ML-DSA-87"]
+HASHES = {
+    "SHA2-224": (4, lambda m: hashlib.sha224(m).digest()),
+    "SHA2-256": (1, lambda m: hashlib.sha256(m).digest()),
+    "SHA2-384": (2, lambda m: hashlib.sha384(m).digest()),
+    "SHA2-512": (3, lambda m: hashlib.sha512(m).digest()),
+    "SHA2-512/224": (5, lambda m: hashlib.new("sha512_224", m).digest()),
+    "SHA2-512/256": (6, lambda m: hashlib.new("sha512_256", m).digest()),
+    "SHA3-224": (7, lambda m: hashlib.sha3_224(m).digest()),
+    "SHA3-256": (8, lambda m: hashlib.sha3_256(m).digest()),
+    "SHA3-384": (9, lambda m: hashlib.sha3_384(m).digest()),
+    "SHA3-512": (10, lambda m: hashlib.sha3_512(m).digest()),
+    "SHAKE-128": (11, lambda m: hashlib.shake_128(m).digest(32)),
+    "SHAKE-256": (12, lambda m: hashlib.shake_256(m).digest(64)),
+}
+
+rng = random.Random(204)
+tmp = tempfile.mkdtemp()
+
+
+def rand(n):
+    return bytes(rng.getrandbits(8) for _ in range(n))
+
+
+def hx(b):
+    return b.hex().upper()
+
+
+def openssl(*args, data=None):
+    return subprocess.run(["openssl", *args], input=data, capture_output=True, check=True).stdout
+
+
+def key_gen(ps, seed):
+    key = os.path.join(tmp, "key.pem")
+    openssl("genpkey", "-algorithm", ps, "-pkeyopt", "hexseed:" + seed.hex(), "-out", key)
+    text = openssl("pkey", "-in", key, "-text", "-noout").decode()
+    fields = {}
+    for name, body in re.findall(r"^(\w+):\n((?:\s+[0-9a-f:]+\n)+)", text, re.M):
+        fields[name] = bytes.fromhex(re.sub(r"[\s:]", "", body))
+    return key, fields["pub"], fields["priv"]
+
+
+def sign(key, data, opts):
+    msg = os.path.join(tmp, "msg.bin")
+    with open(msg, "wb") as f:
+        f.write(data)
+    args = ["pkeyutl", "-sign", "-rawin", "-inkey", key, "-in", msg]
+    for o in opts:
+        args += ["-pkeyopt", o]
+    return openssl(*args)
+
+
+def verify(key, data, sig, opts):
+    msg, sigf = os.path.join(tmp, "msg.bin"), os.path.join(tmp, "sig.bin")
+    for path, content in ((msg, data), (sigf, sig)):
+        with open(path, "wb") as f:
+            f.write(content)
+    args = ["pkeyutl", "-verify", "-rawin", "-inkey", key, "-in", msg, "-sigfile", sigf]
+    for o in opts:
+        args += ["-pkeyopt", o]
+    return subprocess.run(["openssl", *args], capture_output=True).returncode == 0
+
+
+def prehash_message(ctx, hash_alg, message):
+    arc, digest = HASHES[hash_alg]
+    oid = bytes([0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, arc])
+    return bytes([1, len(ctx)]) + ctx + oid + digest(message)
+
+
+def sign_opts(group, test, rnd):
+    """OpenSSL options and input that sign `test` the way `group` asks."""
+    opts = ["deterministic:1"] if rnd is None else ["hextest-entropy:" + rnd.hex()]
+    if group.get("externalMu"):
+        return opts + ["mu:1"], test["mu"]
+    if group["signatureInterface"] == "internal":
+        return opts + ["message-encoding:0"], test["message"]
+    if group["preHash"] == "preHash":
+        return opts + ["message-encoding:0"], prehash_message(test["context"], test["hashAlg"], test["message"])
+    if test["context"]:
+        opts.append("hexcontext-string:" + test["context"].hex())
+    return opts, test["message"]
+
+
+def test_fields(group, hash_cycle):
+    test = {}
+    if group.get("externalMu"):
+        test["mu"] = rand(64)
+    else:
+        test["message"] = rand(rng.randrange(0, 128))
+    if group["signatureInterface"] == "external":
+        test["context"] = rand(rng.choice([0, 1, 16, 255]))
+        if group["preHash"] == "preHash":
+            test["hashAlg"] = next(hash_cycle)
+    return test
+
+
+def encode(test):
+    return {k: (hx(v) if isinstance(v, bytes) else v) for k, v in test.items()}
+
+
+def document(mode, groups):
+    return {"generator": GENERATOR, "algorithm": "ML-DSA", "mode": mode, "revision": "FIPS204", "testGroups": groups}
+
+
+def write(mode, prompt, expected):
+    path = os.path.join(OUT, "ML-DSA-%s-FIPS204" % mode)
+    os.makedirs(path, exist_ok=True)
+    for name, groups in (("prompt.json", prompt), ("expectedResults.json", expected)):
+        with open(os.path.join(path, name), "w") as f:
+            json.dump(document(mode, groups), f, indent=2)
+            f.write("\n")
+
+
+VARIANTS = [
+    {"signatureInterface": "external", "preHash": "pure"},
+    {"signatureInterface": "external", "preHash": "preHash"},
+    {"signatureInterface": "internal", "preHash": "pure", "externalMu": False},
+    {"signatureInterface": "internal", "preHash": "pure", "externalMu": True},
+]
+
+
+def gen_key_gen():
+    prompt, expected, tc = [], [], 1
+    for tg, ps in enumerate(SETS, 1):
+        tests, results = [], []
+        for _ in range(3):
+            seed = rand(32)
+            _, pk, sk = key_gen(ps, seed)
+            tests.append({"tcId": tc, "seed": hx(seed)})
+            results.append({"tcId": tc, "pk": hx(pk), "sk": hx(sk)})
+            tc += 1
+        prompt.append({"tgId": tg, "testType": "AFT", "parameterSet": ps, "tests": tests})
+        expected.append({"tgId": tg, "tests": results})
+    write("keyGen", prompt, expected)
+
+
+def gen_sig_gen():
+    hash_cycle = iter(list(HASHES) * 2)
+    prompt, expected, tg, tc = [], [], 1, 1
+    for ps in SETS:
+        for variant in VARIANTS:
+            for deterministic in (True, False):
+                group = dict(variant, deterministic=deterministic)
+                tests, results = [], []
+                for _ in range(2 if variant["preHash"] == "preHash" else 1):
+                    key, _, sk = key_gen(ps, rand(32))
+                    test = test_fields(group, hash_cycle)
+                    rnd = None if deterministic else rand(32)
+                    opts, data = sign_opts(group, test, rnd)
+                    sig = sign(key, data, opts)
+                    test = dict(tcId=tc, sk=sk, **test)
+                    if rnd is not None:
+                        test["rnd"] = rnd
+                    tests.append(encode(test))
+                    results.append({"tcId": tc, "signature": hx(sig)})
+                    tc += 1
+                prompt.append(dict(tgId=tg, testType="AFT", parameterSet=ps, **group, tests=tests))
+                expected.append({"tgId": tg, "tests": results})
+                tg += 1
+    write("sigGen", prompt, expected)
+
+
+def gen_sig_ver():
+    hash_cycle = iter(list(HASHES) * 2)
+    prompt, expected, tg, tc = [], [], 1, 1
+    for ps in SETS:
+        for variant in VARIANTS:
+            tests, results = [], []
+            corrupted = "modified signature" if tg % 2 else "modified hint"
+            for case in ("valid", "modified message", corrupted):
+                key, pk, _ = key_gen(ps, rand(32))
+                test = test_fields(variant, hash_cycle)
+                opts, data = sign_opts(variant, test, rand(32))
+                sig = bytearray(sign(key, data, opts))
+                field = "mu" if variant.get("externalMu") else "message"
+                if case == "modified message":
+                    test[field] = bytes([test[field][0] ^ 1]) + test[field][1:] if test[field] else b"\x00"
+                elif case == "modified signature":
+                    sig[len(sig) // 2] ^= 0x10
+                elif case == "modified hint":
+                    sig[-1] = 0xff
+                test = dict(tcId=tc, pk=pk, **test, signature=bytes(sig))
+                # Cross-check the expected outcome with OpenSSL
+                opts, data = sign_opts(variant, test, None)
+                passed = verify(key, data, bytes(sig), [o for o in opts if not o.startswith("deterministic")])
+                assert passed == (case == "valid"), (ps, variant, case)
+                tests.append(encode(test))
+                results.append({"tcId": tc, "testPassed": passed})
+                tc += 1
+            prompt.append(dict(tgId=tg, testType="AFT", parameterSet=ps, **variant, tests=tests))
+            expected.append({"tgId": tg, "tests": results})
+            tg += 1
+    write("sigVer", prompt, expected)
+
+
+gen_key_gen()
+gen_sig_gen()
+gen_sig_ver()
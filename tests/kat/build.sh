@@ -0,0 +1,22 @@
+#!/bin/sh
+# Build the reference implementations' PQCgenKAT_sign and write their
+# request and response files next to this script, for tests/kat.rs.
+# Needs git, make, a C compiler and the OpenSSL headers (for the DRBG).
+set -eu
+cd "$(dirname "$0")"
+work=$(mktemp -d)
+trap 'rm -rf "$work"' EXIT
+
+# ML-DSA (FIPS 204)
+git clone --quiet --depth 1 https://github.com/pq-crystals/dilithium "$work/ml-dsa"
+make -s -C "$work/ml-dsa/ref" nistkat/PQCgenKAT_sign2 nistkat/PQCgenKAT_sign3 nistkat/PQCgenKAT_sign5
+mkdir -p ml-dsa
+for mode in 2 3 5; do
+    (cd ml-dsa && "$work/ml-dsa/ref/nistkat/PQCgenKAT_sign$mode")
+done
+
+# Round 3, specification v3.1
+git clone --quiet --depth 1 --branch v3.1 https://github.com/pq-crystals/dilithium "$work/round3"
+make -s -C "$work/round3/ref" nistkat/PQCgenKAT_sign2
+mkdir -p round3
+(cd round3 && "$work/round3/ref/nistkat/PQCgenKAT_sign2")
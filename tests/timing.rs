@@ -0,0 +1,109 @@
+//! dudect-style timing test of signing.
+//!
+//! Signing times under one fixed secret key are compared against times under
+//! fresh random secret keys with Welch's t-test. All keys share the fixed
+//! key's rho, so both classes expand the same matrix, and only signatures
+//! that took a single iteration of the rejection loop are kept, since the
+//! iteration count is revealed by design. A large |t| means the time depends
+//! on the secret key.
+//!
+//! The test takes minutes and needs a release build, so it is ignored by
+//! default. The timing workflow in CI runs it with:
+//!
+//! ```text
+//! cargo test --release --test timing -- --ignored --nocapture
+//! ```
+
+use std::time::Instant;
+
+use dilithium_lib::{with_sign_stats, Dilithium2};
+use rand::prelude::*;
+
+/// Measurements per class after filtering.
+const SAMPLES: usize = 20_000;
+/// dudect reports a leak from |t| > 10 on.
+const T_THRESHOLD: f64 = 10.0;
+
+/// Running mean and variance (Welford).
+#[derive(Default)]
+struct Stats {
+    n: f64,
+    mean: f64,
+    m2: f64,
+}
+
+impl Stats {
+    fn push(&mut self, x: f64) {
+        self.n += 1.0;
+        let delta = x - self.mean;
+        self.mean += delta / self.n;
+        self.m2 += delta * (x - self.mean);
+    }
+
+    fn variance(&self) -> f64 {
+        self.m2 / (self.n - 1.0)
+    }
+}
+
+fn welch_t(a: &Stats, b: &Stats) -> f64 {
+    (a.mean - b.mean) / (a.variance() / a.n + b.variance() / b.n).sqrt()
+}
+
+/// Time one deterministic signature, or None if it needed more than one
+/// iteration.
+fn measure(m: &[u8], sk: &[u8]) -> Option<f64> {
+    let rnd = vec![0u8; 32];
+    let start = Instant::now();
+    let (sig, stats) = with_sign_stats(|| Dilithium2::sign_internal(m.to_vec(), sk.to_vec(), rnd));
+    let elapsed = start.elapsed().as_nanos() as f64;
+    sig.unwrap();
+    (stats.iterations == 1).then_some(elapsed)
+}
+
+#[test]
+#[ignore]
+fn signing_time_is_independent_of_the_secret_key() {
+    let mut rng = rand::rng();
+    let (fixed, _) = Dilithium2::key_gen(None).unwrap();
+    let random_key = |rng: &mut rand::rngs::ThreadRng| {
+        let mut seed = vec![0u8; 32];
+        rng.fill_bytes(&mut seed);
+        let (mut sk, _) = Dilithium2::key_gen(Some(seed)).unwrap();
+        sk[..32].copy_from_slice(&fixed[..32]);
+        sk
+    };
+
+    // Warm up caches and the branch predictor before measuring.
+    for _ in 0..200 {
+        let _ = measure(&[0u8; 32], &fixed);
+    }
+
+    let mut raw: [Vec<f64>; 2] = [Vec::new(), Vec::new()];
+    while raw.iter().any(|r| r.len() < SAMPLES) {
+        let class = rng.random::<bool>() as usize;
+        let sk = if class == 0 { fixed.clone() } else { random_key(&mut rng) };
+        let mut m = [0u8; 32];
+        rng.fill_bytes(&mut m);
+        if let Some(t) = measure(&m, &sk) {
+            raw[class].push(t);
+        }
+    }
+
+    // Crop the slowest tenth of both classes, which is mostly interrupts and
+    // scheduling noise.
+    let mut all: Vec<f64> = raw.iter().flatten().copied().collect();
+    all.sort_by(f64::total_cmp);
+    let cutoff = all[all.len() * 9 / 10];
+    let [fixed_stats, random_stats] = raw.map(|r| {
+        let mut s = Stats::default();
+        r.into_iter().filter(|&t| t < cutoff).for_each(|t| s.push(t));
+        s
+    });
+
+    let t = welch_t(&fixed_stats, &random_stats);
+    println!(
+        "fixed: {:.0} ns, random: {:.0} ns, t = {:.2}",
+        fixed_stats.mean, random_stats.mean, t
+    );
+    assert!(t.abs() < T_THRESHOLD, "signing time depends on the secret key: t = {t:.2}");
+}